@@ -14,13 +14,16 @@ fn main() {
                 AudioSessionState, AudioSessionDisconnectReason, IAudioSessionControl2,
                 IAudioSessionEnumerator, ISimpleAudioVolume, ENDPOINT_HARDWARE_SUPPORT_METER,
                 ENDPOINT_HARDWARE_SUPPORT_MUTE, ENDPOINT_HARDWARE_SUPPORT_VOLUME,
-                IAudioVolumeDuckNotification,
+                IAudioVolumeDuckNotification, EndpointFormFactor,
             },
-            Storage::StructuredStorage::{STGM_READ, STGM_READWRITE, STGM_WRITE},
+            Storage::StructuredStorage::{PropVariantClear, STGM_READ, STGM_READWRITE, STGM_WRITE},
             System::{
-                Com::{CoInitializeEx, CoCreateInstance, CoTaskMemFree, CLSCTX},
+                Com::{
+                    CoInitializeEx, CoUninitialize, CoCreateInstance, CoTaskMemAlloc, CoTaskMemFree,
+                    CLSCTX,
+                },
                 OleAutomation::VARENUM,
-                PropertiesSystem::{IPropertyStore, PropVariantToStringAlloc},
+                PropertiesSystem::IPropertyStore,
                 SystemServices::{
                     DEVPKEY_DeviceInterface_FriendlyName, DEVPKEY_Device_DeviceDesc,
                     DEVPKEY_Device_FriendlyName,
@@ -1,5 +1,10 @@
+use std::sync::mpsc::{self, Receiver};
+
 use windows::Guid;
 
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+
 use crate::{
     audio_endpoint_volume_callback::{
         AudioEndpointVolumeCallback, AudioEndpointVolumeCallbackWrapper,
@@ -9,9 +14,14 @@ use crate::{
     },
     bits::HardwareSupportMask,
     device::Activate,
+    error::{Context, Result},
+    events::{self, VolumeEvent},
     util::as_raw_or_null,
 };
 
+#[cfg(feature = "stream")]
+use crate::stream::{self, Backpressure};
+
 /// See also: [`IAudioEndpointVolume`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nn-endpointvolume-iaudioendpointvolume)
 #[derive(Debug, Clone)]
 pub struct AudioEndpointVolume {
@@ -28,37 +38,41 @@ impl Activate for AudioEndpointVolume {
 
 impl AudioEndpointVolume {
     /// See also: [`IAudioEndpointVolume::GetChannelCount`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelcount)
-    pub fn get_channel_count(&self) -> windows::Result<u32> {
-        unsafe { self.inner.GetChannelCount() }
+    pub fn get_channel_count(&self) -> Result<u32> {
+        unsafe { self.inner.GetChannelCount() }.context("IAudioEndpointVolume::GetChannelCount")
     }
 
     /// See also: [`IAudioEndpointVolume::GetChannelVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelvolumelevel)
-    pub fn get_channel_volume_level(&self, channel: u32) -> windows::Result<f32> {
+    pub fn get_channel_volume_level(&self, channel: u32) -> Result<f32> {
         unsafe { self.inner.GetChannelVolumeLevel(channel) }
+            .context("IAudioEndpointVolume::GetChannelVolumeLevel")
     }
 
     /// See also: [`IAudioEndpointVolume::GetChannelVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelvolumelevelscalar)
-    pub fn get_channel_volume_level_scalar(&self, channel: u32) -> windows::Result<f32> {
+    pub fn get_channel_volume_level_scalar(&self, channel: u32) -> Result<f32> {
         unsafe { self.inner.GetChannelVolumeLevelScalar(channel) }
+            .context("IAudioEndpointVolume::GetChannelVolumeLevelScalar")
     }
 
     /// See also: [`IAudioEndpointVolume::GetMasterVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmastervolumelevel)
-    pub fn get_master_volume_level(&self) -> windows::Result<f32> {
+    pub fn get_master_volume_level(&self) -> Result<f32> {
         unsafe { self.inner.GetMasterVolumeLevel() }
+            .context("IAudioEndpointVolume::GetMasterVolumeLevel")
     }
 
     /// See also: [`IAudioEndpointVolume::GetMasterVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmastervolumelevelscalar)
-    pub fn get_master_volume_level_scalar(&self) -> windows::Result<f32> {
+    pub fn get_master_volume_level_scalar(&self) -> Result<f32> {
         unsafe { self.inner.GetMasterVolumeLevelScalar() }
+            .context("IAudioEndpointVolume::GetMasterVolumeLevelScalar")
     }
 
     /// See also: [`IAudioEndpointVolume::GetMute`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmute)
-    pub fn get_mute(&self) -> windows::Result<bool> {
-        unsafe { self.inner.GetMute().map(Into::into) }
+    pub fn get_mute(&self) -> Result<bool> {
+        unsafe { self.inner.GetMute().map(Into::into) }.context("IAudioEndpointVolume::GetMute")
     }
 
     /// See also: [`IAudioEndpointVolume::GetVolumeRange`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getvolumerange)
-    pub fn get_volume_range(&self) -> windows::Result<VolumeRange> {
+    pub fn get_volume_range(&self) -> Result<VolumeRange> {
         let mut volume_range = VolumeRange {
             min_db: 0.0,
             max_db: 0.0,
@@ -69,13 +83,14 @@ impl AudioEndpointVolume {
                 &mut volume_range.min_db,
                 &mut volume_range.max_db,
                 &mut volume_range.increment_db,
-            )?
-        };
+            )
+        }
+        .context("IAudioEndpointVolume::GetVolumeRange")?;
         Ok(volume_range)
     }
 
     /// See also: [`IAudioEndpointVolume::GetVolumeStepInfo`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getvolumestepinfo)
-    pub fn get_volume_step_info(&self) -> windows::Result<VolumeStepInfo> {
+    pub fn get_volume_step_info(&self) -> Result<VolumeStepInfo> {
         let mut volume_step_info = VolumeStepInfo {
             current_step: 0,
             num_steps: 0,
@@ -84,14 +99,16 @@ impl AudioEndpointVolume {
             self.inner.GetVolumeStepInfo(
                 &mut volume_step_info.current_step,
                 &mut volume_step_info.num_steps,
-            )?
-        };
+            )
+        }
+        .context("IAudioEndpointVolume::GetVolumeStepInfo")?;
         Ok(volume_step_info)
     }
 
     /// See also: [`IAudioEndpointVolume::QueryHardwareSupport`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-queryhardwaresupport)
-    pub fn query_hardware_support(&self) -> windows::Result<HardwareSupportMask> {
-        let raw = unsafe { self.inner.QueryHardwareSupport()? };
+    pub fn query_hardware_support(&self) -> Result<HardwareSupportMask> {
+        let raw = unsafe { self.inner.QueryHardwareSupport() }
+            .context("IAudioEndpointVolume::QueryHardwareSupport")?;
         Ok(HardwareSupportMask::from_bits(raw).expect("invalid mask"))
     }
 
@@ -99,30 +116,55 @@ impl AudioEndpointVolume {
     pub fn register_control_change_notify<T>(
         &self,
         callback: T,
-    ) -> windows::Result<AudioEndpointVolumeCallbackHandle>
+    ) -> Result<AudioEndpointVolumeCallbackHandle>
     where
         T: AudioEndpointVolumeCallback,
     {
         let callback =
             IAudioEndpointVolumeCallback::from(AudioEndpointVolumeCallbackWrapper::new(callback));
-        unsafe { self.inner.RegisterControlChangeNotify(&callback).unwrap() };
+        unsafe { self.inner.RegisterControlChangeNotify(&callback) }
+            .context("IAudioEndpointVolume::RegisterControlChangeNotify")?;
         Ok(AudioEndpointVolumeCallbackHandle {
             inner: callback,
             parent: self.inner.clone(),
         })
     }
 
+    /// Like [`register_control_change_notify`](Self::register_control_change_notify),
+    /// but sends the notifications to a channel.
+    pub fn register_control_change_notify_channel(
+        &self,
+    ) -> Result<(AudioEndpointVolumeCallbackHandle, Receiver<VolumeEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_control_change_notify(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// Like [`register_control_change_notify`](Self::register_control_change_notify), but
+    /// returns the notifications as a [`Stream`](futures_core::Stream). Dropping
+    /// the stream unregisters the callback.
+    #[cfg(feature = "stream")]
+    pub fn register_control_change_notify_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<impl Stream<Item = VolumeEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_control_change_notify(callback)
+        })
+    }
+
     /// See also: [`IAudioEndpointVolume::SetChannelVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setchannelvolumelevel)
     pub fn set_channel_volume_level(
         &self,
         channel: u32,
         level_db: f32,
         event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    ) -> Result<()> {
         unsafe {
             self.inner
                 .SetChannelVolumeLevel(channel, level_db, as_raw_or_null(event_context))
         }
+        .context("IAudioEndpointVolume::SetChannelVolumeLevel")
     }
 
     /// See also: [`IAudioEndpointVolume::SetChannelVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setchannelvolumelevelscalar)
@@ -131,11 +173,12 @@ impl AudioEndpointVolume {
         channel: u32,
         level: f32,
         event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    ) -> Result<()> {
         unsafe {
             self.inner
                 .SetChannelVolumeLevelScalar(channel, level, as_raw_or_null(event_context))
         }
+        .context("IAudioEndpointVolume::SetChannelVolumeLevelScalar")
     }
 
     /// See also: [`IAudioEndpointVolume::SetMasterVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmastervolumelevel)
@@ -143,11 +186,12 @@ impl AudioEndpointVolume {
         &self,
         level_db: f32,
         event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    ) -> Result<()> {
         unsafe {
             self.inner
                 .SetMasterVolumeLevel(level_db, as_raw_or_null(event_context))
         }
+        .context("IAudioEndpointVolume::SetMasterVolumeLevel")
     }
 
     /// See also: [`IAudioEndpointVolume::SetMasterVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmastervolumelevelscalar)
@@ -155,15 +199,16 @@ impl AudioEndpointVolume {
         &self,
         level: f32,
         event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    ) -> Result<()> {
         unsafe {
             self.inner
                 .SetMasterVolumeLevelScalar(level, as_raw_or_null(event_context))
         }
+        .context("IAudioEndpointVolume::SetMasterVolumeLevelScalar")
     }
 
     /// See also: [`IAudioEndpointVolume::SetMute`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmute)
-    pub fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> windows::Result<()> {
+    pub fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
         unsafe {
             self.inner.SetMute(
                 mute,
@@ -172,16 +217,19 @@ impl AudioEndpointVolume {
                     .unwrap_or(std::ptr::null()),
             )
         }
+        .context("IAudioEndpointVolume::SetMute")
     }
 
     /// See also: [`IAudioEndpointVolume::VolumeStepDown`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-volumestepdown)
-    pub fn volume_step_down(&self, event_context: Option<&Guid>) -> windows::Result<()> {
+    pub fn volume_step_down(&self, event_context: Option<&Guid>) -> Result<()> {
         unsafe { self.inner.VolumeStepDown(as_raw_or_null(event_context)) }
+            .context("IAudioEndpointVolume::VolumeStepDown")
     }
 
     /// See also: [`IAudioEndpointVolume::VolumeStepUp`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-volumestepup)
-    pub fn volume_step_up(&self, event_context: Option<&Guid>) -> windows::Result<()> {
+    pub fn volume_step_up(&self, event_context: Option<&Guid>) -> Result<()> {
         unsafe { self.inner.VolumeStepUp(as_raw_or_null(event_context)) }
+            .context("IAudioEndpointVolume::VolumeStepUp")
     }
 }
 
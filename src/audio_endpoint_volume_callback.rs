@@ -14,6 +14,29 @@ pub trait AudioEndpointVolumeCallback: 'static {
     }
 }
 
+/// Closures can be registered directly:
+///
+/// ```no_run
+/// use win32_coreaudio::{DataFlow, DeviceEnumerator, DeviceRole, NotificationData};
+///
+/// let volume = DeviceEnumerator::new()?
+///     .get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console)?
+///     .activate_audio_endpoint_volume()?;
+/// let _handle = volume.register_control_change_notify(|data: &NotificationData| {
+///     println!("volume is now {}", data.master_volume);
+/// })?;
+/// # Ok::<(), win32_coreaudio::Error>(())
+/// ```
+impl<F> AudioEndpointVolumeCallback for F
+where
+    F: FnMut(&NotificationData) + 'static,
+{
+    fn on_notify(&mut self, data: &NotificationData) -> windows::Result<()> {
+        self(data);
+        Ok(())
+    }
+}
+
 #[non_exhaustive]
 pub struct NotificationData<'a> {
     pub event_context: Guid,
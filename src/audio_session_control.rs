@@ -1,6 +1,10 @@
 use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver};
 
-use windows::{Guid, Interface};
+use windows::{Abi, Guid, Interface};
+
+#[cfg(feature = "stream")]
+use futures_core::Stream;
 
 use crate::{
     audio_session_events::{AudioSessionEvents, AudioSessionEventsWrapper},
@@ -8,11 +12,16 @@ use crate::{
         IAudioSessionControl, IAudioSessionControl2, IAudioSessionEvents,
     },
     bits::AudioSessionState,
+    error::{Context, Error, Result, AUDCLNT_S_NO_SINGLE_PROCESS},
+    events::{self, SessionEvent},
     string::{WinStr, WinString},
     util::as_raw_or_null,
     SimpleAudioVolume,
 };
 
+#[cfg(feature = "stream")]
+use crate::stream::{self, Backpressure};
+
 /// See also: [`IAudioSessionControl`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessioncontrol)
 #[derive(Debug, Clone)]
 pub struct AudioSessionControl {
@@ -25,38 +34,41 @@ impl AudioSessionControl {
     }
 
     /// See also: [`IAudioSessionControl::GetDisplayName`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getdisplayname)
-    pub fn get_display_name(&self) -> windows::Result<WinString> {
+    pub fn get_display_name(&self) -> Result<WinString> {
         unsafe {
             self.inner
                 .GetDisplayName()
                 .map(|x| WinString::from_com_pwstr(x))
         }
+        .context("IAudioSessionControl::GetDisplayName")
     }
 
     /// See also: [`IAudioSessionControl::GetGroupingParam`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getgroupingparam)
-    pub fn get_grouping_param(&self) -> windows::Result<Guid> {
-        unsafe { self.inner.GetGroupingParam() }
+    pub fn get_grouping_param(&self) -> Result<Guid> {
+        unsafe { self.inner.GetGroupingParam() }.context("IAudioSessionControl::GetGroupingParam")
     }
 
     /// See also: [`IAudioSessionControl::GetIconPath`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-geticonpath)
-    pub fn get_icon_path(&self) -> windows::Result<WinString> {
+    pub fn get_icon_path(&self) -> Result<WinString> {
         unsafe {
             self.inner
                 .GetIconPath()
                 .map(|x| WinString::from_com_pwstr(x))
         }
+        .context("IAudioSessionControl::GetIconPath")
     }
 
     /// See also: [`IAudioSessionControl::GetState`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getstate)
-    pub fn get_state(&self) -> windows::Result<AudioSessionState> {
-        unsafe { self.inner.GetState().map(AudioSessionState::from_raw) }
+    pub fn get_state(&self) -> Result<AudioSessionState> {
+        unsafe { self.inner.GetState().map(|x| AudioSessionState::from(x.0)) }
+            .context("IAudioSessionControl::GetState")
     }
 
     /// See also: [`IAudioSessionControl::RegisterAudioSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-registeraudiosessionnotification)
     pub fn register_audio_session_notification<T>(
         &self,
         session_notification: T,
-    ) -> windows::Result<AudioSessionEventsHandle>
+    ) -> Result<AudioSessionEventsHandle>
     where
         T: AudioSessionEvents,
     {
@@ -64,56 +76,77 @@ impl AudioSessionControl {
             IAudioSessionEvents::from(AudioSessionEventsWrapper::new(session_notification));
         unsafe {
             self.inner
-                .RegisterAudioSessionNotification(&session_notification)?
-        };
+                .RegisterAudioSessionNotification(&session_notification)
+        }
+        .context("IAudioSessionControl::RegisterAudioSessionNotification")?;
         Ok(AudioSessionEventsHandle {
             inner: session_notification,
             parent: self.inner.clone(),
         })
     }
 
-    /// See also: [`IAudioSessionControl::SetDisplayName`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-setdisplayname)
-    pub fn set_display_name(
+    /// Like [`register_audio_session_notification`](Self::register_audio_session_notification),
+    /// but sends the notifications to a channel.
+    pub fn register_audio_session_notification_channel(
+        &self,
+    ) -> Result<(AudioSessionEventsHandle, Receiver<SessionEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_audio_session_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// Like [`register_audio_session_notification`](Self::register_audio_session_notification), but
+    /// returns the notifications as a [`Stream`](futures_core::Stream). Dropping
+    /// the stream unregisters the callback.
+    #[cfg(feature = "stream")]
+    pub fn register_audio_session_notification_stream(
         &self,
-        value: &WinStr,
-        event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+        backpressure: Backpressure,
+    ) -> Result<impl Stream<Item = SessionEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_audio_session_notification(callback)
+        })
+    }
+
+    /// See also: [`IAudioSessionControl::SetDisplayName`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-setdisplayname)
+    pub fn set_display_name(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
         unsafe {
             self.inner
                 .SetDisplayName(value.as_pwstr(), as_raw_or_null(event_context))
         }
+        .context("IAudioSessionControl::SetDisplayName")
     }
 
     /// See also: [`IAudioSessionControl::SetGroupingParam`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-setgroupingparam)
-    pub fn set_grouping_param(
-        &self,
-        value: &Guid,
-        event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    pub fn set_grouping_param(&self, value: &Guid, event_context: Option<&Guid>) -> Result<()> {
         unsafe {
             self.inner
                 .SetGroupingParam(value, as_raw_or_null(event_context))
         }
+        .context("IAudioSessionControl::SetGroupingParam")
     }
 
     /// See also: [`IAudioSessionControl::SetIconPath`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-seticonpath)
-    pub fn set_icon_path(
-        &self,
-        value: &WinStr,
-        event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    pub fn set_icon_path(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
         unsafe {
             self.inner
                 .SetIconPath(value.as_pwstr(), as_raw_or_null(event_context))
         }
+        .context("IAudioSessionControl::SetIconPath")
     }
 
-    pub fn upgrade(&self) -> windows::Result<AudioSessionControl2> {
-        self.inner.cast().map(AudioSessionControl2::new)
+    pub fn upgrade(&self) -> Result<AudioSessionControl2> {
+        self.inner
+            .cast()
+            .map(AudioSessionControl2::new)
+            .context("IUnknown::QueryInterface")
     }
 
-    pub fn get_simple_audio_volume(&self) -> windows::Result<SimpleAudioVolume> {
-        self.inner.cast().map(SimpleAudioVolume::new)
+    pub fn get_simple_audio_volume(&self) -> Result<SimpleAudioVolume> {
+        self.inner
+            .cast()
+            .map(SimpleAudioVolume::new)
+            .context("IUnknown::QueryInterface")
     }
 }
 
@@ -131,26 +164,41 @@ impl AudioSessionControl2 {
     }
 
     /// See also: [`IAudioSessionControl2::GetProcessId`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getprocessid)
-    pub fn get_process_id(&self) -> windows::Result<u32> {
-        unsafe { self.inner.GetProcessId() }
+    ///
+    /// If the session spans more than one process, this returns an error of
+    /// kind [`ErrorKind::NoSingleProcess`](crate::ErrorKind::NoSingleProcess).
+    pub fn get_process_id(&self) -> Result<u32> {
+        const OPERATION: &str = "IAudioSessionControl2::GetProcessId";
+        let mut process_id = 0;
+        // `GetProcessId` signals a multi-process session with a success code,
+        // which the generated binding discards, so call through the vtable.
+        let code =
+            unsafe { (Interface::vtable(&self.inner).14)(Abi::abi(&self.inner), &mut process_id) };
+        if code.0 == AUDCLNT_S_NO_SINGLE_PROCESS {
+            return Err(Error::from_code(OPERATION, code.0));
+        }
+        code.ok().context(OPERATION)?;
+        Ok(process_id)
     }
 
     /// See also: [`IAudioSessionControl2::GetSessionIdentifier`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getsessionidentifier)
-    pub fn get_session_identifier(&self) -> windows::Result<WinString> {
+    pub fn get_session_identifier(&self) -> Result<WinString> {
         unsafe {
             self.inner
                 .GetSessionIdentifier()
                 .map(|x| WinString::from_com_pwstr(x))
         }
+        .context("IAudioSessionControl2::GetSessionIdentifier")
     }
 
     /// See also: [`IAudioSessionControl2::GetSessionInstanceIdentifier`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getsessioninstanceidentifier)
-    pub fn get_session_instance_identifier(&self) -> windows::Result<WinString> {
+    pub fn get_session_instance_identifier(&self) -> Result<WinString> {
         unsafe {
             self.inner
                 .GetSessionInstanceIdentifier()
                 .map(|x| WinString::from_com_pwstr(x))
         }
+        .context("IAudioSessionControl2::GetSessionInstanceIdentifier")
     }
 
     /// See also: [`IAudioSessionControl2::IsSystemSoundsSession`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-issystemsoundssession)
@@ -159,8 +207,9 @@ impl AudioSessionControl2 {
     }
 
     /// See also: [`IAudioSessionControl2::SetDuckingPreference`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-setduckingpreference)
-    pub fn set_ducking_preference(&self, opt_out: bool) -> windows::Result<()> {
+    pub fn set_ducking_preference(&self, opt_out: bool) -> Result<()> {
         unsafe { self.inner.SetDuckingPreference(opt_out) }
+            .context("IAudioSessionControl2::SetDuckingPreference")
     }
 }
 
@@ -3,6 +3,7 @@ use std::{iter::FusedIterator, ops::Range};
 use crate::{
     audio_session_control::AudioSessionControl,
     bindings::Windows::Win32::Media::Audio::CoreAudio::IAudioSessionEnumerator,
+    error::{Context, Error, Result},
 };
 
 /// See also: [`IAudioSessionEnumerator`](https://docs.microsoft.com/en-us/windows/desktop/api/audiopolicy/nn-audiopolicy-iaudiosessionenumerator)
@@ -16,36 +17,65 @@ impl AudioSessionEnumerator {
         Self { inner }
     }
 
-    pub fn get_count(&self) -> windows::Result<i32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> Result<i32> {
+        unsafe { self.inner.GetCount() }.context("IAudioSessionEnumerator::GetCount")
     }
 
-    pub fn get_session(&self, session: i32) -> windows::Result<AudioSessionControl> {
+    pub fn get_session(&self, session: i32) -> Result<AudioSessionControl> {
         unsafe { self.inner.GetSession(session).map(AudioSessionControl::new) }
+            .context("IAudioSessionEnumerator::GetSession")
+    }
+
+    /// Returns an iterator that yields an error when a session cannot be
+    /// retrieved, e.g. because it was removed during enumeration.
+    /// Iterating over a `&AudioSessionEnumerator` does the same.
+    pub fn try_iter(&self) -> TryAudioSessionIter<'_> {
+        TryAudioSessionIter::new(self)
+    }
+
+    /// Returns an iterator that panics when a session cannot be retrieved.
+    ///
+    /// # Panics
+    ///
+    /// The iterator panics if the count or any session cannot be
+    /// retrieved. Use [`try_iter`](Self::try_iter) to handle these errors
+    /// instead.
+    pub fn unwrapping_iter(&self) -> AudioSessionIter<'_> {
+        AudioSessionIter::new(self)
+    }
+
+    /// Collects all sessions, returning the first error encountered.
+    pub fn to_vec(&self) -> Result<Vec<AudioSessionControl>> {
+        self.try_iter().collect()
     }
 }
 
 impl<'a> IntoIterator for &'a AudioSessionEnumerator {
-    type IntoIter = AudioSessionIter<'a>;
-    type Item = AudioSessionControl;
+    type IntoIter = TryAudioSessionIter<'a>;
+    type Item = Result<AudioSessionControl>;
 
     fn into_iter(self) -> Self::IntoIter {
-        AudioSessionIter::new(self)
+        TryAudioSessionIter::new(self)
     }
 }
 
+/// An iterator over the sessions in a [`AudioSessionEnumerator`].
+///
+/// # Panics
+///
+/// Panics if the count or any session cannot be retrieved. Use
+/// [`AudioSessionEnumerator::try_iter`] to handle these errors instead.
+///
+/// See also: [`AudioSessionEnumerator::unwrapping_iter`]
 #[derive(Debug, Clone)]
 pub struct AudioSessionIter<'a> {
-    inner: &'a AudioSessionEnumerator,
-    range: Range<i32>,
+    inner: TryAudioSessionIter<'a>,
 }
 
 impl<'a> AudioSessionIter<'a> {
     pub(crate) fn new(inner: &'a AudioSessionEnumerator) -> Self {
-        let count = inner.get_count().unwrap();
         Self {
-            inner,
-            range: 0..count,
+            inner: TryAudioSessionIter::new(inner),
         }
     }
 }
@@ -54,16 +84,63 @@ impl<'a> Iterator for AudioSessionIter<'a> {
     type Item = AudioSessionControl;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.range
-            .next()
-            .map(|index| self.inner.get_session(index).unwrap())
+        self.inner.next().map(|result| result.unwrap())
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.range.len();
-        (len, Some(len))
+        self.inner.size_hint()
     }
 }
 
 impl<'a> ExactSizeIterator for AudioSessionIter<'a> {}
 impl<'a> FusedIterator for AudioSessionIter<'a> {}
+
+/// A fallible iterator over the sessions in a [`AudioSessionEnumerator`].
+///
+/// If the number of sessions cannot be retrieved, the error is yielded as
+/// the only item.
+#[derive(Debug, Clone)]
+pub struct TryAudioSessionIter<'a> {
+    inner: &'a AudioSessionEnumerator,
+    range: Range<i32>,
+    error: Option<Error>,
+}
+
+impl<'a> TryAudioSessionIter<'a> {
+    pub(crate) fn new(inner: &'a AudioSessionEnumerator) -> Self {
+        let (count, error) = match inner.get_count() {
+            Ok(count) => (count, None),
+            Err(error) => (0, Some(error)),
+        };
+        Self {
+            inner,
+            range: 0..count,
+            error,
+        }
+    }
+
+    /// Skips any sessions that cannot be retrieved, instead of yielding
+    /// errors.
+    pub fn skip_errors(self) -> impl Iterator<Item = AudioSessionControl> + 'a {
+        self.filter_map(Result::ok)
+    }
+}
+
+impl<'a> Iterator for TryAudioSessionIter<'a> {
+    type Item = Result<AudioSessionControl>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        self.range.next().map(|index| self.inner.get_session(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.range.len() + self.error.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for TryAudioSessionIter<'a> {}
+impl<'a> FusedIterator for TryAudioSessionIter<'a> {}
@@ -1,4 +1,5 @@
 use std::convert::TryInto;
+use std::fmt::{self, Debug, Formatter};
 
 use windows::Guid;
 
@@ -13,6 +14,7 @@ use crate::{
     },
     bits::{AudioSessionDisconnectReason, AudioSessionState},
     string::WinStr,
+    util::Handler,
 };
 
 /// See also: [`IAudioSessionEvents`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessionevents)
@@ -85,6 +87,193 @@ pub trait AudioSessionEvents: 'static {
     }
 }
 
+type ChannelVolumeChangedFn = dyn FnMut(&[f32], usize, Option<&Guid>);
+type StringChangedFn = dyn FnMut(&WinStr, Option<&Guid>);
+type GroupingParamChangedFn = dyn FnMut(Option<&Guid>, Option<&Guid>);
+type SessionDisconnectedFn = dyn FnMut(AudioSessionDisconnectReason);
+type SimpleVolumeChangedFn = dyn FnMut(f32, bool, Option<&Guid>);
+type StateChangedFn = dyn FnMut(AudioSessionState);
+
+/// An [`AudioSessionEvents`] made of a closure for each event.
+///
+/// Events without a closure are ignored.
+///
+/// ```no_run
+/// use win32_coreaudio::{AudioSessionEventsBuilder, DeviceEnumerator, DataFlow, DeviceRole};
+///
+/// let device = DeviceEnumerator::new()?.get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console)?;
+/// for session in &device.activate_audio_session_manager2()?.get_session_enumerator()? {
+///     let _handle = session?.register_audio_session_notification(
+///         AudioSessionEventsBuilder::new()
+///             .on_state_changed(|state| println!("session is now {}", state))
+///             .on_simple_volume_changed(|volume, mute, _| println!("{} {}", volume, mute)),
+///     )?;
+/// }
+/// # Ok::<(), win32_coreaudio::Error>(())
+/// ```
+#[derive(Default)]
+pub struct AudioSessionEventsBuilder {
+    channel_volume_changed: Handler<ChannelVolumeChangedFn>,
+    display_name_changed: Handler<StringChangedFn>,
+    grouping_param_changed: Handler<GroupingParamChangedFn>,
+    icon_path_changed: Handler<StringChangedFn>,
+    session_disconnected: Handler<SessionDisconnectedFn>,
+    simple_volume_changed: Handler<SimpleVolumeChangedFn>,
+    state_changed: Handler<StateChangedFn>,
+}
+
+impl AudioSessionEventsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See also: [`AudioSessionEvents::on_channel_volume_changed`]
+    pub fn on_channel_volume_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&[f32], usize, Option<&Guid>) + 'static,
+    {
+        self.channel_volume_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_display_name_changed`]
+    pub fn on_display_name_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr, Option<&Guid>) + 'static,
+    {
+        self.display_name_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_grouping_param_changed`]
+    pub fn on_grouping_param_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(Option<&Guid>, Option<&Guid>) + 'static,
+    {
+        self.grouping_param_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_icon_path_changed`]
+    pub fn on_icon_path_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr, Option<&Guid>) + 'static,
+    {
+        self.icon_path_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_session_disconnected`]
+    pub fn on_session_disconnected<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(AudioSessionDisconnectReason) + 'static,
+    {
+        self.session_disconnected = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_simple_volume_changed`]
+    pub fn on_simple_volume_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(f32, bool, Option<&Guid>) + 'static,
+    {
+        self.simple_volume_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioSessionEvents::on_state_changed`]
+    pub fn on_state_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(AudioSessionState) + 'static,
+    {
+        self.state_changed = Some(Box::new(f));
+        self
+    }
+}
+
+impl AudioSessionEvents for AudioSessionEventsBuilder {
+    fn on_channel_volume_changed(
+        &mut self,
+        new_volume_levels: &[f32],
+        changed_channel: usize,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.channel_volume_changed {
+            f(new_volume_levels, changed_channel, event_context);
+        }
+        Ok(())
+    }
+
+    fn on_display_name_changed(
+        &mut self,
+        new_display_name: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.display_name_changed {
+            f(new_display_name, event_context);
+        }
+        Ok(())
+    }
+
+    fn on_grouping_param_changed(
+        &mut self,
+        new_grouping_param: Option<&Guid>,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.grouping_param_changed {
+            f(new_grouping_param, event_context);
+        }
+        Ok(())
+    }
+
+    fn on_icon_path_changed(
+        &mut self,
+        new_icon_path: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.icon_path_changed {
+            f(new_icon_path, event_context);
+        }
+        Ok(())
+    }
+
+    fn on_session_disconnected(
+        &mut self,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.session_disconnected {
+            f(disconnect_reason);
+        }
+        Ok(())
+    }
+
+    fn on_simple_volume_changed(
+        &mut self,
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.simple_volume_changed {
+            f(new_volume, new_mute, event_context);
+        }
+        Ok(())
+    }
+
+    fn on_state_changed(&mut self, new_state: AudioSessionState) -> windows::Result<()> {
+        if let Some(f) = &mut self.state_changed {
+            f(new_state);
+        }
+        Ok(())
+    }
+}
+
+impl Debug for AudioSessionEventsBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AudioSessionEventsBuilder")
+            .finish_non_exhaustive()
+    }
+}
+
 #[windows::implement(Windows::Win32::Media::Audio::CoreAudio::IAudioSessionEvents)]
 pub(crate) struct AudioSessionEventsWrapper {
     inner: Box<dyn AudioSessionEvents>,
@@ -161,7 +350,7 @@ impl AudioSessionEventsWrapper {
         disconnect_reason: EAudioSessionDisconnectReason,
     ) -> windows::Result<()> {
         self.inner
-            .on_session_disconnected(AudioSessionDisconnectReason::from_raw(disconnect_reason))
+            .on_session_disconnected(AudioSessionDisconnectReason::from(disconnect_reason.0))
     }
 
     fn OnSimpleVolumeChanged(
@@ -178,6 +367,6 @@ impl AudioSessionEventsWrapper {
 
     fn OnStateChanged(&mut self, new_state: EAudioSessionState) -> windows::Result<()> {
         self.inner
-            .on_state_changed(AudioSessionState::from_raw(new_state))
+            .on_state_changed(AudioSessionState::from(new_state.0))
     }
 }
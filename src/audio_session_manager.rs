@@ -1,7 +1,11 @@
 use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver};
 
 use windows::{Guid, Interface};
 
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+
 use crate::{
     audio_session_control::AudioSessionControl,
     audio_session_enumerator::AudioSessionEnumerator,
@@ -14,10 +18,15 @@ use crate::{
         IAudioVolumeDuckNotification,
     },
     device::Activate,
+    error::{Context, Result},
+    events::{self, DuckEvent, SessionCreated},
     simple_audio_volume::SimpleAudioVolume,
     string::WinStr,
 };
 
+#[cfg(feature = "stream")]
+use crate::stream::{self, Backpressure};
+
 /// See also: [`IAudioSessionManager`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessionmanager)
 #[derive(Debug, Clone)]
 pub struct AudioSessionManager {
@@ -37,28 +46,30 @@ impl AudioSessionManager {
     pub fn get_audio_session_control(
         &self,
         audio_session_guid: &Guid,
-    ) -> windows::Result<AudioSessionControl> {
+    ) -> Result<AudioSessionControl> {
         unsafe {
             self.inner
                 .GetAudioSessionControl(audio_session_guid, 0)
                 .map(AudioSessionControl::new)
         }
+        .context("IAudioSessionManager::GetAudioSessionControl")
     }
 
     /// See also: [`IAudioSessionManager::GetSimpleAudioVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager-getsimpleaudiovolume)
-    pub fn get_simple_audio_volume(
-        &self,
-        audio_session_guid: &Guid,
-    ) -> windows::Result<SimpleAudioVolume> {
+    pub fn get_simple_audio_volume(&self, audio_session_guid: &Guid) -> Result<SimpleAudioVolume> {
         unsafe {
             self.inner
                 .GetSimpleAudioVolume(audio_session_guid, 0)
                 .map(SimpleAudioVolume::new)
         }
+        .context("IAudioSessionManager::GetSimpleAudioVolume")
     }
 
-    pub fn upgrade(&self) -> windows::Result<AudioSessionManager2> {
-        self.inner.cast().map(AudioSessionManager2::from_raw)
+    pub fn upgrade(&self) -> Result<AudioSessionManager2> {
+        self.inner
+            .cast()
+            .map(AudioSessionManager2::from_raw)
+            .context("IUnknown::QueryInterface")
     }
 }
 
@@ -80,12 +91,13 @@ impl Activate for AudioSessionManager2 {
 
 impl AudioSessionManager2 {
     /// See also: [`IAudioSessionManager2::GetSessionEnumerator`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-getsessionenumerator)
-    pub fn get_session_enumerator(&self) -> windows::Result<AudioSessionEnumerator> {
+    pub fn get_session_enumerator(&self) -> Result<AudioSessionEnumerator> {
         unsafe {
             self.inner
                 .GetSessionEnumerator()
                 .map(AudioSessionEnumerator::new)
         }
+        .context("IAudioSessionManager2::GetSessionEnumerator")
     }
 
     /// See also: [`IAudioSessionManager2::RegisterDuckNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-registerducknotification)
@@ -93,7 +105,7 @@ impl AudioSessionManager2 {
         &self,
         session_id: &WinStr,
         duck_notification: T,
-    ) -> windows::Result<AudioVolumeDuckNotificationHandle>
+    ) -> Result<AudioVolumeDuckNotificationHandle>
     where
         T: AudioVolumeDuckNotification,
     {
@@ -102,19 +114,45 @@ impl AudioSessionManager2 {
         );
         unsafe {
             self.inner
-                .RegisterDuckNotification(session_id.as_pwstr(), &duck_notification)?
-        };
+                .RegisterDuckNotification(session_id.as_pwstr(), &duck_notification)
+        }
+        .context("IAudioSessionManager2::RegisterDuckNotification")?;
         Ok(AudioVolumeDuckNotificationHandle {
             inner: duck_notification,
             parent: self.inner.clone(),
         })
     }
 
+    /// Like [`register_duck_notification`](Self::register_duck_notification),
+    /// but sends the notifications to a channel.
+    pub fn register_duck_notification_channel(
+        &self,
+        session_id: &WinStr,
+    ) -> Result<(AudioVolumeDuckNotificationHandle, Receiver<DuckEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_duck_notification(session_id, events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// Like [`register_duck_notification`](Self::register_duck_notification), but
+    /// returns the notifications as a [`Stream`](futures_core::Stream). Dropping
+    /// the stream unregisters the callback.
+    #[cfg(feature = "stream")]
+    pub fn register_duck_notification_stream(
+        &self,
+        session_id: &WinStr,
+        backpressure: Backpressure,
+    ) -> Result<impl Stream<Item = DuckEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_duck_notification(session_id, callback)
+        })
+    }
+
     /// See also: [`IAudioSessionManager2::RegisterSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-registersessionnotification)
     pub fn register_session_notification<T>(
         &self,
         session_notification: T,
-    ) -> windows::Result<AudioSessionNotificationHandle>
+    ) -> Result<AudioSessionNotificationHandle>
     where
         T: AudioSessionNotification,
     {
@@ -123,13 +161,37 @@ impl AudioSessionManager2 {
         );
         unsafe {
             self.inner
-                .RegisterSessionNotification(&session_notification)?;
+                .RegisterSessionNotification(&session_notification)
         }
+        .context("IAudioSessionManager2::RegisterSessionNotification")?;
         Ok(AudioSessionNotificationHandle {
             inner: session_notification,
             parent: self.inner.clone(),
         })
     }
+
+    /// Like [`register_session_notification`](Self::register_session_notification),
+    /// but sends the notifications to a channel.
+    pub fn register_session_notification_channel(
+        &self,
+    ) -> Result<(AudioSessionNotificationHandle, Receiver<SessionCreated>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_session_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// Like [`register_session_notification`](Self::register_session_notification), but
+    /// returns the notifications as a [`Stream`](futures_core::Stream). Dropping
+    /// the stream unregisters the callback.
+    #[cfg(feature = "stream")]
+    pub fn register_session_notification_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<impl Stream<Item = SessionCreated>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_session_notification(callback)
+        })
+    }
 }
 
 impl Deref for AudioSessionManager2 {
@@ -11,6 +11,16 @@ pub trait AudioSessionNotification: 'static {
     }
 }
 
+impl<F> AudioSessionNotification for F
+where
+    F: FnMut(AudioSessionControl) + 'static,
+{
+    fn on_session_created(&mut self, new_session: AudioSessionControl) -> windows::Result<()> {
+        self(new_session);
+        Ok(())
+    }
+}
+
 #[windows::implement(Windows::Win32::Media::Audio::CoreAudio::IAudioSessionNotification)]
 pub(crate) struct AudioSessionNotificationWrapper {
     inner: Box<dyn AudioSessionNotification>,
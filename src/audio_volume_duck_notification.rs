@@ -1,7 +1,10 @@
+use std::fmt::{self, Debug, Formatter};
+
 use crate::bindings::Windows::Win32::Foundation::PWSTR;
 use crate::bindings::*;
 
 use crate::string::WinStr;
+use crate::util::Handler;
 
 /// See also: [`IAudioVolumeDuckNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiovolumeducknotification)
 pub trait AudioVolumeDuckNotification: 'static {
@@ -22,6 +25,70 @@ pub trait AudioVolumeDuckNotification: 'static {
     }
 }
 
+type VolumeDuckFn = dyn FnMut(&WinStr, u32);
+type VolumeUnduckFn = dyn FnMut(&WinStr);
+
+/// An [`AudioVolumeDuckNotification`] made of a closure for each
+/// notification.
+///
+/// Notifications without a closure are ignored.
+#[derive(Default)]
+pub struct AudioVolumeDuckNotificationBuilder {
+    volume_duck: Handler<VolumeDuckFn>,
+    volume_unduck: Handler<VolumeUnduckFn>,
+}
+
+impl AudioVolumeDuckNotificationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See also: [`AudioVolumeDuckNotification::on_volume_duck_notification`]
+    pub fn on_volume_duck_notification<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr, u32) + 'static,
+    {
+        self.volume_duck = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`AudioVolumeDuckNotification::on_volume_unduck_notification`]
+    pub fn on_volume_unduck_notification<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr) + 'static,
+    {
+        self.volume_unduck = Some(Box::new(f));
+        self
+    }
+}
+
+impl AudioVolumeDuckNotification for AudioVolumeDuckNotificationBuilder {
+    fn on_volume_duck_notification(
+        &mut self,
+        session_id: &WinStr,
+        num_communication_sessions: u32,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.volume_duck {
+            f(session_id, num_communication_sessions);
+        }
+        Ok(())
+    }
+
+    fn on_volume_unduck_notification(&mut self, session_id: &WinStr) -> windows::Result<()> {
+        if let Some(f) = &mut self.volume_unduck {
+            f(session_id);
+        }
+        Ok(())
+    }
+}
+
+impl Debug for AudioVolumeDuckNotificationBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AudioVolumeDuckNotificationBuilder")
+            .finish_non_exhaustive()
+    }
+}
+
 #[windows::implement(Windows::Win32::Media::Audio::CoreAudio::IAudioVolumeDuckNotification)]
 pub(crate) struct AudioVolumeDuckNotificationWrapper {
     inner: Box<dyn AudioVolumeDuckNotification>,
@@ -0,0 +1,514 @@
+//! Traits that abstract over the Core Audio wrappers in this crate.
+//!
+//! Application code that is generic over [`Backend`] can run against the
+//! real Windows audio stack through [`ComBackend`], or against the
+//! in-memory [`MockBackend`](crate::mock::MockBackend) (with the `mock`
+//! feature) in unit tests on any platform.
+//!
+//! The traits mirror the methods of the wrapper types, which implement them
+//! on Windows. Each method documents the wrapper method it corresponds to.
+
+use std::sync::mpsc::{self, Receiver};
+
+use windows::Guid;
+
+use crate::{
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::AudioEndpointVolumeCallback,
+    audio_session_events::AudioSessionEvents,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    bits::{
+        AudioSessionState, DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask,
+        HardwareSupportMask, StorageAccessMode,
+    },
+    error::Result,
+    events::{self, DeviceEvent, DuckEvent, SessionCreated, SessionEvent, VolumeEvent},
+    notification_client::NotificationClient,
+    property_snapshot::PropertySnapshot,
+    property_store::{Property, PropertyKey},
+    string::{WinStr, WinString},
+    typed_property::{FromProperty, IntoProperty, PropertyError, TypedPropertyKey},
+};
+
+#[cfg(windows)]
+pub use crate::com_backend::ComBackend;
+
+#[cfg(feature = "stream")]
+use crate::stream::{self, Backpressure, EventStream};
+
+/// An implementation of the Core Audio API.
+pub trait Backend {
+    type DeviceEnumerator: DeviceEnumeratorBackend;
+
+    /// Creates a device enumerator, the entry point to the API.
+    ///
+    /// See also: [`DeviceEnumerator::new`](crate::DeviceEnumerator::new)
+    fn device_enumerator(&self) -> Result<Self::DeviceEnumerator>;
+}
+
+/// See also: [`DeviceEnumerator`](crate::DeviceEnumerator)
+pub trait DeviceEnumeratorBackend {
+    type Device: DeviceBackend;
+    /// Unregisters the notification client when dropped.
+    type NotificationClientHandle;
+
+    /// See also: [`DeviceEnumerator::enum_audio_endpoints`](crate::DeviceEnumerator::enum_audio_endpoints)
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> Result<Vec<Self::Device>>;
+
+    /// See also: [`DeviceEnumerator::get_default_audio_endpoint`](crate::DeviceEnumerator::get_default_audio_endpoint)
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> Result<Self::Device>;
+
+    /// See also: [`DeviceEnumerator::get_device`](crate::DeviceEnumerator::get_device)
+    fn get_device(&self, device_id: &WinStr) -> Result<Self::Device>;
+
+    /// See also: [`DeviceEnumerator::register_endpoint_notification`](crate::DeviceEnumerator::register_endpoint_notification)
+    fn register_endpoint_notification<T>(
+        &self,
+        notification_client: T,
+    ) -> Result<Self::NotificationClientHandle>
+    where
+        T: NotificationClient;
+
+    /// See also: [`DeviceEnumerator::register_endpoint_notification_channel`](crate::DeviceEnumerator::register_endpoint_notification_channel)
+    fn register_endpoint_notification_channel(
+        &self,
+    ) -> Result<(Self::NotificationClientHandle, Receiver<DeviceEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_endpoint_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// See also: [`DeviceEnumerator::register_endpoint_notification_stream`](crate::DeviceEnumerator::register_endpoint_notification_stream)
+    #[cfg(feature = "stream")]
+    fn register_endpoint_notification_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<EventStream<Self::NotificationClientHandle, DeviceEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_endpoint_notification(callback)
+        })
+    }
+}
+
+/// See also: [`Device`](crate::Device)
+pub trait DeviceBackend {
+    type PropertyStore: PropertyStoreBackend;
+    type AudioEndpointVolume: AudioEndpointVolumeBackend;
+    type AudioSessionManager: AudioSessionManagerBackend;
+
+    /// See also: [`Device::get_id`](crate::Device::get_id)
+    fn get_id(&self) -> Result<WinString>;
+
+    /// See also: [`Device::get_state`](crate::Device::get_state)
+    fn get_state(&self) -> Result<DeviceState>;
+
+    /// See also: [`Device::open_property_store`](crate::Device::open_property_store)
+    fn open_property_store(
+        &self,
+        storage_access_mode: StorageAccessMode,
+    ) -> Result<Self::PropertyStore>;
+
+    /// See also: [`Device::activate_audio_endpoint_volume`](crate::Device::activate_audio_endpoint_volume)
+    fn activate_audio_endpoint_volume(&self) -> Result<Self::AudioEndpointVolume>;
+
+    /// See also: [`Device::activate_audio_session_manager2`](crate::Device::activate_audio_session_manager2)
+    fn activate_audio_session_manager2(&self) -> Result<Self::AudioSessionManager>;
+}
+
+/// See also: [`PropertyStore`](crate::PropertyStore)
+pub trait PropertyStoreBackend {
+    /// See also: [`PropertyStore::commit`](crate::PropertyStore::commit)
+    fn commit(&self) -> Result<()>;
+
+    /// See also: [`PropertyStore::get_at`](crate::PropertyStore::get_at)
+    fn get_at(&self, index: u32) -> Result<PropertyKey>;
+
+    /// See also: [`PropertyStore::get_count`](crate::PropertyStore::get_count)
+    fn get_count(&self) -> Result<u32>;
+
+    /// See also: [`PropertyStore::get_value`](crate::PropertyStore::get_value)
+    fn get_value(&self, key: &PropertyKey) -> Result<Property>;
+
+    /// See also: [`PropertyStore::set_value`](crate::PropertyStore::set_value)
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> Result<()>;
+
+    /// See also: [`PropertyStore::get`](crate::PropertyStore::get)
+    fn get<T>(&self, key: &TypedPropertyKey<T>) -> Result<Option<T>, PropertyError>
+    where
+        T: FromProperty,
+    {
+        key.decode(self.get_value(key)?)
+    }
+
+    /// See also: [`PropertyStore::set`](crate::PropertyStore::set)
+    fn set<T>(&self, key: &TypedPropertyKey<T>, value: T) -> Result<()>
+    where
+        T: IntoProperty,
+    {
+        self.set_value(key, &value.into_property())
+    }
+
+    /// See also: [`PropertyStore::snapshot`](crate::PropertyStore::snapshot)
+    fn snapshot(&self) -> Result<PropertySnapshot> {
+        Ok((0..self.get_count()?)
+            .filter_map(|index| {
+                let key = self.get_at(index).ok()?;
+                Some((key, self.get_value(&key).ok()?))
+            })
+            .collect())
+    }
+}
+
+/// See also: [`AudioEndpointVolume`](crate::AudioEndpointVolume)
+pub trait AudioEndpointVolumeBackend {
+    /// Unregisters the callback when dropped.
+    type CallbackHandle;
+
+    /// See also: [`AudioEndpointVolume::get_channel_count`](crate::AudioEndpointVolume::get_channel_count)
+    fn get_channel_count(&self) -> Result<u32>;
+
+    /// See also: [`AudioEndpointVolume::get_channel_volume_level`](crate::AudioEndpointVolume::get_channel_volume_level)
+    fn get_channel_volume_level(&self, channel: u32) -> Result<f32>;
+
+    /// See also: [`AudioEndpointVolume::get_channel_volume_level_scalar`](crate::AudioEndpointVolume::get_channel_volume_level_scalar)
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> Result<f32>;
+
+    /// See also: [`AudioEndpointVolume::get_master_volume_level`](crate::AudioEndpointVolume::get_master_volume_level)
+    fn get_master_volume_level(&self) -> Result<f32>;
+
+    /// See also: [`AudioEndpointVolume::get_master_volume_level_scalar`](crate::AudioEndpointVolume::get_master_volume_level_scalar)
+    fn get_master_volume_level_scalar(&self) -> Result<f32>;
+
+    /// See also: [`AudioEndpointVolume::get_mute`](crate::AudioEndpointVolume::get_mute)
+    fn get_mute(&self) -> Result<bool>;
+
+    /// See also: [`AudioEndpointVolume::get_volume_range`](crate::AudioEndpointVolume::get_volume_range)
+    fn get_volume_range(&self) -> Result<VolumeRange>;
+
+    /// See also: [`AudioEndpointVolume::get_volume_step_info`](crate::AudioEndpointVolume::get_volume_step_info)
+    fn get_volume_step_info(&self) -> Result<VolumeStepInfo>;
+
+    /// See also: [`AudioEndpointVolume::query_hardware_support`](crate::AudioEndpointVolume::query_hardware_support)
+    fn query_hardware_support(&self) -> Result<HardwareSupportMask>;
+
+    /// See also: [`AudioEndpointVolume::register_control_change_notify`](crate::AudioEndpointVolume::register_control_change_notify)
+    fn register_control_change_notify<T>(&self, callback: T) -> Result<Self::CallbackHandle>
+    where
+        T: AudioEndpointVolumeCallback;
+
+    /// See also: [`AudioEndpointVolume::register_control_change_notify_channel`](crate::AudioEndpointVolume::register_control_change_notify_channel)
+    fn register_control_change_notify_channel(
+        &self,
+    ) -> Result<(Self::CallbackHandle, Receiver<VolumeEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_control_change_notify(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// See also: [`AudioEndpointVolume::register_control_change_notify_stream`](crate::AudioEndpointVolume::register_control_change_notify_stream)
+    #[cfg(feature = "stream")]
+    fn register_control_change_notify_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<EventStream<Self::CallbackHandle, VolumeEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_control_change_notify(callback)
+        })
+    }
+
+    /// See also: [`AudioEndpointVolume::set_channel_volume_level`](crate::AudioEndpointVolume::set_channel_volume_level)
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::set_channel_volume_level_scalar`](crate::AudioEndpointVolume::set_channel_volume_level_scalar)
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::set_master_volume_level`](crate::AudioEndpointVolume::set_master_volume_level)
+    fn set_master_volume_level(&self, level_db: f32, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::set_master_volume_level_scalar`](crate::AudioEndpointVolume::set_master_volume_level_scalar)
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::set_mute`](crate::AudioEndpointVolume::set_mute)
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::volume_step_down`](crate::AudioEndpointVolume::volume_step_down)
+    fn volume_step_down(&self, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioEndpointVolume::volume_step_up`](crate::AudioEndpointVolume::volume_step_up)
+    fn volume_step_up(&self, event_context: Option<&Guid>) -> Result<()>;
+}
+
+/// See also: [`AudioSessionManager2`](crate::AudioSessionManager2)
+pub trait AudioSessionManagerBackend {
+    type AudioSessionControl: AudioSessionControlBackend;
+    /// Unregisters the session notification when dropped.
+    type SessionNotificationHandle;
+    /// Unregisters the duck notification when dropped.
+    type DuckNotificationHandle;
+
+    /// Lists the current sessions on the device.
+    ///
+    /// See also: [`AudioSessionManager2::get_session_enumerator`](crate::AudioSessionManager2::get_session_enumerator)
+    fn get_sessions(&self) -> Result<Vec<Self::AudioSessionControl>>;
+
+    /// See also: [`AudioSessionManager2::register_duck_notification`](crate::AudioSessionManager2::register_duck_notification)
+    fn register_duck_notification<T>(
+        &self,
+        session_id: &WinStr,
+        duck_notification: T,
+    ) -> Result<Self::DuckNotificationHandle>
+    where
+        T: AudioVolumeDuckNotification;
+
+    /// See also: [`AudioSessionManager2::register_duck_notification_channel`](crate::AudioSessionManager2::register_duck_notification_channel)
+    fn register_duck_notification_channel(
+        &self,
+        session_id: &WinStr,
+    ) -> Result<(Self::DuckNotificationHandle, Receiver<DuckEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_duck_notification(session_id, events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// See also: [`AudioSessionManager2::register_duck_notification_stream`](crate::AudioSessionManager2::register_duck_notification_stream)
+    #[cfg(feature = "stream")]
+    fn register_duck_notification_stream(
+        &self,
+        session_id: &WinStr,
+        backpressure: Backpressure,
+    ) -> Result<EventStream<Self::DuckNotificationHandle, DuckEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_duck_notification(session_id, callback)
+        })
+    }
+
+    /// See also: [`AudioSessionManager2::register_session_notification`](crate::AudioSessionManager2::register_session_notification)
+    fn register_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<Self::SessionNotificationHandle>
+    where
+        T: SessionNotification<Self::AudioSessionControl>;
+
+    /// See also: [`AudioSessionManager2::register_session_notification_channel`](crate::AudioSessionManager2::register_session_notification_channel)
+    fn register_session_notification_channel(
+        &self,
+    ) -> Result<(
+        Self::SessionNotificationHandle,
+        SessionCreatedReceiver<Self>,
+    )>
+    where
+        Self::AudioSessionControl: 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_session_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// See also: [`AudioSessionManager2::register_session_notification_stream`](crate::AudioSessionManager2::register_session_notification_stream)
+    #[cfg(feature = "stream")]
+    fn register_session_notification_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<SessionCreatedStream<Self>>
+    where
+        Self::AudioSessionControl: 'static,
+    {
+        stream::event_stream(backpressure, |callback| {
+            self.register_session_notification(callback)
+        })
+    }
+}
+
+type SessionCreatedReceiver<T> =
+    Receiver<SessionCreated<<T as AudioSessionManagerBackend>::AudioSessionControl>>;
+
+#[cfg(feature = "stream")]
+type SessionCreatedStream<T> = EventStream<
+    <T as AudioSessionManagerBackend>::SessionNotificationHandle,
+    SessionCreated<<T as AudioSessionManagerBackend>::AudioSessionControl>,
+>;
+
+/// See also: [`AudioSessionControl`](crate::AudioSessionControl) and
+/// [`AudioSessionControl2`](crate::AudioSessionControl2)
+pub trait AudioSessionControlBackend {
+    type SimpleAudioVolume: SimpleAudioVolumeBackend;
+    /// Unregisters the session events when dropped.
+    type AudioSessionEventsHandle;
+
+    /// See also: [`AudioSessionControl::get_display_name`](crate::AudioSessionControl::get_display_name)
+    fn get_display_name(&self) -> Result<WinString>;
+
+    /// See also: [`AudioSessionControl::get_grouping_param`](crate::AudioSessionControl::get_grouping_param)
+    fn get_grouping_param(&self) -> Result<Guid>;
+
+    /// See also: [`AudioSessionControl::get_icon_path`](crate::AudioSessionControl::get_icon_path)
+    fn get_icon_path(&self) -> Result<WinString>;
+
+    /// See also: [`AudioSessionControl::get_state`](crate::AudioSessionControl::get_state)
+    fn get_state(&self) -> Result<AudioSessionState>;
+
+    /// See also: [`AudioSessionControl::register_audio_session_notification`](crate::AudioSessionControl::register_audio_session_notification)
+    fn register_audio_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<Self::AudioSessionEventsHandle>
+    where
+        T: AudioSessionEvents;
+
+    /// See also: [`AudioSessionControl::register_audio_session_notification_channel`](crate::AudioSessionControl::register_audio_session_notification_channel)
+    fn register_audio_session_notification_channel(
+        &self,
+    ) -> Result<(Self::AudioSessionEventsHandle, Receiver<SessionEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_audio_session_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// See also: [`AudioSessionControl::register_audio_session_notification_stream`](crate::AudioSessionControl::register_audio_session_notification_stream)
+    #[cfg(feature = "stream")]
+    fn register_audio_session_notification_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<EventStream<Self::AudioSessionEventsHandle, SessionEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_audio_session_notification(callback)
+        })
+    }
+
+    /// See also: [`AudioSessionControl::set_display_name`](crate::AudioSessionControl::set_display_name)
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioSessionControl::set_grouping_param`](crate::AudioSessionControl::set_grouping_param)
+    fn set_grouping_param(&self, value: &Guid, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioSessionControl::set_icon_path`](crate::AudioSessionControl::set_icon_path)
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`AudioSessionControl::get_simple_audio_volume`](crate::AudioSessionControl::get_simple_audio_volume)
+    fn get_simple_audio_volume(&self) -> Result<Self::SimpleAudioVolume>;
+
+    /// See also: [`AudioSessionControl2::get_process_id`](crate::AudioSessionControl2::get_process_id)
+    fn get_process_id(&self) -> Result<u32>;
+
+    /// See also: [`AudioSessionControl2::get_session_identifier`](crate::AudioSessionControl2::get_session_identifier)
+    fn get_session_identifier(&self) -> Result<WinString>;
+
+    /// See also: [`AudioSessionControl2::get_session_instance_identifier`](crate::AudioSessionControl2::get_session_instance_identifier)
+    fn get_session_instance_identifier(&self) -> Result<WinString>;
+
+    /// See also: [`AudioSessionControl2::is_system_sounds_session`](crate::AudioSessionControl2::is_system_sounds_session)
+    fn is_system_sounds_session(&self) -> bool;
+
+    /// See also: [`AudioSessionControl2::set_ducking_preference`](crate::AudioSessionControl2::set_ducking_preference)
+    fn set_ducking_preference(&self, opt_out: bool) -> Result<()>;
+}
+
+/// See also: [`SimpleAudioVolume`](crate::SimpleAudioVolume)
+pub trait SimpleAudioVolumeBackend {
+    /// See also: [`SimpleAudioVolume::get_master_volume`](crate::SimpleAudioVolume::get_master_volume)
+    fn get_master_volume(&self) -> Result<f32>;
+
+    /// See also: [`SimpleAudioVolume::get_mute`](crate::SimpleAudioVolume::get_mute)
+    fn get_mute(&self) -> Result<bool>;
+
+    /// See also: [`SimpleAudioVolume::set_master_volume`](crate::SimpleAudioVolume::set_master_volume)
+    fn set_master_volume(&self, volume_level: f32, event_context: Option<&Guid>) -> Result<()>;
+
+    /// See also: [`SimpleAudioVolume::set_mute`](crate::SimpleAudioVolume::set_mute)
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()>;
+}
+
+/// A backend-generic version of
+/// [`AudioSessionNotification`](crate::AudioSessionNotification), which
+/// receives new sessions as the backend's session type `S`.
+pub trait SessionNotification<S>: 'static {
+    /// See also: [`IAudioSessionNotification::OnSessionCreated`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionnotification-onsessioncreated)
+    fn on_session_created(&mut self, new_session: S) -> windows::Result<()> {
+        let _ = new_session;
+        Ok(())
+    }
+}
+
+impl<S, F> SessionNotification<S> for F
+where
+    F: FnMut(S) + 'static,
+{
+    fn on_session_created(&mut self, new_session: S) -> windows::Result<()> {
+        self(new_session);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, ErrorKind, E_ACCESSDENIED, E_INVALIDARG};
+
+    /// A store with three properties, one of whose values can't be read.
+    struct Store;
+
+    fn key(pid: u32) -> PropertyKey {
+        PropertyKey::new(Guid::zeroed(), pid)
+    }
+
+    impl PropertyStoreBackend for Store {
+        fn commit(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_at(&self, index: u32) -> Result<PropertyKey> {
+            Ok(key(index))
+        }
+
+        fn get_count(&self) -> Result<u32> {
+            Ok(3)
+        }
+
+        fn get_value(&self, key: &PropertyKey) -> Result<Property> {
+            match key.pid() {
+                1 => Err(Error::from_code("IPropertyStore::GetValue", E_ACCESSDENIED)),
+                pid => Ok(Property::U32(pid)),
+            }
+        }
+
+        fn set_value(&self, _key: &PropertyKey, _property: &Property) -> Result<()> {
+            Err(Error::from_code("IPropertyStore::SetValue", E_INVALIDARG))
+        }
+    }
+
+    #[test]
+    fn snapshot_skips_unreadable_values() {
+        let snapshot = Store.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.get(&key(0)), Some(&Property::U32(0)));
+        assert_eq!(snapshot.get(&key(1)), None);
+        assert_eq!(snapshot.get(&key(2)), Some(&Property::U32(2)));
+        assert_eq!(
+            Store.get_value(&key(1)).unwrap_err().kind(),
+            ErrorKind::AccessDenied
+        );
+    }
+}
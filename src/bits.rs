@@ -1,29 +1,66 @@
 //! Collection of translated bitflag and enumeration types.
 
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::str::FromStr;
+
+use windows::{Guid, HRESULT};
+
 use crate::bindings::Windows::Win32::{
     Media::Audio::CoreAudio::{
-        eAll, eCapture, eCommunications, eConsole, eMultimedia, eRender,
-        AudioSessionDisconnectReason as EAudioSessionDisconnectReason,
-        AudioSessionState as EAudioSessionState, AudioSessionStateActive, AudioSessionStateExpired,
-        AudioSessionStateInactive, DisconnectReasonDeviceRemoval,
-        DisconnectReasonExclusiveModeOverride, DisconnectReasonFormatChanged,
-        DisconnectReasonServerShutdown, DisconnectReasonSessionDisconnected,
-        DisconnectReasonSessionLogoff, EDataFlow, ERole, DEVICE_STATEMASK_ALL, DEVICE_STATE_ACTIVE,
-        DEVICE_STATE_DISABLED, DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED,
-        ENDPOINT_HARDWARE_SUPPORT_METER, ENDPOINT_HARDWARE_SUPPORT_MUTE,
-        ENDPOINT_HARDWARE_SUPPORT_VOLUME,
+        eAll, eCapture, eCommunications, eConsole, eMultimedia, eRender, AudioSessionStateActive,
+        AudioSessionStateExpired, AudioSessionStateInactive, DigitalAudioDisplayDevice,
+        DisconnectReasonDeviceRemoval, DisconnectReasonExclusiveModeOverride,
+        DisconnectReasonFormatChanged, DisconnectReasonServerShutdown,
+        DisconnectReasonSessionDisconnected, DisconnectReasonSessionLogoff, Handset, Headphones,
+        Headset, LineLevel, Microphone, RemoteNetworkDevice, Speakers, UnknownDigitalPassthrough,
+        UnknownFormFactor, DEVICE_STATEMASK_ALL, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED,
+        DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED, ENDPOINT_HARDWARE_SUPPORT_METER,
+        ENDPOINT_HARDWARE_SUPPORT_MUTE, ENDPOINT_HARDWARE_SUPPORT_VOLUME, SPDIF,
     },
     Storage::StructuredStorage::{STGM_READ, STGM_READWRITE, STGM_WRITE},
 };
+use crate::{
+    error::E_INVALIDARG,
+    property_store::Property,
+    typed_property::{FromProperty, IntoProperty},
+    util::{guid_to_string, parse_guid},
+};
 
 macro_rules! map_enum {
+    (@from_raw $name:ident: $repr:ty, $unknown:ident) => {
+        impl From<$repr> for $name {
+            fn from(raw: $repr) -> Self {
+                Self::from_known(raw).unwrap_or(Self::$unknown(raw))
+            }
+        }
+    };
+
+    (@from_raw $name:ident: $repr:ty) => {
+        impl TryFrom<$repr> for $name {
+            type Error = InvalidValueError<$repr>;
+
+            fn try_from(raw: $repr) -> Result<Self, Self::Error> {
+                Self::from_known(raw).ok_or(InvalidValueError {
+                    type_name: stringify!($name),
+                    value: raw,
+                })
+            }
+        }
+    };
+
     ($(
         $(#[$meta:meta])*
-        $vis:vis enum $name:ident: $maptype:ident {
+        $vis:vis enum $name:ident: $repr:ty {
             $(
                 $(#[$varmeta:meta])*
                 $varname:ident = $varmap:expr,
             )*
+            $(
+                $(#[$unknownmeta:meta])*
+                _ => $unknown:ident,
+            )?
         }
     )*) => {$(
         $(#[$meta])*
@@ -32,41 +69,178 @@ macro_rules! map_enum {
                 $(#[$varmeta])*
                 $varname,
             )*
+            $(
+                $(#[$unknownmeta])*
+                $unknown($repr),
+            )?
         }
 
         impl $name {
+            /// All known values, in declaration order.
+            pub const ALL: &'static [Self] = &[$(Self::$varname),*];
+
             #[allow(dead_code)]
-            pub(crate) fn from_raw(raw: $maptype) -> Self {
+            pub(crate) fn to_raw(&self) -> $repr {
+                match *self {
+                    $(Self::$varname => $varmap,)*
+                    $(Self::$unknown(raw) => raw,)?
+                }
+            }
+
+            fn from_known(raw: $repr) -> Option<Self> {
                 match raw {
-                    $(x if x == $varmap => Self::$varname,)*
-                    _ => panic!("invalid raw value {:?}", raw)
+                    $(x if x == $varmap => Some(Self::$varname),)*
+                    _ => None,
                 }
             }
 
+            /// The canonical name of this value, which is the same as the
+            /// variant name.
             #[allow(dead_code)]
-            pub(crate) fn to_raw(&self) -> $maptype {
+            pub(crate) fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$varname => stringify!($varname),)*
+                    $(Self::$unknown(_) => stringify!($unknown),)?
+                }
+            }
+
+            #[allow(dead_code)]
+            pub(crate) fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($varname) => Some(Self::$varname),)*
+                    _ => None,
+                }
+            }
+
+            /// The names of all known values.
+            #[allow(dead_code)]
+            pub(crate) const NAMES: &'static [&'static str] = &[$(stringify!($varname)),*];
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> Self {
+                value.to_raw()
+            }
+        }
+
+        map_enum!(@from_raw $name: $repr $(, $unknown)?);
+
+        /// Writes the variant name. Unknown values are written with their raw
+        /// value, e.g. `Unknown(5)`.
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
                 match self {
-                    $(Self::$varname => $varmap),*
+                    $(Self::$unknown(raw) => write!(f, "{}({:?})", stringify!($unknown), raw),)?
+                    #[allow(unreachable_patterns)]
+                    _ => f.write_str(self.name()),
                 }
             }
         }
-    )*}
+
+        /// Parses a variant name or an unknown value, as written by
+        /// `Display`.
+        impl FromStr for $name {
+            type Err = ParseEnumError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_name(s)
+                    $(.or_else(|| {
+                        let raw = s
+                            .strip_prefix(concat!(stringify!($unknown), "("))?
+                            .strip_suffix(')')?;
+                        <$repr>::parse_raw(raw).map(Self::from)
+                    }))?
+                    .ok_or(ParseEnumError {
+                        type_name: stringify!($name),
+                    })
+            }
+        }
+    )*};
+}
+
+/// Parses the raw value of an `Unknown` variant, as written by `Debug`.
+trait ParseRaw: Sized {
+    fn parse_raw(s: &str) -> Option<Self>;
+}
+
+impl ParseRaw for u32 {
+    fn parse_raw(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+}
+
+impl ParseRaw for i32 {
+    fn parse_raw(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+}
+
+impl ParseRaw for Guid {
+    fn parse_raw(s: &str) -> Option<Self> {
+        parse_guid(s)
+    }
+}
+
+/// An error returned when converting a raw value that does not correspond
+/// to any variant of an enum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidValueError<T> {
+    type_name: &'static str,
+    value: T,
+}
+
+impl<T: Copy> InvalidValueError<T> {
+    /// The raw value that could not be converted.
+    pub fn value(&self) -> T {
+        self.value
+    }
+}
+
+impl<T: Debug> Display for InvalidValueError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} value {:?}", self.type_name, self.value)
+    }
 }
 
+impl<T: Debug> Error for InvalidValueError<T> {}
+
+/// Reported to COM as `E_INVALIDARG` when a callback receives a value it
+/// cannot represent.
+impl<T> From<InvalidValueError<T>> for windows::Error {
+    fn from(_: InvalidValueError<T>) -> Self {
+        windows::Error::fast_error(HRESULT(E_INVALIDARG))
+    }
+}
+
+/// An error returned when parsing an enum from a name fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseEnumError {
+    type_name: &'static str,
+}
+
+impl Display for ParseEnumError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} name", self.type_name)
+    }
+}
+
+impl Error for ParseEnumError {}
+
 map_enum! {
     /// See also: [`EDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-edataflow)
     #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum DataFlow: EDataFlow {
-        Render = eRender,
-        Capture = eCapture,
+    pub enum DataFlow: i32 {
+        Render = eRender.0,
+        Capture = eCapture.0,
     }
 
     /// See also: [`EDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-edataflow)
     #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum DataFlowMask: EDataFlow {
-        Render = eRender,
-        Capture = eCapture,
-        All = eAll,
+    pub enum DataFlowMask: i32 {
+        Render = eRender.0,
+        Capture = eCapture.0,
+        All = eAll.0,
     }
 
     /// See also: [`DEVICE_STATE_XXXX Constants`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/device-state-xxx-constants)
@@ -76,14 +250,15 @@ map_enum! {
         Disabled = DEVICE_STATE_DISABLED,
         NotPresent = DEVICE_STATE_NOTPRESENT,
         Unplugged = DEVICE_STATE_UNPLUGGED,
+        _ => Unknown,
     }
 
     /// See also: [`ERole`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-erole)
     #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum DeviceRole: ERole {
-        Console = eConsole,
-        Multimedia = eMultimedia,
-        Communications = eCommunications,
+    pub enum DeviceRole: i32 {
+        Console = eConsole.0,
+        Multimedia = eMultimedia.0,
+        Communications = eCommunications.0,
     }
 
     /// See also: [`STGM Constants`](https://docs.microsoft.com/en-us/windows/win32/stg/stgm-constants)
@@ -96,20 +271,144 @@ map_enum! {
 
     /// See also: [`AudioSessionState`](https://docs.microsoft.com/en-us/windows/win32/api/audiosessiontypes/ne-audiosessiontypes-audiosessionstate)
     #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum AudioSessionState: EAudioSessionState {
-        Active = AudioSessionStateActive,
-        Expired = AudioSessionStateExpired,
-        Inactive = AudioSessionStateInactive,
+    pub enum AudioSessionState: i32 {
+        Active = AudioSessionStateActive.0,
+        Expired = AudioSessionStateExpired.0,
+        Inactive = AudioSessionStateInactive.0,
+        _ => Unknown,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum AudioSessionDisconnectReason: i32 {
+        DeviceRemoval = DisconnectReasonDeviceRemoval.0,
+        ServerShutdown = DisconnectReasonServerShutdown.0,
+        FormatChanged = DisconnectReasonFormatChanged.0,
+        SessionLogoff = DisconnectReasonSessionLogoff.0,
+        SessionDisconnected = DisconnectReasonSessionDisconnected.0,
+        ExclusiveModeOverride = DisconnectReasonExclusiveModeOverride.0,
+        _ => Unknown,
     }
 
+    /// See also: [`EndpointFormFactor`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-endpointformfactor)
     #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum AudioSessionDisconnectReason: EAudioSessionDisconnectReason {
-        DeviceRemoval = DisconnectReasonDeviceRemoval,
-        ServerShutdown = DisconnectReasonServerShutdown,
-        FormatChanged = DisconnectReasonFormatChanged,
-        SessionLogoff = DisconnectReasonSessionLogoff,
-        SessionDisconnected = DisconnectReasonSessionDisconnected,
-        ExclusiveModeOverride = DisconnectReasonExclusiveModeOverride,
+    #[allow(clippy::upper_case_acronyms)]
+    pub enum EndpointFormFactor: u32 {
+        RemoteNetworkDevice = RemoteNetworkDevice.0 as u32,
+        Speakers = Speakers.0 as u32,
+        LineLevel = LineLevel.0 as u32,
+        Headphones = Headphones.0 as u32,
+        Microphone = Microphone.0 as u32,
+        Headset = Headset.0 as u32,
+        Handset = Handset.0 as u32,
+        UnknownDigitalPassthrough = UnknownDigitalPassthrough.0 as u32,
+        SPDIF = SPDIF.0 as u32,
+        DigitalAudioDisplayDevice = DigitalAudioDisplayDevice.0 as u32,
+        UnknownFormFactor = UnknownFormFactor.0 as u32,
+        _ => Unknown,
+    }
+
+    /// The type of jack of an endpoint, identified by a `KSNODETYPE_*` GUID.
+    ///
+    /// See also: [`PKEY_AudioEndpoint_JackSubType`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioendpoint-jacksubtype)
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum JackSubType: Guid {
+        Microphone = ks_node_type(0x1be1),
+        DesktopMicrophone = ks_node_type(0x1be2),
+        PersonalMicrophone = ks_node_type(0x1be3),
+        OmniDirectionalMicrophone = ks_node_type(0x1be4),
+        MicrophoneArray = ks_node_type(0x1be5),
+        ProcessingMicrophoneArray = ks_node_type(0x1be6),
+        Speaker = ks_node_type(0x1ce1),
+        Headphones = ks_node_type(0x1ce2),
+        HeadMountedDisplayAudio = ks_node_type(0x1ce3),
+        DesktopSpeaker = ks_node_type(0x1ce4),
+        RoomSpeaker = ks_node_type(0x1ce5),
+        CommunicationSpeaker = ks_node_type(0x1ce6),
+        LowFrequencyEffectsSpeaker = ks_node_type(0x1ce7),
+        Handset = ks_node_type(0x1de1),
+        Headset = ks_node_type(0x1de2),
+        SpeakerphoneNoEchoReduction = ks_node_type(0x1de3),
+        EchoSuppressingSpeakerphone = ks_node_type(0x1de4),
+        EchoCancelingSpeakerphone = ks_node_type(0x1de5),
+        PhoneLine = ks_node_type(0x1ee1),
+        Telephone = ks_node_type(0x1ee2),
+        DownLinePhone = ks_node_type(0x1ee3),
+        AnalogConnector = ks_node_type(0x1fe1),
+        DigitalAudioInterface = ks_node_type(0x1fe2),
+        LineConnector = ks_node_type(0x1fe3),
+        LegacyAudioConnector = ks_node_type(0x1fe4),
+        SpdifInterface = ks_node_type(0x1fe5),
+        DaStream1394 = ks_node_type(0x1fe6),
+        DvStreamSoundtrack1394 = ks_node_type(0x1fe7),
+        HdmiInterface = KSNODETYPE_HDMI_INTERFACE,
+        DisplayPortInterface = KSNODETYPE_DISPLAYPORT_INTERFACE,
+        _ => Unknown,
+    }
+}
+
+/// The `KSNODETYPE_*` GUIDs of terminal types defined by the USB audio
+/// specification: `{DFF2xxxx-F70F-11D0-B917-00A0C9223196}`
+const fn ks_node_type(data1_low: u16) -> Guid {
+    Guid::from_values(
+        0xdff2_0000 | data1_low as u32,
+        0xf70f,
+        0x11d0,
+        [0xb9, 0x17, 0x00, 0xa0, 0xc9, 0x22, 0x31, 0x96],
+    )
+}
+
+/// `{D1B9CC2A-F519-417F-91C9-55FA65481001}`
+const KSNODETYPE_HDMI_INTERFACE: Guid = Guid::from_values(
+    0xd1b9cc2a,
+    0xf519,
+    0x417f,
+    [0x91, 0xc9, 0x55, 0xfa, 0x65, 0x48, 0x10, 0x01],
+);
+
+/// `{E47E4031-3EA6-418D-8F9B-B73843CCBA97}`
+const KSNODETYPE_DISPLAYPORT_INTERFACE: Guid = Guid::from_values(
+    0xe47e4031,
+    0x3ea6,
+    0x418d,
+    [0x8f, 0x9b, 0xb7, 0x38, 0x43, 0xcc, 0xba, 0x97],
+);
+
+/// Reads a `VT_UI4`.
+impl FromProperty for EndpointFormFactor {
+    const EXPECTED: &'static str = "EndpointFormFactor";
+
+    fn from_property(property: Property) -> Result<Self, Property> {
+        match property {
+            Property::U32(x) => Ok(x.into()),
+            other => Err(other),
+        }
+    }
+}
+
+/// Writes a `VT_UI4`.
+impl IntoProperty for EndpointFormFactor {
+    fn into_property(self) -> Property {
+        Property::U32(self.to_raw())
+    }
+}
+
+/// Reads a `VT_LPWSTR` containing a braced GUID.
+impl FromProperty for JackSubType {
+    const EXPECTED: &'static str = "KSNODETYPE GUID string";
+
+    fn from_property(property: Property) -> Result<Self, Property> {
+        let guid = match &property {
+            Property::Str(s) => s.to_string().ok().as_deref().and_then(parse_guid),
+            _ => None,
+        };
+        guid.map(Self::from).ok_or(property)
+    }
+}
+
+/// Writes a `VT_LPWSTR` containing a braced GUID.
+impl IntoProperty for JackSubType {
+    fn into_property(self) -> Property {
+        Property::Str(guid_to_string(&self.to_raw()).into())
     }
 }
 
@@ -130,3 +429,74 @@ bitflags::bitflags! {
         const VOLUME = ENDPOINT_HARDWARE_SUPPORT_VOLUME;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T>(values: &[T])
+    where
+        T: Display + FromStr<Err = ParseEnumError> + PartialEq + Debug,
+    {
+        for value in values {
+            assert_eq!(value.to_string().parse::<T>().as_ref(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn display_and_parse() {
+        round_trip(DataFlow::ALL);
+        round_trip(DataFlowMask::ALL);
+        round_trip(DeviceState::ALL);
+        round_trip(DeviceRole::ALL);
+        round_trip(StorageAccessMode::ALL);
+        round_trip(AudioSessionState::ALL);
+        round_trip(AudioSessionDisconnectReason::ALL);
+        round_trip(EndpointFormFactor::ALL);
+        round_trip(JackSubType::ALL);
+
+        assert_eq!(EndpointFormFactor::SPDIF.to_string(), "SPDIF");
+        assert_eq!(JackSubType::HdmiInterface.to_string(), "HdmiInterface");
+    }
+
+    #[test]
+    fn display_and_parse_unknown() {
+        let guid = Guid::from_values(1, 2, 3, [4; 8]);
+        round_trip(&[DeviceState::Unknown(0x20)]);
+        round_trip(&[AudioSessionState::Unknown(-1)]);
+        round_trip(&[AudioSessionDisconnectReason::Unknown(42)]);
+        round_trip(&[EndpointFormFactor::Unknown(99)]);
+        round_trip(&[JackSubType::Unknown(guid)]);
+
+        assert_eq!(DeviceState::Unknown(0x20).to_string(), "Unknown(32)");
+        // A known raw value parses as its variant.
+        assert_eq!("Unknown(1)".parse(), Ok(DeviceState::Active));
+        assert_eq!(
+            "Unknown({00000001-0002-0003-0404-040404040404})".parse(),
+            Ok(JackSubType::Unknown(guid))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("render".parse::<DataFlow>().is_err());
+        assert!("Unknown(0)".parse::<DataFlow>().is_err());
+        assert!("Unknown".parse::<DeviceState>().is_err());
+        assert!("Unknown(-1)".parse::<DeviceState>().is_err());
+        assert!("Unknown(1".parse::<DeviceState>().is_err());
+        assert!("Unknown(x)".parse::<JackSubType>().is_err());
+    }
+
+    #[test]
+    fn raw_values() {
+        assert_eq!(DeviceState::from(DEVICE_STATE_ACTIVE), DeviceState::Active);
+        assert_eq!(DeviceState::from(0x20), DeviceState::Unknown(0x20));
+        assert_eq!(u32::from(DeviceState::Unknown(0x20)), 0x20);
+        assert_eq!(DataFlow::try_from(eCapture.0), Ok(DataFlow::Capture));
+        assert_eq!(DataFlow::try_from(7).unwrap_err().value(), 7);
+        assert_eq!(
+            JackSubType::from(ks_node_type(0x1de2)),
+            JackSubType::Headset
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+
+use crate::{
+    bindings::Windows::Win32::System::Com::{
+        CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED,
+    },
+    error::{Context, ErrorKind, Result},
+};
+
+thread_local! {
+    /// The number of live [`ComGuard`]s on this thread.
+    static GUARDS: Cell<usize> = const { Cell::new(0) };
+
+    /// The guard created by [`ensure_thread_init`], if COM was initialized
+    /// implicitly. It is dropped when the thread exits.
+    static IMPLICIT_GUARD: RefCell<Option<ComGuard>> = const { RefCell::new(None) };
+}
+
+/// The COM apartment model to use on the current thread.
+///
+/// Every thread that uses this crate must have COM initialized. If a thread
+/// calls [`DeviceEnumerator::new`](crate::DeviceEnumerator::new) without
+/// initializing COM first, it is initialized as
+/// [`MultiThreaded`](Self::MultiThreaded) until the thread exits.
+///
+/// Use this to choose the apartment explicitly, or to tell the crate that the
+/// host application (e.g. a GUI framework) has already initialized COM:
+///
+/// ```no_run
+/// use win32_coreaudio::{ComApartment, DeviceEnumerator};
+///
+/// let _com = ComApartment::SingleThreaded.initialize()?;
+/// let enumerator = DeviceEnumerator::new()?;
+/// # Ok::<(), win32_coreaudio::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComApartment {
+    /// A single-threaded apartment (STA), as used by GUI threads.
+    ///
+    /// See also: [`COINIT_APARTMENTTHREADED`](https://docs.microsoft.com/en-us/windows/win32/api/objbase/ne-objbase-coinit)
+    SingleThreaded,
+    /// The multithreaded apartment (MTA).
+    ///
+    /// See also: [`COINIT_MULTITHREADED`](https://docs.microsoft.com/en-us/windows/win32/api/objbase/ne-objbase-coinit)
+    MultiThreaded,
+    /// COM has already been initialized on this thread by the host
+    /// application, which is responsible for uninitializing it.
+    Host,
+}
+
+impl ComApartment {
+    /// Initializes COM on the current thread.
+    ///
+    /// If the thread is already in an apartment of a different type
+    /// (`RPC_E_CHANGED_MODE`), COM is still usable, so this succeeds with a
+    /// guard that does not uninitialize COM.
+    ///
+    /// See also: [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex)
+    pub fn initialize(self) -> Result<ComGuard> {
+        let coinit = match self {
+            Self::SingleThreaded => COINIT_APARTMENTTHREADED,
+            Self::MultiThreaded => COINIT_MULTITHREADED,
+            Self::Host => return Ok(ComGuard::new(false)),
+        };
+        let result = unsafe { CoInitializeEx(std::ptr::null_mut(), coinit) };
+        // Both `S_OK` and `S_FALSE` must be balanced by `CoUninitialize`.
+        match result.context("CoInitializeEx") {
+            Ok(()) => Ok(ComGuard::new(true)),
+            Err(e) if e.kind() == ErrorKind::ChangedMode => Ok(ComGuard::new(false)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Keeps COM initialized on the current thread until it is dropped.
+///
+/// Created by [`ComApartment::initialize`]. Objects from this crate must not
+/// be used on this thread after the last guard is dropped.
+#[derive(Debug)]
+#[must_use = "COM will be uninitialized when the guard is dropped"]
+pub struct ComGuard {
+    uninitialize: bool,
+    // COM initialization is per-thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ComGuard {
+    fn new(uninitialize: bool) -> Self {
+        GUARDS.with(|guards| guards.set(guards.get() + 1));
+        Self {
+            uninitialize,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Whether this guard initialized COM, and will uninitialize it when
+    /// dropped.
+    pub fn owns_initialization(&self) -> bool {
+        self.uninitialize
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        // The thread-local may already be destroyed if this is the implicit
+        // guard being dropped at thread exit.
+        let _ = GUARDS.try_with(|guards| guards.set(guards.get() - 1));
+        if self.uninitialize {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+/// Make sure this is called at every static entrypoint to this crate.
+///
+/// Initializes COM as [`ComApartment::MultiThreaded`] if the caller has not
+/// initialized it through a [`ComGuard`].
+pub(crate) fn ensure_thread_init() -> Result<()> {
+    if GUARDS.with(Cell::get) > 0 {
+        return Ok(());
+    }
+    let guard = ComApartment::MultiThreaded.initialize()?;
+    IMPLICIT_GUARD.with(|implicit| *implicit.borrow_mut() = Some(guard));
+    Ok(())
+}
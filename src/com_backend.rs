@@ -0,0 +1,350 @@
+use windows::Guid;
+
+use crate::{
+    audio_endpoint_volume::{AudioEndpointVolume, AudioEndpointVolumeCallbackHandle},
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::AudioEndpointVolumeCallback,
+    audio_session_control::{AudioSessionControl, AudioSessionControl2, AudioSessionEventsHandle},
+    audio_session_events::AudioSessionEvents,
+    audio_session_manager::{
+        AudioSessionManager2, AudioSessionNotificationHandle, AudioVolumeDuckNotificationHandle,
+    },
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{
+        AudioEndpointVolumeBackend, AudioSessionControlBackend, AudioSessionManagerBackend,
+        Backend, DeviceBackend, DeviceEnumeratorBackend, PropertyStoreBackend, SessionNotification,
+        SimpleAudioVolumeBackend,
+    },
+    bits::{
+        AudioSessionState, DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask,
+        HardwareSupportMask, StorageAccessMode,
+    },
+    device::Device,
+    device_enumerator::{DeviceEnumerator, NotificationClientHandle},
+    error::Result,
+    notification_client::NotificationClient,
+    property_store::{Property, PropertyKey, PropertyStore},
+    simple_audio_volume::SimpleAudioVolume,
+    string::{WinStr, WinString},
+};
+
+/// The [`Backend`] implemented by the Windows Core Audio API, through the
+/// wrapper types in this crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ComBackend;
+
+impl Backend for ComBackend {
+    type DeviceEnumerator = DeviceEnumerator;
+
+    fn device_enumerator(&self) -> Result<DeviceEnumerator> {
+        DeviceEnumerator::new()
+    }
+}
+
+impl DeviceEnumeratorBackend for DeviceEnumerator {
+    type Device = Device;
+    type NotificationClientHandle = NotificationClientHandle;
+
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> Result<Vec<Device>> {
+        DeviceEnumerator::enum_audio_endpoints(self, data_flow_mask, state_mask)?.to_vec()
+    }
+
+    fn get_default_audio_endpoint(&self, data_flow: DataFlow, role: DeviceRole) -> Result<Device> {
+        DeviceEnumerator::get_default_audio_endpoint(self, data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> Result<Device> {
+        DeviceEnumerator::get_device(self, device_id)
+    }
+
+    fn register_endpoint_notification<T>(
+        &self,
+        notification_client: T,
+    ) -> Result<NotificationClientHandle>
+    where
+        T: NotificationClient,
+    {
+        DeviceEnumerator::register_endpoint_notification(self, notification_client)
+    }
+}
+
+impl DeviceBackend for Device {
+    type PropertyStore = PropertyStore;
+    type AudioEndpointVolume = AudioEndpointVolume;
+    type AudioSessionManager = AudioSessionManager2;
+
+    fn get_id(&self) -> Result<WinString> {
+        Device::get_id(self)
+    }
+
+    fn get_state(&self) -> Result<DeviceState> {
+        Device::get_state(self)
+    }
+
+    fn open_property_store(&self, storage_access_mode: StorageAccessMode) -> Result<PropertyStore> {
+        Device::open_property_store(self, storage_access_mode)
+    }
+
+    fn activate_audio_endpoint_volume(&self) -> Result<AudioEndpointVolume> {
+        Device::activate_audio_endpoint_volume(self)
+    }
+
+    fn activate_audio_session_manager2(&self) -> Result<AudioSessionManager2> {
+        Device::activate_audio_session_manager2(self)
+    }
+}
+
+impl PropertyStoreBackend for PropertyStore {
+    fn commit(&self) -> Result<()> {
+        PropertyStore::commit(self)
+    }
+
+    fn get_at(&self, index: u32) -> Result<PropertyKey> {
+        PropertyStore::get_at(self, index)
+    }
+
+    fn get_count(&self) -> Result<u32> {
+        PropertyStore::get_count(self)
+    }
+
+    fn get_value(&self, key: &PropertyKey) -> Result<Property> {
+        PropertyStore::get_value(self, key)
+    }
+
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> Result<()> {
+        PropertyStore::set_value(self, key, property)
+    }
+}
+
+impl AudioEndpointVolumeBackend for AudioEndpointVolume {
+    type CallbackHandle = AudioEndpointVolumeCallbackHandle;
+
+    fn get_channel_count(&self) -> Result<u32> {
+        AudioEndpointVolume::get_channel_count(self)
+    }
+
+    fn get_channel_volume_level(&self, channel: u32) -> Result<f32> {
+        AudioEndpointVolume::get_channel_volume_level(self, channel)
+    }
+
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> Result<f32> {
+        AudioEndpointVolume::get_channel_volume_level_scalar(self, channel)
+    }
+
+    fn get_master_volume_level(&self) -> Result<f32> {
+        AudioEndpointVolume::get_master_volume_level(self)
+    }
+
+    fn get_master_volume_level_scalar(&self) -> Result<f32> {
+        AudioEndpointVolume::get_master_volume_level_scalar(self)
+    }
+
+    fn get_mute(&self) -> Result<bool> {
+        AudioEndpointVolume::get_mute(self)
+    }
+
+    fn get_volume_range(&self) -> Result<VolumeRange> {
+        AudioEndpointVolume::get_volume_range(self)
+    }
+
+    fn get_volume_step_info(&self) -> Result<VolumeStepInfo> {
+        AudioEndpointVolume::get_volume_step_info(self)
+    }
+
+    fn query_hardware_support(&self) -> Result<HardwareSupportMask> {
+        AudioEndpointVolume::query_hardware_support(self)
+    }
+
+    fn register_control_change_notify<T>(
+        &self,
+        callback: T,
+    ) -> Result<AudioEndpointVolumeCallbackHandle>
+    where
+        T: AudioEndpointVolumeCallback,
+    {
+        AudioEndpointVolume::register_control_change_notify(self, callback)
+    }
+
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        AudioEndpointVolume::set_channel_volume_level(self, channel, level_db, event_context)
+    }
+
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        AudioEndpointVolume::set_channel_volume_level_scalar(self, channel, level, event_context)
+    }
+
+    fn set_master_volume_level(&self, level_db: f32, event_context: Option<&Guid>) -> Result<()> {
+        AudioEndpointVolume::set_master_volume_level(self, level_db, event_context)
+    }
+
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        AudioEndpointVolume::set_master_volume_level_scalar(self, level, event_context)
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
+        AudioEndpointVolume::set_mute(self, mute, event_context)
+    }
+
+    fn volume_step_down(&self, event_context: Option<&Guid>) -> Result<()> {
+        AudioEndpointVolume::volume_step_down(self, event_context)
+    }
+
+    fn volume_step_up(&self, event_context: Option<&Guid>) -> Result<()> {
+        AudioEndpointVolume::volume_step_up(self, event_context)
+    }
+}
+
+impl AudioSessionManagerBackend for AudioSessionManager2 {
+    type AudioSessionControl = AudioSessionControl2;
+    type SessionNotificationHandle = AudioSessionNotificationHandle;
+    type DuckNotificationHandle = AudioVolumeDuckNotificationHandle;
+
+    fn get_sessions(&self) -> Result<Vec<AudioSessionControl2>> {
+        self.get_session_enumerator()?
+            .to_vec()?
+            .iter()
+            .map(AudioSessionControl::upgrade)
+            .collect()
+    }
+
+    fn register_duck_notification<T>(
+        &self,
+        session_id: &WinStr,
+        duck_notification: T,
+    ) -> Result<AudioVolumeDuckNotificationHandle>
+    where
+        T: AudioVolumeDuckNotification,
+    {
+        AudioSessionManager2::register_duck_notification(self, session_id, duck_notification)
+    }
+
+    fn register_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<AudioSessionNotificationHandle>
+    where
+        T: SessionNotification<AudioSessionControl2>,
+    {
+        AudioSessionManager2::register_session_notification(
+            self,
+            UpgradeSessionNotification(session_notification),
+        )
+    }
+}
+
+/// Adapts a [`SessionNotification`] for [`AudioSessionControl2`] to the
+/// callback interface, which receives an [`AudioSessionControl`].
+struct UpgradeSessionNotification<T>(T);
+
+impl<T> AudioSessionNotification for UpgradeSessionNotification<T>
+where
+    T: SessionNotification<AudioSessionControl2>,
+{
+    fn on_session_created(&mut self, new_session: AudioSessionControl) -> windows::Result<()> {
+        self.0.on_session_created(new_session.upgrade()?)
+    }
+}
+
+impl AudioSessionControlBackend for AudioSessionControl2 {
+    type SimpleAudioVolume = SimpleAudioVolume;
+    type AudioSessionEventsHandle = AudioSessionEventsHandle;
+
+    fn get_display_name(&self) -> Result<WinString> {
+        AudioSessionControl::get_display_name(self)
+    }
+
+    fn get_grouping_param(&self) -> Result<Guid> {
+        AudioSessionControl::get_grouping_param(self)
+    }
+
+    fn get_icon_path(&self) -> Result<WinString> {
+        AudioSessionControl::get_icon_path(self)
+    }
+
+    fn get_state(&self) -> Result<AudioSessionState> {
+        AudioSessionControl::get_state(self)
+    }
+
+    fn register_audio_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<AudioSessionEventsHandle>
+    where
+        T: AudioSessionEvents,
+    {
+        AudioSessionControl::register_audio_session_notification(self, session_notification)
+    }
+
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
+        AudioSessionControl::set_display_name(self, value, event_context)
+    }
+
+    fn set_grouping_param(&self, value: &Guid, event_context: Option<&Guid>) -> Result<()> {
+        AudioSessionControl::set_grouping_param(self, value, event_context)
+    }
+
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
+        AudioSessionControl::set_icon_path(self, value, event_context)
+    }
+
+    fn get_simple_audio_volume(&self) -> Result<SimpleAudioVolume> {
+        AudioSessionControl::get_simple_audio_volume(self)
+    }
+
+    fn get_process_id(&self) -> Result<u32> {
+        AudioSessionControl2::get_process_id(self)
+    }
+
+    fn get_session_identifier(&self) -> Result<WinString> {
+        AudioSessionControl2::get_session_identifier(self)
+    }
+
+    fn get_session_instance_identifier(&self) -> Result<WinString> {
+        AudioSessionControl2::get_session_instance_identifier(self)
+    }
+
+    fn is_system_sounds_session(&self) -> bool {
+        AudioSessionControl2::is_system_sounds_session(self)
+    }
+
+    fn set_ducking_preference(&self, opt_out: bool) -> Result<()> {
+        AudioSessionControl2::set_ducking_preference(self, opt_out)
+    }
+}
+
+impl SimpleAudioVolumeBackend for SimpleAudioVolume {
+    fn get_master_volume(&self) -> Result<f32> {
+        SimpleAudioVolume::get_master_volume(self)
+    }
+
+    fn get_mute(&self) -> Result<bool> {
+        SimpleAudioVolume::get_mute(self)
+    }
+
+    fn set_master_volume(&self, volume_level: f32, event_context: Option<&Guid>) -> Result<()> {
+        SimpleAudioVolume::set_master_volume(self, volume_level, event_context)
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
+        SimpleAudioVolume::set_mute(self, mute, event_context)
+    }
+}
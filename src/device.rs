@@ -2,19 +2,16 @@ use crate::{
     audio_endpoint_volume::AudioEndpointVolume,
     audio_session_manager::AudioSessionManager,
     bindings::Windows::Win32::{
-        Media::Audio::CoreAudio::IMMDevice,
-        Storage::StructuredStorage::PROPVARIANT,
-        System::{
-            Com::CLSCTX_ALL,
-            SystemServices::{
-                DEVPKEY_DeviceInterface_FriendlyName, DEVPKEY_Device_DeviceDesc,
-                DEVPKEY_Device_FriendlyName,
-            },
-        },
+        Media::Audio::CoreAudio::IMMDevice, Storage::StructuredStorage::PROPVARIANT,
+        System::Com::CLSCTX_ALL,
     },
-    bits::{DeviceState, StorageAccessMode},
-    property_store::{PropertyKey, PropertyStore},
+    bits::{DeviceState, EndpointFormFactor, JackSubType, StorageAccessMode},
+    error::{Context, Result},
+    pkeys,
+    property_store::PropertyStore,
     string::WinString,
+    typed_property::PropertyError,
+    wave_format::{SetFormatError, WaveFormat, WaveFormatError},
     AudioSessionManager2,
 };
 use windows::{Abi, Interface};
@@ -30,50 +27,106 @@ impl Device {
         Self { inner }
     }
 
-    pub(crate) unsafe fn activate<T>(&self, params: *mut PROPVARIANT) -> windows::Result<T>
+    pub(crate) unsafe fn activate<T>(&self, params: *mut PROPVARIANT) -> Result<T>
     where
         T: Activate,
     {
         let mut raw = None;
         unsafe {
             self.inner
-                .Activate(&T::Raw::IID, CLSCTX_ALL.0, params, raw.set_abi())?;
+                .Activate(&T::Raw::IID, CLSCTX_ALL.0, params, raw.set_abi())
         }
+        .context("IMMDevice::Activate")?;
         Ok(T::from_raw(raw.unwrap()))
     }
 
-    pub fn activate_audio_endpoint_volume(&self) -> windows::Result<AudioEndpointVolume> {
+    pub fn activate_audio_endpoint_volume(&self) -> Result<AudioEndpointVolume> {
         unsafe { self.activate(std::ptr::null_mut()) }
     }
 
-    pub fn activate_audio_session_manager(&self) -> windows::Result<AudioSessionManager> {
+    pub fn activate_audio_session_manager(&self) -> Result<AudioSessionManager> {
         unsafe { self.activate(std::ptr::null_mut()) }
     }
 
-    pub fn activate_audio_session_manager2(&self) -> windows::Result<AudioSessionManager2> {
+    pub fn activate_audio_session_manager2(&self) -> Result<AudioSessionManager2> {
         unsafe { self.activate(std::ptr::null_mut()) }
     }
 
     /// See also: [`IMMDevice::GetId`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getid)
-    pub fn get_id(&self) -> windows::Result<WinString> {
-        Ok(unsafe { WinString::from_com_pwstr(self.inner.GetId()?) })
+    pub fn get_id(&self) -> Result<WinString> {
+        let id = unsafe { self.inner.GetId() }.context("IMMDevice::GetId")?;
+        Ok(unsafe { WinString::from_com_pwstr(id) })
     }
 
     /// See also: [`IMMDevice::GetState`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getstate)
-    pub fn get_state(&self) -> windows::Result<DeviceState> {
-        Ok(DeviceState::from_raw(unsafe { self.inner.GetState()? }))
+    pub fn get_state(&self) -> Result<DeviceState> {
+        unsafe { self.inner.GetState() }
+            .map(DeviceState::from)
+            .context("IMMDevice::GetState")
     }
 
     /// See also: [`IMMDevice::OpenPropertyStore`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-openpropertystore)
     pub fn open_property_store(
         &self,
         storage_access_mode: StorageAccessMode,
-    ) -> windows::Result<PropertyStore> {
+    ) -> Result<PropertyStore> {
         unsafe {
             self.inner
                 .OpenPropertyStore(storage_access_mode.to_raw() as _)
                 .map(PropertyStore::new)
         }
+        .context("IMMDevice::OpenPropertyStore")
+    }
+
+    /// Reads the default shared-mode format of the endpoint, from
+    /// [`AUDIO_ENGINE_DEVICE_FORMAT`](pkeys::AUDIO_ENGINE_DEVICE_FORMAT).
+    pub fn get_device_format(&self) -> Result<Option<WaveFormat>, PropertyError> {
+        self.open_property_store(StorageAccessMode::Read)?
+            .get(&pkeys::AUDIO_ENGINE_DEVICE_FORMAT)
+    }
+
+    /// Reads the physical form of the endpoint, from
+    /// [`AUDIO_ENDPOINT_FORM_FACTOR`](pkeys::AUDIO_ENDPOINT_FORM_FACTOR).
+    pub fn get_form_factor(&self) -> Result<Option<EndpointFormFactor>, PropertyError> {
+        self.open_property_store(StorageAccessMode::Read)?
+            .get(&pkeys::AUDIO_ENDPOINT_FORM_FACTOR)
+    }
+
+    /// Reads the type of jack the endpoint is connected through, from
+    /// [`AUDIO_ENDPOINT_JACK_SUB_TYPE`](pkeys::AUDIO_ENDPOINT_JACK_SUB_TYPE).
+    pub fn get_jack_subtype(&self) -> Result<Option<JackSubType>, PropertyError> {
+        self.open_property_store(StorageAccessMode::Read)?
+            .get(&pkeys::AUDIO_ENDPOINT_JACK_SUB_TYPE)
+    }
+
+    /// Changes the default shared-mode format of the endpoint, as the
+    /// "Advanced" tab of the sound control panel does.
+    ///
+    /// The format must have the same channel layout as the current format.
+    /// This opens the property store for writing, which requires
+    /// administrator privileges.
+    pub fn set_device_format(&self, format: &WaveFormat) -> Result<(), SetFormatError> {
+        format.validate()?;
+        let store = self.open_property_store(StorageAccessMode::ReadWrite)?;
+        if let Some(current) = store.get(&pkeys::AUDIO_ENGINE_DEVICE_FORMAT)? {
+            if current.channels != format.channels || current.channel_mask != format.channel_mask {
+                return Err(WaveFormatError::LayoutMismatch {
+                    channels: current.channels,
+                    channel_mask: current.channel_mask,
+                }
+                .into());
+            }
+        }
+        store.set(&pkeys::AUDIO_ENGINE_DEVICE_FORMAT, format.clone())?;
+        store.commit()?;
+        Ok(())
+    }
+
+    /// Reads the default format specified by the driver, from
+    /// [`AUDIO_ENGINE_OEM_FORMAT`](pkeys::AUDIO_ENGINE_OEM_FORMAT).
+    pub fn get_oem_format(&self) -> Result<Option<WaveFormat>, PropertyError> {
+        self.open_property_store(StorageAccessMode::Read)?
+            .get(&pkeys::AUDIO_ENGINE_OEM_FORMAT)
     }
 }
 
@@ -82,8 +135,3 @@ pub(crate) trait Activate {
 
     fn from_raw(raw: Self::Raw) -> Self;
 }
-
-pub const DEVICE_INTERFACE_FRIENDLY_NAME: PropertyKey =
-    PropertyKey::from_raw(DEVPKEY_DeviceInterface_FriendlyName);
-pub const DEVICE_DESCRIPTION: PropertyKey = PropertyKey::from_raw(DEVPKEY_Device_DeviceDesc);
-pub const DEVICE_FRIENDLY_NAME: PropertyKey = PropertyKey::from_raw(DEVPKEY_Device_FriendlyName);
@@ -1,7 +1,9 @@
 use std::{iter::FusedIterator, ops::Range};
 
 use crate::{
-    bindings::Windows::Win32::Media::Audio::CoreAudio::IMMDeviceCollection, device::Device,
+    bindings::Windows::Win32::Media::Audio::CoreAudio::IMMDeviceCollection,
+    device::Device,
+    error::{Context, Error, Result},
 };
 
 /// See also: [`IMMDeviceCollection`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immdevicecollection)
@@ -15,36 +17,64 @@ impl DeviceCollection {
         Self { inner }
     }
 
-    pub fn get_count(&self) -> windows::Result<u32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> Result<u32> {
+        unsafe { self.inner.GetCount() }.context("IMMDeviceCollection::GetCount")
     }
 
-    pub fn item(&self, device: u32) -> windows::Result<Device> {
-        unsafe { self.inner.Item(device).map(Device::new) }
+    pub fn item(&self, device: u32) -> Result<Device> {
+        unsafe { self.inner.Item(device).map(Device::new) }.context("IMMDeviceCollection::Item")
+    }
+
+    /// Returns an iterator that yields an error when a device cannot be
+    /// retrieved, e.g. because it was removed during enumeration.
+    /// Iterating over a `&DeviceCollection` does the same.
+    pub fn try_iter(&self) -> TryDeviceIter<'_> {
+        TryDeviceIter::new(self)
+    }
+
+    /// Returns an iterator that panics when a device cannot be retrieved.
+    ///
+    /// # Panics
+    ///
+    /// The iterator panics if the count or any device cannot be
+    /// retrieved. Use [`try_iter`](Self::try_iter) to handle these errors
+    /// instead.
+    pub fn unwrapping_iter(&self) -> DeviceIter<'_> {
+        DeviceIter::new(self)
+    }
+
+    /// Collects all devices, returning the first error encountered.
+    pub fn to_vec(&self) -> Result<Vec<Device>> {
+        self.try_iter().collect()
     }
 }
 
 impl<'a> IntoIterator for &'a DeviceCollection {
-    type IntoIter = DeviceIter<'a>;
-    type Item = Device;
+    type IntoIter = TryDeviceIter<'a>;
+    type Item = Result<Device>;
 
     fn into_iter(self) -> Self::IntoIter {
-        DeviceIter::new(self)
+        TryDeviceIter::new(self)
     }
 }
 
+/// An iterator over the devices in a [`DeviceCollection`].
+///
+/// # Panics
+///
+/// Panics if the count or any device cannot be retrieved. Use
+/// [`DeviceCollection::try_iter`] to handle these errors instead.
+///
+/// See also: [`DeviceCollection::unwrapping_iter`]
 #[derive(Debug, Clone)]
 pub struct DeviceIter<'a> {
-    inner: &'a DeviceCollection,
-    range: Range<u32>,
+    inner: TryDeviceIter<'a>,
 }
 
 impl<'a> DeviceIter<'a> {
     pub(crate) fn new(inner: &'a DeviceCollection) -> Self {
-        let count = inner.get_count().unwrap();
         Self {
-            inner,
-            range: 0..count,
+            inner: TryDeviceIter::new(inner),
         }
     }
 }
@@ -53,16 +83,63 @@ impl<'a> Iterator for DeviceIter<'a> {
     type Item = Device;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.range
-            .next()
-            .map(|index| self.inner.item(index).unwrap())
+        self.inner.next().map(|result| result.unwrap())
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.range.len();
-        (len, Some(len))
+        self.inner.size_hint()
     }
 }
 
 impl<'a> ExactSizeIterator for DeviceIter<'a> {}
 impl<'a> FusedIterator for DeviceIter<'a> {}
+
+/// A fallible iterator over the devices in a [`DeviceCollection`].
+///
+/// If the number of devices cannot be retrieved, the error is yielded as
+/// the only item.
+#[derive(Debug, Clone)]
+pub struct TryDeviceIter<'a> {
+    inner: &'a DeviceCollection,
+    range: Range<u32>,
+    error: Option<Error>,
+}
+
+impl<'a> TryDeviceIter<'a> {
+    pub(crate) fn new(inner: &'a DeviceCollection) -> Self {
+        let (count, error) = match inner.get_count() {
+            Ok(count) => (count, None),
+            Err(error) => (0, Some(error)),
+        };
+        Self {
+            inner,
+            range: 0..count,
+            error,
+        }
+    }
+
+    /// Skips any devices that cannot be retrieved, instead of yielding
+    /// errors.
+    pub fn skip_errors(self) -> impl Iterator<Item = Device> + 'a {
+        self.filter_map(Result::ok)
+    }
+}
+
+impl<'a> Iterator for TryDeviceIter<'a> {
+    type Item = Result<Device>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        self.range.next().map(|index| self.inner.item(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.range.len() + self.error.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for TryDeviceIter<'a> {}
+impl<'a> FusedIterator for TryDeviceIter<'a> {}
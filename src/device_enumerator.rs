@@ -1,15 +1,31 @@
+use std::sync::mpsc::{self, Receiver};
+
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+
 use crate::{
     bindings::Windows::Win32::{
-        Media::Audio::CoreAudio::{IMMDeviceEnumerator, IMMNotificationClient, MMDeviceEnumerator},
+        Media::Audio::CoreAudio::{
+            EDataFlow, ERole, IMMDeviceEnumerator, IMMNotificationClient, MMDeviceEnumerator,
+        },
         System::Com::{CoCreateInstance, CLSCTX_ALL},
     },
-    bits::{DataFlow, DataFlowMask, DeviceRole, DeviceStateMask},
+    bits::{DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask},
+    com_apartment,
     device::Device,
     device_collection::DeviceCollection,
-    notification_client::{NotificationClient, NotificationClientWrapper},
+    error::{Context, Result},
+    events::{self, DeviceEvent},
+    notification_client::{
+        NotificationClient, NotificationClientBuilder, NotificationClientWrapper,
+    },
+    property_store::PropertyKey,
     string::WinStr,
 };
 
+#[cfg(feature = "stream")]
+use crate::stream::{self, Backpressure};
+
 /// See also: [`IMMDeviceEnumerator`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immdeviceenumerator)
 #[derive(Debug, Clone)]
 pub struct DeviceEnumerator {
@@ -17,11 +33,12 @@ pub struct DeviceEnumerator {
 }
 
 impl DeviceEnumerator {
-    pub fn new() -> windows::Result<Self> {
+    pub fn new() -> Result<Self> {
         // Static entrypoint:
-        crate::ensure_thread_init();
+        com_apartment::ensure_thread_init()?;
 
-        let inner = unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+        let inner = unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
+            .context("CoCreateInstance(MMDeviceEnumerator)")?;
         Ok(Self { inner })
     }
 
@@ -30,11 +47,12 @@ impl DeviceEnumerator {
         &self,
         data_flow_mask: DataFlowMask,
         state_mask: DeviceStateMask,
-    ) -> windows::Result<DeviceCollection> {
+    ) -> Result<DeviceCollection> {
         let inner = unsafe {
             self.inner
-                .EnumAudioEndpoints(data_flow_mask.to_raw(), state_mask.bits())?
-        };
+                .EnumAudioEndpoints(EDataFlow(data_flow_mask.to_raw()), state_mask.bits())
+        }
+        .context("IMMDeviceEnumerator::EnumAudioEndpoints")?;
         Ok(DeviceCollection::new(inner))
     }
 
@@ -43,36 +61,123 @@ impl DeviceEnumerator {
         &self,
         data_flow: DataFlow,
         role: DeviceRole,
-    ) -> windows::Result<Device> {
+    ) -> Result<Device> {
         unsafe {
             self.inner
-                .GetDefaultAudioEndpoint(data_flow.to_raw(), role.to_raw())
+                .GetDefaultAudioEndpoint(EDataFlow(data_flow.to_raw()), ERole(role.to_raw()))
                 .map(Device::new)
         }
+        .context("IMMDeviceEnumerator::GetDefaultAudioEndpoint")
     }
 
     /// See also: [`IMMDeviceEnumerator::GetDevice`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-getdevice)
-    pub fn get_device(&self, device_id: &WinStr) -> windows::Result<Device> {
+    pub fn get_device(&self, device_id: &WinStr) -> Result<Device> {
         unsafe { self.inner.GetDevice(device_id.as_pwstr()).map(Device::new) }
+            .context("IMMDeviceEnumerator::GetDevice")
     }
 
     /// See also: [`IMMDeviceEnumerator::RegisterEndpointNotificationCallback`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-registerendpointnotificationcallback)
     pub fn register_endpoint_notification<T>(
         &self,
         notification_client: T,
-    ) -> windows::Result<NotificationClientHandle>
+    ) -> Result<NotificationClientHandle>
     where
         T: NotificationClient,
     {
         let wrapper =
             IMMNotificationClient::from(NotificationClientWrapper::new(notification_client));
-        unsafe { self.inner.RegisterEndpointNotificationCallback(&wrapper)? };
+        unsafe { self.inner.RegisterEndpointNotificationCallback(&wrapper) }
+            .context("IMMDeviceEnumerator::RegisterEndpointNotificationCallback")?;
 
         Ok(NotificationClientHandle {
             inner: wrapper,
             parent: self.inner.clone(),
         })
     }
+
+    /// Registers a closure for [`NotificationClient::on_default_device_changed`] only.
+    ///
+    /// Use [`NotificationClientBuilder`] to handle several notifications with
+    /// one registration.
+    pub fn on_default_device_changed<F>(&self, f: F) -> Result<NotificationClientHandle>
+    where
+        F: FnMut(DataFlow, DeviceRole, &WinStr) + 'static,
+    {
+        self.register_endpoint_notification(
+            NotificationClientBuilder::new().on_default_device_changed(f),
+        )
+    }
+
+    /// Registers a closure for [`NotificationClient::on_device_added`] only.
+    ///
+    /// Use [`NotificationClientBuilder`] to handle several notifications with
+    /// one registration.
+    pub fn on_device_added<F>(&self, f: F) -> Result<NotificationClientHandle>
+    where
+        F: FnMut(&WinStr) + 'static,
+    {
+        self.register_endpoint_notification(NotificationClientBuilder::new().on_device_added(f))
+    }
+
+    /// Registers a closure for [`NotificationClient::on_device_removed`] only.
+    ///
+    /// Use [`NotificationClientBuilder`] to handle several notifications with
+    /// one registration.
+    pub fn on_device_removed<F>(&self, f: F) -> Result<NotificationClientHandle>
+    where
+        F: FnMut(&WinStr) + 'static,
+    {
+        self.register_endpoint_notification(NotificationClientBuilder::new().on_device_removed(f))
+    }
+
+    /// Registers a closure for [`NotificationClient::on_device_state_changed`] only.
+    ///
+    /// Use [`NotificationClientBuilder`] to handle several notifications with
+    /// one registration.
+    pub fn on_device_state_changed<F>(&self, f: F) -> Result<NotificationClientHandle>
+    where
+        F: FnMut(&WinStr, DeviceState) + 'static,
+    {
+        self.register_endpoint_notification(
+            NotificationClientBuilder::new().on_device_state_changed(f),
+        )
+    }
+
+    /// Registers a closure for [`NotificationClient::on_property_value_changed`] only.
+    ///
+    /// Use [`NotificationClientBuilder`] to handle several notifications with
+    /// one registration.
+    pub fn on_property_value_changed<F>(&self, f: F) -> Result<NotificationClientHandle>
+    where
+        F: FnMut(&WinStr, PropertyKey) + 'static,
+    {
+        self.register_endpoint_notification(
+            NotificationClientBuilder::new().on_property_value_changed(f),
+        )
+    }
+
+    /// Like [`register_endpoint_notification`](Self::register_endpoint_notification),
+    /// but sends the notifications to a channel.
+    pub fn register_endpoint_notification_channel(
+        &self,
+    ) -> Result<(NotificationClientHandle, Receiver<DeviceEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.register_endpoint_notification(events::send_to(sender))?;
+        Ok((handle, receiver))
+    }
+
+    /// Like [`register_endpoint_notification`](Self::register_endpoint_notification), but
+    /// returns the notifications as a [`Stream`](futures_core::Stream). Dropping
+    /// the stream unregisters the callback.
+    #[cfg(feature = "stream")]
+    pub fn register_endpoint_notification_stream(
+        &self,
+        backpressure: Backpressure,
+    ) -> Result<impl Stream<Item = DeviceEvent>> {
+        stream::event_stream(backpressure, |callback| {
+            self.register_endpoint_notification(callback)
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
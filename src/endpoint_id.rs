@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use windows::Guid;
+
+use crate::{
+    bits::DataFlow,
+    string::{WinStr, WinString},
+    util::{guid_to_string, parse_guid, GuidFields},
+};
+
+/// A parsed MMDevice endpoint ID string, as returned by [`Device::get_id`].
+///
+/// Endpoint IDs have the form `{0.0.0.00000000}.{guid}`. The first part is a
+/// prefix that encodes the data flow of the endpoint (`{0.0.0.00000000}` for
+/// render and `{0.0.1.00000000}` for capture), and the second part is the
+/// instance GUID of the endpoint.
+///
+/// Parsing is case-insensitive, and IDs that differ only in case are equal;
+/// formatting produces the lowercase form that Windows itself uses.
+///
+/// [`Device::get_id`]: crate::Device::get_id
+#[derive(Debug, Clone)]
+pub struct EndpointId {
+    prefix: String,
+    flow: DataFlow,
+    guid: Guid,
+}
+
+impl EndpointId {
+    /// Creates the ID of an endpoint with the standard prefix for the given
+    /// data flow.
+    pub fn new(flow: DataFlow, guid: Guid) -> Self {
+        let flow_digit = match flow {
+            DataFlow::Render => 0,
+            DataFlow::Capture => 1,
+        };
+        Self {
+            prefix: format!("{{0.0.{}.00000000}}", flow_digit),
+            flow,
+            guid,
+        }
+    }
+
+    /// The data flow of the endpoint, as encoded in the prefix.
+    pub fn flow(&self) -> DataFlow {
+        self.flow
+    }
+
+    /// The instance GUID of the endpoint.
+    pub fn guid(&self) -> Guid {
+        self.guid
+    }
+
+    /// The prefix of the ID in lowercase, including braces, e.g.
+    /// `{0.0.0.00000000}`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Formats the ID as a string that can be passed to
+    /// [`DeviceEnumerator::get_device`](crate::DeviceEnumerator::get_device).
+    pub fn to_winstring(&self) -> WinString {
+        self.to_string().into()
+    }
+
+    fn key(&self) -> (&str, GuidFields) {
+        (&self.prefix, GuidFields::of(&self.guid))
+    }
+}
+
+impl Display for EndpointId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}",
+            self.prefix,
+            guid_to_string(&self.guid).to_ascii_lowercase()
+        )
+    }
+}
+
+impl FromStr for EndpointId {
+    type Err = ParseEndpointIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find("}.").ok_or(ParseEndpointIdError)?;
+        let (prefix, guid) = (&s[..split + 1], &s[split + 2..]);
+
+        let fields = prefix
+            .strip_prefix('{')
+            .and_then(|p| p.strip_suffix('}'))
+            .ok_or(ParseEndpointIdError)?;
+        let fields: Vec<&str> = fields.split('.').collect();
+        let valid_fields = fields.len() == 4
+            && fields
+                .iter()
+                .all(|f| !f.is_empty() && f.bytes().all(|b| b.is_ascii_hexdigit()));
+        if !valid_fields {
+            return Err(ParseEndpointIdError);
+        }
+        let flow = match fields[2] {
+            "0" => DataFlow::Render,
+            "1" => DataFlow::Capture,
+            _ => return Err(ParseEndpointIdError),
+        };
+
+        if !guid.starts_with('{') {
+            return Err(ParseEndpointIdError);
+        }
+        let guid = parse_guid(guid).ok_or(ParseEndpointIdError)?;
+
+        Ok(Self {
+            prefix: prefix.to_ascii_lowercase(),
+            flow,
+            guid,
+        })
+    }
+}
+
+impl TryFrom<&WinStr> for EndpointId {
+    type Error = ParseEndpointIdError;
+
+    fn try_from(value: &WinStr) -> Result<Self, Self::Error> {
+        value.to_string().map_err(|_| ParseEndpointIdError)?.parse()
+    }
+}
+
+impl PartialEq for EndpointId {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for EndpointId {}
+
+impl PartialOrd for EndpointId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EndpointId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for EndpointId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+/// An error returned when parsing an [`EndpointId`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseEndpointIdError;
+
+impl Display for ParseEndpointIdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid endpoint ID, expected `{0.0.0.00000000}.{guid}`")
+    }
+}
+
+impl Error for ParseEndpointIdError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winstr;
+
+    const RENDER_ID: &str = "{0.0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}";
+    const CAPTURE_ID: &str = "{0.0.1.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}";
+
+    fn guid() -> Guid {
+        Guid::from_values(
+            0x3a8b6c4e,
+            0x2f1d,
+            0x4e5a,
+            [0x9b, 0x7c, 0x0d, 0x1e, 0x2f, 0x3a, 0x4b, 0x5c],
+        )
+    }
+
+    #[test]
+    fn parse_and_format() {
+        let id: EndpointId = RENDER_ID.parse().unwrap();
+        assert_eq!(id.flow(), DataFlow::Render);
+        assert_eq!(id.guid(), guid());
+        assert_eq!(id.prefix(), "{0.0.0.00000000}");
+        assert_eq!(id.to_string(), RENDER_ID);
+        assert_eq!(id, EndpointId::new(DataFlow::Render, guid()));
+
+        let id: EndpointId = CAPTURE_ID.parse().unwrap();
+        assert_eq!(id.flow(), DataFlow::Capture);
+        assert_eq!(id.to_string(), CAPTURE_ID);
+        assert_eq!(id.to_winstring().to_string().unwrap(), CAPTURE_ID);
+    }
+
+    #[test]
+    fn parse_is_case_insensitive() {
+        let upper: EndpointId = RENDER_ID.to_ascii_uppercase().parse().unwrap();
+        assert_eq!(upper, RENDER_ID.parse().unwrap());
+        assert_eq!(upper.to_string(), RENDER_ID);
+    }
+
+    #[test]
+    fn parse_winstr() {
+        let id = EndpointId::try_from(winstr!(
+            "{0.0.1.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}"
+        ))
+        .unwrap();
+        assert_eq!(id, EndpointId::new(DataFlow::Capture, guid()));
+
+        let unpaired = WinString::from_vec(vec![b'{' as u16, 0xD800]).unwrap();
+        assert!(EndpointId::try_from(unpaired.as_winstr()).is_err());
+    }
+
+    #[test]
+    fn keeps_nonstandard_prefix() {
+        let s = "{0.0.0.0000abcd}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}";
+        let id: EndpointId = s.parse().unwrap();
+        assert_eq!(id.prefix(), "{0.0.0.0000abcd}");
+        assert_eq!(id.to_string(), s);
+        assert_ne!(id, EndpointId::new(DataFlow::Render, guid()));
+    }
+
+    #[test]
+    fn prefix_case_is_ignored() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash(id: &EndpointId) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            id.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let lower: EndpointId = "{0.0.0.0000abcd}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}"
+            .parse()
+            .unwrap();
+        let upper: EndpointId = "{0.0.0.0000ABCD}.{3A8B6C4E-2F1D-4E5A-9B7C-0D1E2F3A4B5C}"
+            .parse()
+            .unwrap();
+        assert_eq!(upper, lower);
+        assert_eq!(upper.cmp(&lower), Ordering::Equal);
+        assert_eq!(hash(&upper), hash(&lower));
+        assert_eq!(upper.prefix(), "{0.0.0.0000abcd}");
+        assert_eq!(upper.to_string(), lower.to_string());
+    }
+
+    #[test]
+    fn malformed() {
+        for s in [
+            "",
+            "speakers",
+            "{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0.0.00000000}",
+            "{0.0.0.00000000}.",
+            "0.0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0..00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0.2.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0.x.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}",
+            "{0.0.0.00000000}.3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c",
+            "{0.0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5}",
+            "{0.0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5g}",
+        ] {
+            assert_eq!(
+                s.parse::<EndpointId>(),
+                Err(ParseEndpointIdError),
+                "{:?}",
+                s
+            );
+        }
+    }
+}
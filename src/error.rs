@@ -0,0 +1,313 @@
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+
+use windows::HRESULT;
+
+/// A specialized [`Result`](std::result::Result) type for operations that
+/// return an [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error returned by a Core Audio, MMDevice or property store method.
+///
+/// It records the name of the COM method that failed, and the underlying
+/// [`windows::Error`] as its source.
+#[derive(Debug, Clone)]
+pub struct Error {
+    operation: &'static str,
+    source: windows::Error,
+}
+
+impl Error {
+    pub(crate) fn new(operation: &'static str, source: windows::Error) -> Self {
+        Self { operation, source }
+    }
+
+    pub(crate) fn from_code(operation: &'static str, code: u32) -> Self {
+        Self::new(operation, windows::Error::fast_error(HRESULT(code)))
+    }
+
+    /// The COM method that failed, e.g. `"IAudioEndpointVolume::SetMute"`.
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    pub fn code(&self) -> HRESULT {
+        self.source.code()
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::from_code(self.code())
+    }
+
+    /// The name of the error code, if it is a known one.
+    pub fn code_name(&self) -> Option<&'static str> {
+        hresult_name(self.code())
+    }
+
+    pub fn as_windows_error(&self) -> &windows::Error {
+        &self.source
+    }
+
+    pub fn into_windows_error(self) -> windows::Error {
+        self.source
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: ", self.operation)?;
+        match self.code_name() {
+            Some(name) => write!(f, "{} ({:#010x})", name, self.code().0),
+            None => write!(f, "{:#010x}", self.code().0),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<Error> for windows::Error {
+    fn from(e: Error) -> Self {
+        e.source
+    }
+}
+
+/// Attaches the name of the failed operation to a [`windows::Result`].
+pub(crate) trait Context<T> {
+    fn context(self, operation: &'static str) -> Result<T>;
+}
+
+impl<T> Context<T> for windows::Result<T> {
+    fn context(self, operation: &'static str) -> Result<T> {
+        self.map_err(|e| Error::new(operation, e))
+    }
+}
+
+/// A category of [`Error`], determined by its error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// `AUDCLNT_E_DEVICE_INVALIDATED`: the endpoint was removed, disabled or
+    /// reconfigured. Objects obtained from it must be released and
+    /// reacquired.
+    DeviceInvalidated,
+    /// `E_NOTFOUND`: the device or property does not exist.
+    NotFound,
+    /// `AUDCLNT_E_SERVICE_NOT_RUNNING`: the Windows Audio service is not
+    /// running.
+    ServiceNotRunning,
+    /// `E_ACCESSDENIED` or `STG_E_ACCESSDENIED`.
+    AccessDenied,
+    /// `AUDCLNT_S_NO_SINGLE_PROCESS`: the session spans more than one
+    /// process.
+    NoSingleProcess,
+    /// `AUDCLNT_E_UNSUPPORTED_FORMAT`
+    UnsupportedFormat,
+    /// `AUDCLNT_E_DEVICE_IN_USE`: the endpoint is used in exclusive mode.
+    DeviceInUse,
+    /// `E_INVALIDARG` or `STG_E_INVALIDPARAMETER`.
+    InvalidArgument,
+    /// `E_OUTOFMEMORY`
+    OutOfMemory,
+    /// `CO_E_NOTINITIALIZED`: COM is not initialized on the calling thread.
+    ComNotInitialized,
+    /// `RPC_E_CHANGED_MODE`: COM was already initialized on the calling
+    /// thread with a different apartment type.
+    ChangedMode,
+    /// `E_NOINTERFACE`
+    NoInterface,
+    /// `E_NOTIMPL`
+    NotImplemented,
+    /// Any other error code.
+    Other,
+}
+
+impl ErrorKind {
+    pub fn from_code(code: HRESULT) -> Self {
+        match code.0 {
+            AUDCLNT_E_DEVICE_INVALIDATED => Self::DeviceInvalidated,
+            E_NOTFOUND => Self::NotFound,
+            AUDCLNT_E_SERVICE_NOT_RUNNING => Self::ServiceNotRunning,
+            E_ACCESSDENIED | STG_E_ACCESSDENIED => Self::AccessDenied,
+            AUDCLNT_S_NO_SINGLE_PROCESS => Self::NoSingleProcess,
+            AUDCLNT_E_UNSUPPORTED_FORMAT => Self::UnsupportedFormat,
+            AUDCLNT_E_DEVICE_IN_USE => Self::DeviceInUse,
+            E_INVALIDARG | STG_E_INVALIDPARAMETER => Self::InvalidArgument,
+            E_OUTOFMEMORY => Self::OutOfMemory,
+            CO_E_NOTINITIALIZED => Self::ComNotInitialized,
+            RPC_E_CHANGED_MODE => Self::ChangedMode,
+            E_NOINTERFACE => Self::NoInterface,
+            E_NOTIMPL => Self::NotImplemented,
+            _ => Self::Other,
+        }
+    }
+}
+
+macro_rules! hresults {
+    ($($name:ident = $value:expr,)*) => {
+        $(pub(crate) const $name: u32 = $value;)*
+
+        /// Looks up the name of a COM, Core Audio or property store error
+        /// code, e.g. `"AUDCLNT_E_DEVICE_INVALIDATED"`.
+        pub fn hresult_name(code: HRESULT) -> Option<&'static str> {
+            match code.0 {
+                $($name => Some(stringify!($name)),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+hresults! {
+    S_OK = 0x0000_0000,
+    S_FALSE = 0x0000_0001,
+    E_NOTIMPL = 0x8000_4001,
+    E_NOINTERFACE = 0x8000_4002,
+    E_POINTER = 0x8000_4003,
+    E_ABORT = 0x8000_4004,
+    E_FAIL = 0x8000_4005,
+    E_UNEXPECTED = 0x8000_ffff,
+    E_ACCESSDENIED = 0x8007_0005,
+    E_HANDLE = 0x8007_0006,
+    E_OUTOFMEMORY = 0x8007_000e,
+    E_INVALIDARG = 0x8007_0057,
+    E_NOTFOUND = 0x8007_0490,
+    RPC_E_CHANGED_MODE = 0x8001_0106,
+    CO_E_NOTINITIALIZED = 0x8004_01f0,
+    REGDB_E_CLASSNOTREG = 0x8004_0154,
+    CLASS_E_NOAGGREGATION = 0x8004_0110,
+    STG_E_ACCESSDENIED = 0x8003_0005,
+    STG_E_INVALIDPARAMETER = 0x8003_0057,
+    INPLACE_S_TRUNCATED = 0x0004_01a0,
+    AUDCLNT_E_NOT_INITIALIZED = 0x8889_0001,
+    AUDCLNT_E_ALREADY_INITIALIZED = 0x8889_0002,
+    AUDCLNT_E_WRONG_ENDPOINT_TYPE = 0x8889_0003,
+    AUDCLNT_E_DEVICE_INVALIDATED = 0x8889_0004,
+    AUDCLNT_E_NOT_STOPPED = 0x8889_0005,
+    AUDCLNT_E_BUFFER_TOO_LARGE = 0x8889_0006,
+    AUDCLNT_E_OUT_OF_ORDER = 0x8889_0007,
+    AUDCLNT_E_UNSUPPORTED_FORMAT = 0x8889_0008,
+    AUDCLNT_E_INVALID_SIZE = 0x8889_0009,
+    AUDCLNT_E_DEVICE_IN_USE = 0x8889_000a,
+    AUDCLNT_E_BUFFER_OPERATION_PENDING = 0x8889_000b,
+    AUDCLNT_E_THREAD_NOT_REGISTERED = 0x8889_000c,
+    AUDCLNT_E_EXCLUSIVE_MODE_NOT_ALLOWED = 0x8889_000e,
+    AUDCLNT_E_ENDPOINT_CREATE_FAILED = 0x8889_000f,
+    AUDCLNT_E_SERVICE_NOT_RUNNING = 0x8889_0010,
+    AUDCLNT_E_EVENTHANDLE_NOT_EXPECTED = 0x8889_0011,
+    AUDCLNT_E_EXCLUSIVE_MODE_ONLY = 0x8889_0012,
+    AUDCLNT_E_BUFDURATION_PERIOD_NOT_EQUAL = 0x8889_0013,
+    AUDCLNT_E_EVENTHANDLE_NOT_SET = 0x8889_0014,
+    AUDCLNT_E_INCORRECT_BUFFER_SIZE = 0x8889_0015,
+    AUDCLNT_E_BUFFER_SIZE_ERROR = 0x8889_0016,
+    AUDCLNT_E_CPUUSAGE_EXCEEDED = 0x8889_0017,
+    AUDCLNT_E_BUFFER_ERROR = 0x8889_0018,
+    AUDCLNT_E_BUFFER_SIZE_NOT_ALIGNED = 0x8889_0019,
+    AUDCLNT_E_INVALID_DEVICE_PERIOD = 0x8889_0020,
+    AUDCLNT_S_BUFFER_EMPTY = 0x0889_0001,
+    AUDCLNT_S_THREAD_ALREADY_REGISTERED = 0x0889_0002,
+    AUDCLNT_S_POSITION_STALLED = 0x0889_0003,
+    AUDCLNT_S_NO_SINGLE_PROCESS = 0x0889_000d,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(code: u32) -> ErrorKind {
+        ErrorKind::from_code(HRESULT(code))
+    }
+
+    #[test]
+    fn error_kind() {
+        assert_eq!(
+            kind(AUDCLNT_E_DEVICE_INVALIDATED),
+            ErrorKind::DeviceInvalidated
+        );
+        assert_eq!(kind(E_NOTFOUND), ErrorKind::NotFound);
+        assert_eq!(
+            kind(AUDCLNT_E_SERVICE_NOT_RUNNING),
+            ErrorKind::ServiceNotRunning
+        );
+        assert_eq!(kind(E_ACCESSDENIED), ErrorKind::AccessDenied);
+        assert_eq!(kind(STG_E_ACCESSDENIED), ErrorKind::AccessDenied);
+        assert_eq!(
+            kind(AUDCLNT_S_NO_SINGLE_PROCESS),
+            ErrorKind::NoSingleProcess
+        );
+        assert_eq!(
+            kind(AUDCLNT_E_UNSUPPORTED_FORMAT),
+            ErrorKind::UnsupportedFormat
+        );
+        assert_eq!(kind(AUDCLNT_E_DEVICE_IN_USE), ErrorKind::DeviceInUse);
+        assert_eq!(kind(E_INVALIDARG), ErrorKind::InvalidArgument);
+        assert_eq!(kind(STG_E_INVALIDPARAMETER), ErrorKind::InvalidArgument);
+        assert_eq!(kind(E_OUTOFMEMORY), ErrorKind::OutOfMemory);
+        assert_eq!(kind(CO_E_NOTINITIALIZED), ErrorKind::ComNotInitialized);
+        assert_eq!(kind(RPC_E_CHANGED_MODE), ErrorKind::ChangedMode);
+        assert_eq!(kind(E_NOINTERFACE), ErrorKind::NoInterface);
+        assert_eq!(kind(E_NOTIMPL), ErrorKind::NotImplemented);
+
+        assert_eq!(kind(E_FAIL), ErrorKind::Other);
+        assert_eq!(kind(AUDCLNT_E_NOT_INITIALIZED), ErrorKind::Other);
+        assert_eq!(kind(0x8000_1234), ErrorKind::Other);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(hresult_name(HRESULT(S_OK)), Some("S_OK"));
+        assert_eq!(
+            hresult_name(HRESULT(E_ACCESSDENIED)),
+            Some("E_ACCESSDENIED")
+        );
+        assert_eq!(
+            hresult_name(HRESULT(STG_E_ACCESSDENIED)),
+            Some("STG_E_ACCESSDENIED")
+        );
+        assert_eq!(
+            hresult_name(HRESULT(AUDCLNT_S_NO_SINGLE_PROCESS)),
+            Some("AUDCLNT_S_NO_SINGLE_PROCESS")
+        );
+        assert_eq!(hresult_name(HRESULT(0x8000_1234)), None);
+        assert_eq!(hresult_name(HRESULT(0x0889_0004)), None);
+    }
+
+    #[test]
+    fn display() {
+        let e = Error::from_code("IMMDevice::Activate", AUDCLNT_E_DEVICE_INVALIDATED);
+        assert_eq!(e.operation(), "IMMDevice::Activate");
+        assert_eq!(e.code_name(), Some("AUDCLNT_E_DEVICE_INVALIDATED"));
+        assert_eq!(
+            e.to_string(),
+            "IMMDevice::Activate failed: AUDCLNT_E_DEVICE_INVALIDATED (0x88890004)"
+        );
+
+        let e = Error::from_code("IPropertyStore::GetValue", 0x8000_1234);
+        assert_eq!(e.code_name(), None);
+        assert_eq!(e.to_string(), "IPropertyStore::GetValue failed: 0x80001234");
+    }
+
+    #[test]
+    fn source() {
+        let e = Err::<(), _>(windows::Error::fast_error(HRESULT(E_ACCESSDENIED)))
+            .context("IMMDevice::OpenPropertyStore")
+            .unwrap_err();
+        assert_eq!(e.operation(), "IMMDevice::OpenPropertyStore");
+        assert_eq!(e.kind(), ErrorKind::AccessDenied);
+
+        let source = e
+            .source()
+            .and_then(|source| source.downcast_ref::<windows::Error>())
+            .unwrap();
+        assert_eq!(source.code(), HRESULT(E_ACCESSDENIED));
+        assert_eq!(e.as_windows_error().code(), HRESULT(E_ACCESSDENIED));
+        assert_eq!(
+            windows::Error::from(e.clone()).code(),
+            HRESULT(E_ACCESSDENIED)
+        );
+        assert_eq!(e.into_windows_error().code(), HRESULT(E_ACCESSDENIED));
+    }
+}
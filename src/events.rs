@@ -0,0 +1,324 @@
+//! Owned versions of the callback arguments, for receiving events through a
+//! channel instead of implementing a callback trait.
+
+use std::sync::mpsc::Sender;
+
+use windows::Guid;
+
+use crate::{
+    audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
+    audio_session_control::AudioSessionControl,
+    audio_session_events::AudioSessionEvents,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::SessionNotification,
+    bits::{AudioSessionDisconnectReason, AudioSessionState, DataFlow, DeviceRole, DeviceState},
+    notification_client::NotificationClient,
+    property_store::PropertyKey,
+    string::{WinStr, WinString},
+};
+
+/// A call to a [`NotificationClient`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    /// `device_id` is empty if there is no longer a default device.
+    ///
+    /// See also: [`NotificationClient::on_default_device_changed`]
+    DefaultDeviceChanged {
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: WinString,
+    },
+    /// See also: [`NotificationClient::on_device_added`]
+    DeviceAdded { device_id: WinString },
+    /// See also: [`NotificationClient::on_device_removed`]
+    DeviceRemoved { device_id: WinString },
+    /// See also: [`NotificationClient::on_device_state_changed`]
+    DeviceStateChanged {
+        device_id: WinString,
+        state: DeviceState,
+    },
+    /// See also: [`NotificationClient::on_property_value_changed`]
+    PropertyValueChanged {
+        device_id: WinString,
+        property_key: PropertyKey,
+    },
+}
+
+/// A call to an [`AudioEndpointVolumeCallback`], with an owned copy of the
+/// [`NotificationData`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeEvent {
+    pub event_context: Guid,
+    pub muted: bool,
+    pub master_volume: f32,
+    pub channel_volumes: Vec<f32>,
+}
+
+/// A call to an [`AudioSessionEvents`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// See also: [`AudioSessionEvents::on_channel_volume_changed`]
+    ChannelVolumeChanged {
+        new_volume_levels: Vec<f32>,
+        changed_channel: usize,
+        event_context: Option<Guid>,
+    },
+    /// See also: [`AudioSessionEvents::on_display_name_changed`]
+    DisplayNameChanged {
+        new_display_name: WinString,
+        event_context: Option<Guid>,
+    },
+    /// See also: [`AudioSessionEvents::on_grouping_param_changed`]
+    GroupingParamChanged {
+        new_grouping_param: Option<Guid>,
+        event_context: Option<Guid>,
+    },
+    /// See also: [`AudioSessionEvents::on_icon_path_changed`]
+    IconPathChanged {
+        new_icon_path: WinString,
+        event_context: Option<Guid>,
+    },
+    /// See also: [`AudioSessionEvents::on_session_disconnected`]
+    SessionDisconnected {
+        disconnect_reason: AudioSessionDisconnectReason,
+    },
+    /// See also: [`AudioSessionEvents::on_simple_volume_changed`]
+    SimpleVolumeChanged {
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<Guid>,
+    },
+    /// See also: [`AudioSessionEvents::on_state_changed`]
+    StateChanged { new_state: AudioSessionState },
+}
+
+/// A call to an [`AudioSessionNotification`].
+///
+/// With a [backend](crate::backend), `S` is the backend's session type.
+#[derive(Debug, Clone)]
+pub struct SessionCreated<S = AudioSessionControl> {
+    pub new_session: S,
+}
+
+/// A call to an [`AudioVolumeDuckNotification`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuckEvent {
+    /// See also: [`AudioVolumeDuckNotification::on_volume_duck_notification`]
+    VolumeDuck {
+        session_id: WinString,
+        num_communication_sessions: u32,
+    },
+    /// See also: [`AudioVolumeDuckNotification::on_volume_unduck_notification`]
+    VolumeUnduck { session_id: WinString },
+}
+
+/// Implements the callback traits by passing each call to `F` as an owned
+/// event.
+pub(crate) struct Forward<F>(pub(crate) F);
+
+/// Forwards events to a channel. Events are discarded once the receiver has
+/// been dropped.
+pub(crate) fn send_to<E>(sender: Sender<E>) -> Forward<impl FnMut(E)> {
+    Forward(move |event| {
+        let _ = sender.send(event);
+    })
+}
+
+impl<F> NotificationClient for Forward<F>
+where
+    F: FnMut(DeviceEvent) + 'static,
+{
+    fn on_default_device_changed(
+        &mut self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> windows::Result<()> {
+        (self.0)(DeviceEvent::DefaultDeviceChanged {
+            data_flow,
+            role,
+            device_id: device_id.to_winstring(),
+        });
+        Ok(())
+    }
+
+    fn on_device_added(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        (self.0)(DeviceEvent::DeviceAdded {
+            device_id: device_id.to_winstring(),
+        });
+        Ok(())
+    }
+
+    fn on_device_removed(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        (self.0)(DeviceEvent::DeviceRemoved {
+            device_id: device_id.to_winstring(),
+        });
+        Ok(())
+    }
+
+    fn on_device_state_changed(
+        &mut self,
+        device_id: &WinStr,
+        state: DeviceState,
+    ) -> windows::Result<()> {
+        (self.0)(DeviceEvent::DeviceStateChanged {
+            device_id: device_id.to_winstring(),
+            state,
+        });
+        Ok(())
+    }
+
+    fn on_property_value_changed(
+        &mut self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> windows::Result<()> {
+        (self.0)(DeviceEvent::PropertyValueChanged {
+            device_id: device_id.to_winstring(),
+            property_key,
+        });
+        Ok(())
+    }
+}
+
+impl<F> AudioEndpointVolumeCallback for Forward<F>
+where
+    F: FnMut(VolumeEvent) + 'static,
+{
+    fn on_notify(&mut self, data: &NotificationData) -> windows::Result<()> {
+        (self.0)(VolumeEvent {
+            event_context: data.event_context,
+            muted: data.muted,
+            master_volume: data.master_volume,
+            channel_volumes: data.channel_volumes.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+impl<F> AudioSessionEvents for Forward<F>
+where
+    F: FnMut(SessionEvent) + 'static,
+{
+    fn on_channel_volume_changed(
+        &mut self,
+        new_volume_levels: &[f32],
+        changed_channel: usize,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::ChannelVolumeChanged {
+            new_volume_levels: new_volume_levels.to_vec(),
+            changed_channel,
+            event_context: event_context.copied(),
+        });
+        Ok(())
+    }
+
+    fn on_display_name_changed(
+        &mut self,
+        new_display_name: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::DisplayNameChanged {
+            new_display_name: new_display_name.to_winstring(),
+            event_context: event_context.copied(),
+        });
+        Ok(())
+    }
+
+    fn on_grouping_param_changed(
+        &mut self,
+        new_grouping_param: Option<&Guid>,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::GroupingParamChanged {
+            new_grouping_param: new_grouping_param.copied(),
+            event_context: event_context.copied(),
+        });
+        Ok(())
+    }
+
+    fn on_icon_path_changed(
+        &mut self,
+        new_icon_path: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::IconPathChanged {
+            new_icon_path: new_icon_path.to_winstring(),
+            event_context: event_context.copied(),
+        });
+        Ok(())
+    }
+
+    fn on_session_disconnected(
+        &mut self,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::SessionDisconnected { disconnect_reason });
+        Ok(())
+    }
+
+    fn on_simple_volume_changed(
+        &mut self,
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        (self.0)(SessionEvent::SimpleVolumeChanged {
+            new_volume,
+            new_mute,
+            event_context: event_context.copied(),
+        });
+        Ok(())
+    }
+
+    fn on_state_changed(&mut self, new_state: AudioSessionState) -> windows::Result<()> {
+        (self.0)(SessionEvent::StateChanged { new_state });
+        Ok(())
+    }
+}
+
+impl<F> AudioSessionNotification for Forward<F>
+where
+    F: FnMut(SessionCreated) + 'static,
+{
+    fn on_session_created(&mut self, new_session: AudioSessionControl) -> windows::Result<()> {
+        (self.0)(SessionCreated { new_session });
+        Ok(())
+    }
+}
+
+impl<S, F> SessionNotification<S> for Forward<F>
+where
+    F: FnMut(SessionCreated<S>) + 'static,
+{
+    fn on_session_created(&mut self, new_session: S) -> windows::Result<()> {
+        (self.0)(SessionCreated { new_session });
+        Ok(())
+    }
+}
+
+impl<F> AudioVolumeDuckNotification for Forward<F>
+where
+    F: FnMut(DuckEvent) + 'static,
+{
+    fn on_volume_duck_notification(
+        &mut self,
+        session_id: &WinStr,
+        num_communication_sessions: u32,
+    ) -> windows::Result<()> {
+        (self.0)(DuckEvent::VolumeDuck {
+            session_id: session_id.to_winstring(),
+            num_communication_sessions,
+        });
+        Ok(())
+    }
+
+    fn on_volume_unduck_notification(&mut self, session_id: &WinStr) -> windows::Result<()> {
+        (self.0)(DuckEvent::VolumeUnduck {
+            session_id: session_id.to_winstring(),
+        });
+        Ok(())
+    }
+}
@@ -14,51 +14,79 @@ mod audio_session_events;
 mod audio_session_manager;
 mod audio_session_notification;
 mod audio_volume_duck_notification;
+pub mod backend;
 mod bits;
+mod com_apartment;
+#[cfg(windows)]
+mod com_backend;
 mod device;
 mod device_collection;
 mod device_enumerator;
+mod endpoint_id;
+mod error;
+mod events;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod notification_client;
+pub mod pkeys;
+mod prop_variant;
+mod property_codec;
+mod property_snapshot;
 mod property_store;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod session_identifier;
 mod simple_audio_volume;
+#[cfg(feature = "mock")]
+pub mod simulator;
+#[cfg(feature = "stream")]
+mod stream;
 pub mod string;
+mod typed_property;
 pub(crate) mod util;
+mod wave_format;
 
 pub use self::{
-    audio_endpoint_volume::{AudioEndpointVolume, AudioEndpointVolumeCallbackHandle},
+    audio_endpoint_volume::{
+        AudioEndpointVolume, AudioEndpointVolumeCallbackHandle, VolumeRange, VolumeStepInfo,
+    },
     audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
     audio_session_control::{AudioSessionControl, AudioSessionControl2, AudioSessionEventsHandle},
-    audio_session_enumerator::{AudioSessionEnumerator, AudioSessionIter},
-    audio_session_events::AudioSessionEvents,
+    audio_session_enumerator::{AudioSessionEnumerator, AudioSessionIter, TryAudioSessionIter},
+    audio_session_events::{AudioSessionEvents, AudioSessionEventsBuilder},
     audio_session_manager::{
         AudioSessionManager, AudioSessionManager2, AudioSessionNotificationHandle,
         AudioVolumeDuckNotificationHandle,
     },
     audio_session_notification::AudioSessionNotification,
-    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    audio_volume_duck_notification::{
+        AudioVolumeDuckNotification, AudioVolumeDuckNotificationBuilder,
+    },
     bits::{
         AudioSessionDisconnectReason, AudioSessionState, DataFlow, DataFlowMask, DeviceRole,
-        DeviceState, DeviceStateMask, HardwareSupportMask, StorageAccessMode,
+        DeviceState, DeviceStateMask, EndpointFormFactor, HardwareSupportMask, InvalidValueError,
+        JackSubType, ParseEnumError, StorageAccessMode,
     },
-    device::{Device, DEVICE_DESCRIPTION, DEVICE_FRIENDLY_NAME, DEVICE_INTERFACE_FRIENDLY_NAME},
-    device_collection::{DeviceCollection, DeviceIter},
+    com_apartment::{ComApartment, ComGuard},
+    device::Device,
+    device_collection::{DeviceCollection, DeviceIter, TryDeviceIter},
     device_enumerator::{DeviceEnumerator, NotificationClientHandle},
-    notification_client::NotificationClient,
-    property_store::{Property, PropertyKey, PropertyStore},
+    endpoint_id::{EndpointId, ParseEndpointIdError},
+    error::{hresult_name, Error, ErrorKind, Result},
+    events::{DeviceEvent, DuckEvent, SessionCreated, SessionEvent, VolumeEvent},
+    notification_client::{NotificationClient, NotificationClientBuilder},
+    pkeys::{DEVICE_DESCRIPTION, DEVICE_FRIENDLY_NAME, DEVICE_INTERFACE_FRIENDLY_NAME},
+    prop_variant::PropVariant,
+    property_snapshot::{PropertyChange, PropertyDiff, PropertySnapshot},
+    property_store::{ParsePropertyKeyError, Property, PropertyIter, PropertyKey, PropertyStore},
+    session_identifier::{ParseSessionIdentifierError, SessionIdentifier},
     simple_audio_volume::SimpleAudioVolume,
+    typed_property::{FromProperty, IntoProperty, PropertyError, TypedPropertyKey},
+    wave_format::{
+        ParseWaveFormatError, SetFormatError, SpeakerPositions, SubFormat, WaveFormat,
+        WaveFormatError,
+    },
 };
 
-use crate::bindings::Windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED};
-use std::sync::Once;
-
-/// Make sure this is called at every static entrypoint to this crate.
-pub(crate) fn ensure_thread_init() {
-    thread_local! {
-        static INIT_ONCE: Once = Once::new();
-    }
-    INIT_ONCE.with(|init_once| {
-        init_once.call_once(|| unsafe {
-            CoInitializeEx(std::ptr::null_mut(), COINIT_MULTITHREADED).unwrap()
-        })
-    })
-}
+#[cfg(feature = "stream")]
+pub use self::stream::{Backpressure, EventStream};
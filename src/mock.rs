@@ -0,0 +1,1693 @@
+//! An in-memory implementation of the [`backend`](crate::backend) traits, for
+//! testing code that uses Core Audio on any platform.
+//!
+//! [`MockBackend`] simulates devices, sessions, volumes and property stores.
+//! Changes made through the backend traits, and through the simulation
+//! methods on the mock types (such as [`MockDevice::set_state`]), fire the
+//! registered callbacks synchronously on the calling thread, with the same
+//! arguments Windows would pass.
+//!
+//! Errors use the same operation names and error codes as the COM API, e.g.
+//! [`ErrorKind::NotFound`](crate::ErrorKind::NotFound) for an unknown
+//! device ID, and
+//! [`ErrorKind::DeviceInvalidated`](crate::ErrorKind::DeviceInvalidated)
+//! when using the volume or sessions of a removed device.
+//!
+//! ```
+//! use win32_coreaudio::{
+//!     backend::{Backend, DeviceBackend, DeviceEnumeratorBackend},
+//!     mock::MockBackend,
+//!     winstr, DataFlow, DeviceRole, DeviceState,
+//! };
+//!
+//! let backend = MockBackend::new();
+//! let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+//!
+//! let enumerator = backend.device_enumerator()?;
+//! let device = enumerator.get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console)?;
+//! assert_eq!(device.get_id()?, speakers.get_id()?);
+//!
+//! speakers.set_state(DeviceState::Unplugged)?;
+//! assert_eq!(device.get_state()?, DeviceState::Unplugged);
+//! # Ok::<(), win32_coreaudio::Error>(())
+//! ```
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::rc::{Rc, Weak};
+
+use windows::Guid;
+
+use crate::{
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
+    audio_session_events::AudioSessionEvents,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{
+        AudioEndpointVolumeBackend, AudioSessionControlBackend, AudioSessionManagerBackend,
+        Backend, DeviceBackend, DeviceEnumeratorBackend, PropertyStoreBackend, SessionNotification,
+        SimpleAudioVolumeBackend,
+    },
+    bits::{
+        AudioSessionDisconnectReason, AudioSessionState, DataFlow, DataFlowMask, DeviceRole,
+        DeviceState, DeviceStateMask, HardwareSupportMask, StorageAccessMode,
+    },
+    error::{
+        Error, Result, AUDCLNT_E_DEVICE_INVALIDATED, E_INVALIDARG, E_NOTFOUND, STG_E_ACCESSDENIED,
+    },
+    notification_client::NotificationClient,
+    property_snapshot::PropertySnapshot,
+    property_store::{Property, PropertyKey},
+    string::{WinStr, WinString},
+};
+
+/// The state shared by a [`MockBackend`] and every object obtained from it.
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<State>>);
+
+impl Debug for Shared {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("MockBackend")
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    devices: Vec<DeviceData>,
+    defaults: Vec<(DataFlow, DeviceRole, WinString)>,
+    notification_clients: Registry<dyn NotificationClient>,
+    next_session: u32,
+}
+
+impl State {
+    fn device(&mut self, id: &WinStr) -> Option<&mut DeviceData> {
+        self.devices
+            .iter_mut()
+            .find(|device| device.id.eq_ignore_case(id))
+    }
+}
+
+#[derive(Debug)]
+struct DeviceData {
+    id: WinString,
+    data_flow: DataFlow,
+    state: DeviceState,
+    properties: PropertySnapshot,
+    volume: VolumeData,
+    volume_callbacks: Registry<dyn AudioEndpointVolumeCallback>,
+    sessions: Vec<SessionData>,
+    session_callbacks: Registry<dyn SessionNotification<MockAudioSessionControl>>,
+    duck_callbacks: Registry<dyn AudioVolumeDuckNotification>,
+}
+
+impl DeviceData {
+    fn session(&mut self, instance_id: &WinStr) -> Option<&mut SessionData> {
+        self.sessions
+            .iter_mut()
+            .find(|session| session.instance_id == *instance_id)
+    }
+}
+
+#[derive(Debug)]
+struct VolumeData {
+    channels: Vec<f32>,
+    muted: bool,
+    range: VolumeRange,
+    num_steps: u32,
+    hardware_support: HardwareSupportMask,
+}
+
+impl VolumeData {
+    /// The master volume is the loudest channel, as in Windows.
+    fn master(&self) -> f32 {
+        self.channels.iter().copied().fold(0.0, f32::max)
+    }
+
+    fn scalar_to_db(&self, scalar: f32) -> f32 {
+        self.range.min_db + scalar * (self.range.max_db - self.range.min_db)
+    }
+
+    fn db_to_scalar(&self, level_db: f32) -> Option<f32> {
+        if level_db < self.range.min_db || level_db > self.range.max_db {
+            return None;
+        }
+        Some((level_db - self.range.min_db) / (self.range.max_db - self.range.min_db))
+    }
+
+    fn current_step(&self) -> u32 {
+        (self.master() * (self.num_steps - 1) as f32).round() as u32
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.set_master(step as f32 / (self.num_steps - 1) as f32);
+    }
+
+    /// Scales every channel so the loudest one has the given volume.
+    fn set_master(&mut self, level: f32) {
+        let master = self.master();
+        for channel in &mut self.channels {
+            *channel = if master > 0.0 {
+                *channel / master * level
+            } else {
+                level
+            };
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SessionData {
+    instance_id: WinString,
+    session_id: WinString,
+    process_id: u32,
+    display_name: WinString,
+    icon_path: WinString,
+    grouping_param: Guid,
+    state: AudioSessionState,
+    volume: f32,
+    muted: bool,
+    ducking_opt_out: bool,
+    events: Registry<dyn AudioSessionEvents>,
+}
+
+type Callback<T> = Rc<RefCell<Box<T>>>;
+
+struct Registry<T: ?Sized> {
+    next_id: u64,
+    entries: Vec<(u64, Callback<T>)>,
+}
+
+impl<T: ?Sized> Registry<T> {
+    fn add(&mut self, callback: Box<T>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push((id, Rc::new(RefCell::new(callback))));
+        id
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Callback<T>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(entry_id, _)| *entry_id == id)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    fn callbacks(&self) -> Vec<Callback<T>> {
+        self.entries
+            .iter()
+            .map(|(_, callback)| callback.clone())
+            .collect()
+    }
+}
+
+impl<T: ?Sized> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T: ?Sized> Debug for Registry<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+/// Calls each callback. This must be done after the state has been released,
+/// so callbacks can call back into the mock.
+fn notify<T: ?Sized>(
+    callbacks: Vec<Callback<T>>,
+    mut f: impl FnMut(&mut T) -> windows::Result<()>,
+) {
+    for callback in callbacks {
+        // A callback that triggers its own notification is not re-entered.
+        if let Ok(mut callback) = callback.try_borrow_mut() {
+            let _ = f(&mut **callback);
+        }
+    }
+}
+
+impl Shared {
+    fn with_device<R>(
+        &self,
+        id: &WinStr,
+        operation: &'static str,
+        code: u32,
+        f: impl FnOnce(&mut DeviceData) -> Result<R>,
+    ) -> Result<R> {
+        let mut state = self.0.borrow_mut();
+        match state.device(id) {
+            Some(device) => f(device),
+            None => Err(Error::from_code(operation, code)),
+        }
+    }
+
+    fn with_session<R>(
+        &self,
+        device_id: &WinStr,
+        instance_id: &WinStr,
+        operation: &'static str,
+        f: impl FnOnce(&mut SessionData) -> Result<R>,
+    ) -> Result<R> {
+        self.with_device(
+            device_id,
+            operation,
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| match device.session(instance_id) {
+                Some(session) => f(session),
+                None => Err(Error::from_code(operation, AUDCLNT_E_DEVICE_INVALIDATED)),
+            },
+        )
+    }
+
+    fn notification_clients(&self) -> Vec<Callback<dyn NotificationClient>> {
+        self.0.borrow().notification_clients.callbacks()
+    }
+
+    fn set_default_device(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> Result<()> {
+        let device_id = {
+            let mut state = self.0.borrow_mut();
+            let device_id = state
+                .device(device_id)
+                .map(|device| device.id.clone())
+                .ok_or_else(|| Error::from_code("IMMDeviceEnumerator::GetDevice", E_NOTFOUND))?;
+            let unchanged = state
+                .defaults
+                .iter()
+                .any(|(flow, r, id)| *flow == data_flow && *r == role && *id == device_id);
+            if unchanged {
+                // Windows does not notify when the default device stays the same.
+                return Ok(());
+            }
+            state
+                .defaults
+                .retain(|(flow, r, _)| *flow != data_flow || *r != role);
+            state.defaults.push((data_flow, role, device_id.clone()));
+            device_id
+        };
+        notify(self.notification_clients(), |client| {
+            client.on_default_device_changed(data_flow, role, &device_id)
+        });
+        Ok(())
+    }
+}
+
+/// A simulated audio system, and the [`Backend`] and
+/// [`DeviceEnumeratorBackend`] for it.
+///
+/// Clones share the same devices and sessions.
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    shared: Shared,
+}
+
+impl MockBackend {
+    /// Creates an audio system with no devices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an active device with two channels at full volume.
+    ///
+    /// If there is no default device for its data flow yet, the device
+    /// becomes the default for every role.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a device with the same ID already exists.
+    pub fn add_device(&self, id: &WinStr, data_flow: DataFlow) -> MockDevice {
+        let needs_default = {
+            let mut state = self.shared.0.borrow_mut();
+            assert!(state.device(id).is_none(), "duplicate device ID {}", id);
+            state.devices.push(DeviceData {
+                id: id.to_winstring(),
+                data_flow,
+                state: DeviceState::Active,
+                properties: PropertySnapshot::new(),
+                volume: VolumeData {
+                    channels: vec![1.0; 2],
+                    muted: false,
+                    range: VolumeRange {
+                        min_db: -65.25,
+                        max_db: 0.0,
+                        increment_db: 0.03125,
+                    },
+                    num_steps: 51,
+                    hardware_support: HardwareSupportMask::empty(),
+                },
+                volume_callbacks: Registry::default(),
+                sessions: Vec::new(),
+                session_callbacks: Registry::default(),
+                duck_callbacks: Registry::default(),
+            });
+            !state.defaults.iter().any(|(flow, _, _)| *flow == data_flow)
+        };
+        notify(self.shared.notification_clients(), |client| {
+            client.on_device_added(id)
+        });
+        if needs_default {
+            for &role in DeviceRole::ALL {
+                // The device was just added, so it exists.
+                let _ = self.shared.set_default_device(data_flow, role, id);
+            }
+        }
+        MockDevice::new(self.shared.clone(), id.to_winstring())
+    }
+
+    /// Changes the default device for a data flow and role.
+    pub fn set_default_device(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> Result<()> {
+        self.shared.set_default_device(data_flow, role, device_id)
+    }
+
+    /// The number of callbacks registered on the backend and its devices.
+    #[cfg(test)]
+    pub(crate) fn registered_callbacks(&self) -> usize {
+        let state = self.shared.0.borrow();
+        state.notification_clients.entries.len()
+            + state
+                .devices
+                .iter()
+                .map(|device| {
+                    device.volume_callbacks.entries.len()
+                        + device.session_callbacks.entries.len()
+                        + device.duck_callbacks.entries.len()
+                        + device
+                            .sessions
+                            .iter()
+                            .map(|session| session.events.entries.len())
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl Backend for MockBackend {
+    type DeviceEnumerator = MockBackend;
+
+    fn device_enumerator(&self) -> Result<MockBackend> {
+        Ok(self.clone())
+    }
+}
+
+impl DeviceEnumeratorBackend for MockBackend {
+    type Device = MockDevice;
+    type NotificationClientHandle = MockCallbackHandle;
+
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> Result<Vec<MockDevice>> {
+        let state = self.shared.0.borrow();
+        Ok(state
+            .devices
+            .iter()
+            .filter(|device| match data_flow_mask {
+                DataFlowMask::Render => device.data_flow == DataFlow::Render,
+                DataFlowMask::Capture => device.data_flow == DataFlow::Capture,
+                DataFlowMask::All => true,
+            })
+            .filter(|device| {
+                state_mask.intersects(DeviceStateMask::from_bits_truncate(device.state.to_raw()))
+            })
+            .map(|device| MockDevice::new(self.shared.clone(), device.id.clone()))
+            .collect())
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> Result<MockDevice> {
+        let state = self.shared.0.borrow();
+        state
+            .defaults
+            .iter()
+            .find(|(flow, r, _)| *flow == data_flow && *r == role)
+            .map(|(_, _, id)| MockDevice::new(self.shared.clone(), id.clone()))
+            .ok_or_else(|| {
+                Error::from_code("IMMDeviceEnumerator::GetDefaultAudioEndpoint", E_NOTFOUND)
+            })
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> Result<MockDevice> {
+        self.shared.with_device(
+            device_id,
+            "IMMDeviceEnumerator::GetDevice",
+            E_NOTFOUND,
+            |device| Ok(MockDevice::new(self.shared.clone(), device.id.clone())),
+        )
+    }
+
+    fn register_endpoint_notification<T>(
+        &self,
+        notification_client: T,
+    ) -> Result<MockCallbackHandle>
+    where
+        T: NotificationClient,
+    {
+        let id = self
+            .shared
+            .0
+            .borrow_mut()
+            .notification_clients
+            .add(Box::new(notification_client));
+        Ok(MockCallbackHandle::new(
+            &self.shared,
+            Registration::NotificationClient,
+            id,
+        ))
+    }
+}
+
+/// A simulated audio endpoint.
+#[derive(Debug, Clone)]
+pub struct MockDevice {
+    shared: Shared,
+    id: WinString,
+}
+
+impl MockDevice {
+    fn new(shared: Shared, id: WinString) -> Self {
+        Self { shared, id }
+    }
+
+    fn with<R>(
+        &self,
+        operation: &'static str,
+        code: u32,
+        f: impl FnOnce(&mut DeviceData) -> Result<R>,
+    ) -> Result<R> {
+        self.shared.with_device(&self.id, operation, code, f)
+    }
+
+    /// Changes the state of the device, e.g. to simulate unplugging it.
+    pub fn set_state(&self, new_state: DeviceState) -> Result<()> {
+        self.with("IMMDevice::GetState", E_NOTFOUND, |device| {
+            device.state = new_state;
+            Ok(())
+        })?;
+        notify(self.shared.notification_clients(), |client| {
+            client.on_device_state_changed(&self.id, new_state)
+        });
+        Ok(())
+    }
+
+    /// Removes the device. Its sessions are disconnected, and the default
+    /// devices it was used for move to the first other active device with the
+    /// same data flow. If there is none, there is no longer a default device,
+    /// and [`on_default_device_changed`](NotificationClient::on_default_device_changed)
+    /// is called with an empty device ID.
+    pub fn remove(&self) -> Result<()> {
+        let (device, reassigned) = {
+            let mut state = self.shared.0.borrow_mut();
+            let index = state
+                .devices
+                .iter()
+                .position(|device| device.id == self.id)
+                .ok_or_else(|| Error::from_code("IMMDevice::GetState", E_NOTFOUND))?;
+            let device = state.devices.remove(index);
+            let replacement = state
+                .devices
+                .iter()
+                .find(|other| {
+                    other.data_flow == device.data_flow && other.state == DeviceState::Active
+                })
+                .map(|other| other.id.clone());
+            let mut reassigned = Vec::new();
+            for (data_flow, role, id) in &mut state.defaults {
+                if *id == self.id {
+                    // Windows reports an empty ID when no device takes over.
+                    let new_id = replacement.clone().unwrap_or_else(|| WinString::from(""));
+                    *id = new_id.clone();
+                    reassigned.push((*data_flow, *role, new_id));
+                }
+            }
+            state.defaults.retain(|(_, _, id)| !id.is_empty());
+            (device, reassigned)
+        };
+        notify(self.shared.notification_clients(), |client| {
+            client.on_device_removed(&self.id)
+        });
+        for (data_flow, role, id) in &reassigned {
+            notify(self.shared.notification_clients(), |client| {
+                client.on_default_device_changed(*data_flow, *role, id)
+            });
+        }
+        for session in &device.sessions {
+            notify(session.events.callbacks(), |events| {
+                events.on_session_disconnected(AudioSessionDisconnectReason::DeviceRemoval)
+            });
+        }
+        Ok(())
+    }
+
+    /// Changes the number of channels. New channels have the current master
+    /// volume.
+    pub fn set_channel_count(&self, channels: u32) -> Result<()> {
+        self.with(
+            "IAudioEndpointVolume::GetChannelCount",
+            E_NOTFOUND,
+            |device| {
+                let master = device.volume.master();
+                device.volume.channels.resize(channels as usize, master);
+                Ok(())
+            },
+        )
+    }
+
+    /// Changes the hardware functions the endpoint volume reports.
+    pub fn set_hardware_support(&self, mask: HardwareSupportMask) -> Result<()> {
+        self.with(
+            "IAudioEndpointVolume::QueryHardwareSupport",
+            E_NOTFOUND,
+            |device| {
+                device.volume.hardware_support = mask;
+                Ok(())
+            },
+        )
+    }
+
+    /// Sets a property directly, as a driver or the control panel would.
+    pub fn set_property(&self, key: &PropertyKey, value: Property) -> Result<()> {
+        self.with("IPropertyStore::SetValue", E_NOTFOUND, |device| {
+            device.properties.insert(*key, value);
+            Ok(())
+        })?;
+        notify(self.shared.notification_clients(), |client| {
+            client.on_property_value_changed(&self.id, *key)
+        });
+        Ok(())
+    }
+
+    /// Creates an inactive session on the device, as an application that
+    /// opens an audio stream would.
+    ///
+    /// A process ID of 0 creates the system sounds session.
+    pub fn add_session(
+        &self,
+        session_identifier: &WinStr,
+        process_id: u32,
+    ) -> Result<MockAudioSessionControl> {
+        let (session, callbacks) = {
+            let mut state = self.shared.0.borrow_mut();
+            let index = state.next_session;
+            state.next_session += 1;
+            let device = state.device(&self.id).ok_or_else(|| {
+                Error::from_code(
+                    "IAudioSessionManager2::GetSessionEnumerator",
+                    AUDCLNT_E_DEVICE_INVALIDATED,
+                )
+            })?;
+            let instance_id = WinString::from(
+                format!("{}%b{}#{}", session_identifier, process_id, index).as_str(),
+            );
+            device.sessions.push(SessionData {
+                instance_id: instance_id.clone(),
+                session_id: session_identifier.to_winstring(),
+                process_id,
+                display_name: WinString::from(""),
+                icon_path: WinString::from(""),
+                grouping_param: Guid::zeroed(),
+                state: AudioSessionState::Inactive,
+                volume: 1.0,
+                muted: false,
+                ducking_opt_out: false,
+                events: Registry::default(),
+            });
+            let session =
+                MockAudioSessionControl::new(self.shared.clone(), self.id.clone(), instance_id);
+            (session, device.session_callbacks.callbacks())
+        };
+        notify(callbacks, |callback| {
+            callback.on_session_created(session.clone())
+        });
+        Ok(session)
+    }
+
+    /// Notifies duck notification callbacks that a communications session
+    /// has started, as Windows does when ducking other streams.
+    pub fn duck(&self, session_id: &WinStr, num_communication_sessions: u32) -> Result<()> {
+        let callbacks = self.duck_callbacks()?;
+        notify(callbacks, |callback| {
+            callback.on_volume_duck_notification(session_id, num_communication_sessions)
+        });
+        Ok(())
+    }
+
+    /// Notifies duck notification callbacks that a communications session
+    /// has ended.
+    pub fn unduck(&self, session_id: &WinStr) -> Result<()> {
+        let callbacks = self.duck_callbacks()?;
+        notify(callbacks, |callback| {
+            callback.on_volume_unduck_notification(session_id)
+        });
+        Ok(())
+    }
+
+    fn duck_callbacks(&self) -> Result<Vec<Callback<dyn AudioVolumeDuckNotification>>> {
+        self.with(
+            "IAudioSessionManager2::RegisterDuckNotification",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| Ok(device.duck_callbacks.callbacks()),
+        )
+    }
+
+    fn activate(&self) -> Result<()> {
+        self.with("IMMDevice::Activate", E_NOTFOUND, |device| {
+            if device.state == DeviceState::Active {
+                Ok(())
+            } else {
+                Err(Error::from_code(
+                    "IMMDevice::Activate",
+                    AUDCLNT_E_DEVICE_INVALIDATED,
+                ))
+            }
+        })
+    }
+}
+
+impl DeviceBackend for MockDevice {
+    type PropertyStore = MockPropertyStore;
+    type AudioEndpointVolume = MockAudioEndpointVolume;
+    type AudioSessionManager = MockAudioSessionManager;
+
+    fn get_id(&self) -> Result<WinString> {
+        Ok(self.id.clone())
+    }
+
+    fn get_state(&self) -> Result<DeviceState> {
+        self.with("IMMDevice::GetState", E_NOTFOUND, |device| Ok(device.state))
+    }
+
+    fn open_property_store(
+        &self,
+        storage_access_mode: StorageAccessMode,
+    ) -> Result<MockPropertyStore> {
+        self.with("IMMDevice::OpenPropertyStore", E_NOTFOUND, |_| Ok(()))?;
+        Ok(MockPropertyStore {
+            device: self.clone(),
+            storage_access_mode,
+            pending: Rc::default(),
+        })
+    }
+
+    fn activate_audio_endpoint_volume(&self) -> Result<MockAudioEndpointVolume> {
+        self.activate()?;
+        Ok(MockAudioEndpointVolume {
+            device: self.clone(),
+        })
+    }
+
+    fn activate_audio_session_manager2(&self) -> Result<MockAudioSessionManager> {
+        self.activate()?;
+        Ok(MockAudioSessionManager {
+            device: self.clone(),
+        })
+    }
+}
+
+/// The property store of a [`MockDevice`].
+///
+/// Values set through the store are only visible to other stores after
+/// [`commit`](PropertyStoreBackend::commit).
+#[derive(Debug, Clone)]
+pub struct MockPropertyStore {
+    device: MockDevice,
+    storage_access_mode: StorageAccessMode,
+    pending: Rc<RefCell<PropertySnapshot>>,
+}
+
+impl MockPropertyStore {
+    /// The committed values, overlaid with the pending ones.
+    fn values(&self, operation: &'static str) -> Result<PropertySnapshot> {
+        let mut values = self
+            .device
+            .with(operation, AUDCLNT_E_DEVICE_INVALIDATED, |device| {
+                Ok(device.properties.clone())
+            })?;
+        values.extend(
+            self.pending
+                .borrow()
+                .iter()
+                .map(|(key, value)| (*key, value.clone())),
+        );
+        Ok(values)
+    }
+}
+
+impl PropertyStoreBackend for MockPropertyStore {
+    fn commit(&self) -> Result<()> {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let changed: Vec<PropertyKey> = self.device.with(
+            "IPropertyStore::Commit",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| {
+                Ok(pending
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        let old = device.properties.insert(key, value.clone());
+                        if old.as_ref() != Some(&value) {
+                            Some(key)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect())
+            },
+        )?;
+        for key in changed {
+            notify(self.device.shared.notification_clients(), |client| {
+                client.on_property_value_changed(&self.device.id, key)
+            });
+        }
+        Ok(())
+    }
+
+    fn get_at(&self, index: u32) -> Result<PropertyKey> {
+        self.values("IPropertyStore::GetAt")?
+            .keys()
+            .nth(index as usize)
+            .copied()
+            .ok_or_else(|| Error::from_code("IPropertyStore::GetAt", E_INVALIDARG))
+    }
+
+    fn get_count(&self) -> Result<u32> {
+        Ok(self.values("IPropertyStore::GetCount")?.len() as u32)
+    }
+
+    fn get_value(&self, key: &PropertyKey) -> Result<Property> {
+        Ok(self
+            .values("IPropertyStore::GetValue")?
+            .get(key)
+            .cloned()
+            .unwrap_or(Property::Empty))
+    }
+
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> Result<()> {
+        if self.storage_access_mode == StorageAccessMode::Read {
+            return Err(Error::from_code(
+                "IPropertyStore::SetValue",
+                STG_E_ACCESSDENIED,
+            ));
+        }
+        if *property == Property::Unsupported {
+            return Err(Error::from_code("PropVariant::new", E_INVALIDARG));
+        }
+        self.pending.borrow_mut().insert(*key, property.clone());
+        Ok(())
+    }
+}
+
+/// The endpoint volume of a [`MockDevice`].
+///
+/// Volume levels in decibels map linearly to scalar levels over the volume
+/// range.
+#[derive(Debug, Clone)]
+pub struct MockAudioEndpointVolume {
+    device: MockDevice,
+}
+
+impl MockAudioEndpointVolume {
+    fn with<R>(
+        &self,
+        operation: &'static str,
+        f: impl FnOnce(&mut VolumeData) -> Result<R>,
+    ) -> Result<R> {
+        self.device
+            .with(operation, AUDCLNT_E_DEVICE_INVALIDATED, |device| {
+                f(&mut device.volume)
+            })
+    }
+
+    /// Changes the volume, then notifies the callbacks.
+    fn update(
+        &self,
+        operation: &'static str,
+        event_context: Option<&Guid>,
+        f: impl FnOnce(&mut VolumeData) -> Result<()>,
+    ) -> Result<()> {
+        let (muted, master_volume, channel_volumes, callbacks) =
+            self.device
+                .with(operation, AUDCLNT_E_DEVICE_INVALIDATED, |device| {
+                    f(&mut device.volume)?;
+                    Ok((
+                        device.volume.muted,
+                        device.volume.master(),
+                        device.volume.channels.clone(),
+                        device.volume_callbacks.callbacks(),
+                    ))
+                })?;
+        notify(callbacks, |callback| {
+            callback.on_notify(&NotificationData {
+                event_context: event_context.copied().unwrap_or_else(Guid::zeroed),
+                muted,
+                master_volume,
+                channel_volumes: &channel_volumes,
+            })
+        });
+        Ok(())
+    }
+
+    fn channel<'a>(
+        volume: &'a mut VolumeData,
+        operation: &'static str,
+        channel: u32,
+    ) -> Result<&'a mut f32> {
+        volume
+            .channels
+            .get_mut(channel as usize)
+            .ok_or_else(|| Error::from_code(operation, E_INVALIDARG))
+    }
+}
+
+fn check_scalar(operation: &'static str, level: f32) -> Result<f32> {
+    if (0.0..=1.0).contains(&level) {
+        Ok(level)
+    } else {
+        Err(Error::from_code(operation, E_INVALIDARG))
+    }
+}
+
+impl AudioEndpointVolumeBackend for MockAudioEndpointVolume {
+    type CallbackHandle = MockCallbackHandle;
+
+    fn get_channel_count(&self) -> Result<u32> {
+        self.with("IAudioEndpointVolume::GetChannelCount", |volume| {
+            Ok(volume.channels.len() as u32)
+        })
+    }
+
+    fn get_channel_volume_level(&self, channel: u32) -> Result<f32> {
+        const OPERATION: &str = "IAudioEndpointVolume::GetChannelVolumeLevel";
+        self.with(OPERATION, |volume| {
+            let level = *Self::channel(volume, OPERATION, channel)?;
+            Ok(volume.scalar_to_db(level))
+        })
+    }
+
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> Result<f32> {
+        const OPERATION: &str = "IAudioEndpointVolume::GetChannelVolumeLevelScalar";
+        self.with(OPERATION, |volume| {
+            Self::channel(volume, OPERATION, channel).map(|level| *level)
+        })
+    }
+
+    fn get_master_volume_level(&self) -> Result<f32> {
+        self.with("IAudioEndpointVolume::GetMasterVolumeLevel", |volume| {
+            Ok(volume.scalar_to_db(volume.master()))
+        })
+    }
+
+    fn get_master_volume_level_scalar(&self) -> Result<f32> {
+        self.with(
+            "IAudioEndpointVolume::GetMasterVolumeLevelScalar",
+            |volume| Ok(volume.master()),
+        )
+    }
+
+    fn get_mute(&self) -> Result<bool> {
+        self.with("IAudioEndpointVolume::GetMute", |volume| Ok(volume.muted))
+    }
+
+    fn get_volume_range(&self) -> Result<VolumeRange> {
+        self.with("IAudioEndpointVolume::GetVolumeRange", |volume| {
+            Ok(volume.range.clone())
+        })
+    }
+
+    fn get_volume_step_info(&self) -> Result<VolumeStepInfo> {
+        self.with("IAudioEndpointVolume::GetVolumeStepInfo", |volume| {
+            Ok(VolumeStepInfo {
+                current_step: volume.current_step(),
+                num_steps: volume.num_steps,
+            })
+        })
+    }
+
+    fn query_hardware_support(&self) -> Result<HardwareSupportMask> {
+        self.with("IAudioEndpointVolume::QueryHardwareSupport", |volume| {
+            Ok(volume.hardware_support)
+        })
+    }
+
+    fn register_control_change_notify<T>(&self, callback: T) -> Result<MockCallbackHandle>
+    where
+        T: AudioEndpointVolumeCallback,
+    {
+        let id = self.device.with(
+            "IAudioEndpointVolume::RegisterControlChangeNotify",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| Ok(device.volume_callbacks.add(Box::new(callback))),
+        )?;
+        Ok(MockCallbackHandle::new(
+            &self.device.shared,
+            Registration::AudioEndpointVolume(self.device.id.clone()),
+            id,
+        ))
+    }
+
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        const OPERATION: &str = "IAudioEndpointVolume::SetChannelVolumeLevel";
+        self.update(OPERATION, event_context, |volume| {
+            let level = volume
+                .db_to_scalar(level_db)
+                .ok_or_else(|| Error::from_code(OPERATION, E_INVALIDARG))?;
+            *Self::channel(volume, OPERATION, channel)? = level;
+            Ok(())
+        })
+    }
+
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        const OPERATION: &str = "IAudioEndpointVolume::SetChannelVolumeLevelScalar";
+        let level = check_scalar(OPERATION, level)?;
+        self.update(OPERATION, event_context, |volume| {
+            *Self::channel(volume, OPERATION, channel)? = level;
+            Ok(())
+        })
+    }
+
+    fn set_master_volume_level(&self, level_db: f32, event_context: Option<&Guid>) -> Result<()> {
+        const OPERATION: &str = "IAudioEndpointVolume::SetMasterVolumeLevel";
+        self.update(OPERATION, event_context, |volume| {
+            let level = volume
+                .db_to_scalar(level_db)
+                .ok_or_else(|| Error::from_code(OPERATION, E_INVALIDARG))?;
+            volume.set_master(level);
+            Ok(())
+        })
+    }
+
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&Guid>,
+    ) -> Result<()> {
+        const OPERATION: &str = "IAudioEndpointVolume::SetMasterVolumeLevelScalar";
+        let level = check_scalar(OPERATION, level)?;
+        self.update(OPERATION, event_context, |volume| {
+            volume.set_master(level);
+            Ok(())
+        })
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
+        self.update("IAudioEndpointVolume::SetMute", event_context, |volume| {
+            volume.muted = mute;
+            Ok(())
+        })
+    }
+
+    fn volume_step_down(&self, event_context: Option<&Guid>) -> Result<()> {
+        self.update(
+            "IAudioEndpointVolume::VolumeStepDown",
+            event_context,
+            |volume| {
+                volume.set_step(volume.current_step().saturating_sub(1));
+                Ok(())
+            },
+        )
+    }
+
+    fn volume_step_up(&self, event_context: Option<&Guid>) -> Result<()> {
+        self.update(
+            "IAudioEndpointVolume::VolumeStepUp",
+            event_context,
+            |volume| {
+                volume.set_step((volume.current_step() + 1).min(volume.num_steps - 1));
+                Ok(())
+            },
+        )
+    }
+}
+
+/// The session manager of a [`MockDevice`].
+#[derive(Debug, Clone)]
+pub struct MockAudioSessionManager {
+    device: MockDevice,
+}
+
+impl AudioSessionManagerBackend for MockAudioSessionManager {
+    type AudioSessionControl = MockAudioSessionControl;
+    type SessionNotificationHandle = MockCallbackHandle;
+    type DuckNotificationHandle = MockCallbackHandle;
+
+    fn get_sessions(&self) -> Result<Vec<MockAudioSessionControl>> {
+        self.device.with(
+            "IAudioSessionManager2::GetSessionEnumerator",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| {
+                Ok(device
+                    .sessions
+                    .iter()
+                    .map(|session| {
+                        MockAudioSessionControl::new(
+                            self.device.shared.clone(),
+                            self.device.id.clone(),
+                            session.instance_id.clone(),
+                        )
+                    })
+                    .collect())
+            },
+        )
+    }
+
+    /// Duck notifications are sent to every registered callback, regardless
+    /// of `session_id`. Use [`MockDevice::duck`] and [`MockDevice::unduck`]
+    /// to trigger them.
+    fn register_duck_notification<T>(
+        &self,
+        session_id: &WinStr,
+        duck_notification: T,
+    ) -> Result<MockCallbackHandle>
+    where
+        T: AudioVolumeDuckNotification,
+    {
+        let _ = session_id;
+        let id = self.device.with(
+            "IAudioSessionManager2::RegisterDuckNotification",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| Ok(device.duck_callbacks.add(Box::new(duck_notification))),
+        )?;
+        Ok(MockCallbackHandle::new(
+            &self.device.shared,
+            Registration::DuckNotification(self.device.id.clone()),
+            id,
+        ))
+    }
+
+    fn register_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<MockCallbackHandle>
+    where
+        T: SessionNotification<MockAudioSessionControl>,
+    {
+        let id = self.device.with(
+            "IAudioSessionManager2::RegisterSessionNotification",
+            AUDCLNT_E_DEVICE_INVALIDATED,
+            |device| Ok(device.session_callbacks.add(Box::new(session_notification))),
+        )?;
+        Ok(MockCallbackHandle::new(
+            &self.device.shared,
+            Registration::SessionNotification(self.device.id.clone()),
+            id,
+        ))
+    }
+}
+
+/// A simulated audio session.
+#[derive(Debug, Clone)]
+pub struct MockAudioSessionControl {
+    shared: Shared,
+    device_id: WinString,
+    instance_id: WinString,
+}
+
+impl MockAudioSessionControl {
+    fn new(shared: Shared, device_id: WinString, instance_id: WinString) -> Self {
+        Self {
+            shared,
+            device_id,
+            instance_id,
+        }
+    }
+
+    fn with<R>(
+        &self,
+        operation: &'static str,
+        f: impl FnOnce(&mut SessionData) -> Result<R>,
+    ) -> Result<R> {
+        self.shared
+            .with_session(&self.device_id, &self.instance_id, operation, f)
+    }
+
+    /// Changes the session, then notifies its event callbacks.
+    fn update(
+        &self,
+        operation: &'static str,
+        f: impl FnOnce(&mut SessionData),
+        event: impl FnMut(&mut dyn AudioSessionEvents) -> windows::Result<()>,
+    ) -> Result<()> {
+        let callbacks = self.with(operation, |session| {
+            f(session);
+            Ok(session.events.callbacks())
+        })?;
+        notify(callbacks, event);
+        Ok(())
+    }
+
+    /// Changes the state of the session, e.g. to simulate an application
+    /// starting or stopping playback.
+    pub fn set_state(&self, new_state: AudioSessionState) -> Result<()> {
+        self.update(
+            "IAudioSessionControl::GetState",
+            |session| session.state = new_state,
+            |events| events.on_state_changed(new_state),
+        )
+    }
+
+    /// Disconnects the session, which leaves it expired.
+    pub fn disconnect(&self, reason: AudioSessionDisconnectReason) -> Result<()> {
+        self.update(
+            "IAudioSessionControl::GetState",
+            |session| session.state = AudioSessionState::Expired,
+            |events| events.on_session_disconnected(reason),
+        )
+    }
+}
+
+impl AudioSessionControlBackend for MockAudioSessionControl {
+    type SimpleAudioVolume = MockSimpleAudioVolume;
+    type AudioSessionEventsHandle = MockCallbackHandle;
+
+    fn get_display_name(&self) -> Result<WinString> {
+        self.with("IAudioSessionControl::GetDisplayName", |session| {
+            Ok(session.display_name.clone())
+        })
+    }
+
+    fn get_grouping_param(&self) -> Result<Guid> {
+        self.with("IAudioSessionControl::GetGroupingParam", |session| {
+            Ok(session.grouping_param)
+        })
+    }
+
+    fn get_icon_path(&self) -> Result<WinString> {
+        self.with("IAudioSessionControl::GetIconPath", |session| {
+            Ok(session.icon_path.clone())
+        })
+    }
+
+    fn get_state(&self) -> Result<AudioSessionState> {
+        self.with(
+            "IAudioSessionControl::GetState",
+            |session| Ok(session.state),
+        )
+    }
+
+    fn register_audio_session_notification<T>(
+        &self,
+        session_notification: T,
+    ) -> Result<MockCallbackHandle>
+    where
+        T: AudioSessionEvents,
+    {
+        let id = self.with(
+            "IAudioSessionControl::RegisterAudioSessionNotification",
+            |session| Ok(session.events.add(Box::new(session_notification))),
+        )?;
+        Ok(MockCallbackHandle::new(
+            &self.shared,
+            Registration::AudioSessionEvents(self.device_id.clone(), self.instance_id.clone()),
+            id,
+        ))
+    }
+
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
+        self.update(
+            "IAudioSessionControl::SetDisplayName",
+            |session| session.display_name = value.to_winstring(),
+            |events| events.on_display_name_changed(value, event_context),
+        )
+    }
+
+    fn set_grouping_param(&self, value: &Guid, event_context: Option<&Guid>) -> Result<()> {
+        self.update(
+            "IAudioSessionControl::SetGroupingParam",
+            |session| session.grouping_param = *value,
+            |events| events.on_grouping_param_changed(Some(value), event_context),
+        )
+    }
+
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&Guid>) -> Result<()> {
+        self.update(
+            "IAudioSessionControl::SetIconPath",
+            |session| session.icon_path = value.to_winstring(),
+            |events| events.on_icon_path_changed(value, event_context),
+        )
+    }
+
+    fn get_simple_audio_volume(&self) -> Result<MockSimpleAudioVolume> {
+        self.with("IUnknown::QueryInterface", |_| Ok(()))?;
+        Ok(MockSimpleAudioVolume {
+            session: self.clone(),
+        })
+    }
+
+    fn get_process_id(&self) -> Result<u32> {
+        self.with("IAudioSessionControl2::GetProcessId", |session| {
+            Ok(session.process_id)
+        })
+    }
+
+    fn get_session_identifier(&self) -> Result<WinString> {
+        self.with("IAudioSessionControl2::GetSessionIdentifier", |session| {
+            Ok(session.session_id.clone())
+        })
+    }
+
+    fn get_session_instance_identifier(&self) -> Result<WinString> {
+        Ok(self.instance_id.clone())
+    }
+
+    fn is_system_sounds_session(&self) -> bool {
+        self.with("IAudioSessionControl2::IsSystemSoundsSession", |session| {
+            Ok(session.process_id == 0)
+        })
+        .unwrap_or(false)
+    }
+
+    fn set_ducking_preference(&self, opt_out: bool) -> Result<()> {
+        self.with("IAudioSessionControl2::SetDuckingPreference", |session| {
+            session.ducking_opt_out = opt_out;
+            Ok(())
+        })
+    }
+}
+
+/// The volume of a [`MockAudioSessionControl`].
+#[derive(Debug, Clone)]
+pub struct MockSimpleAudioVolume {
+    session: MockAudioSessionControl,
+}
+
+impl MockSimpleAudioVolume {
+    fn update(
+        &self,
+        operation: &'static str,
+        event_context: Option<&Guid>,
+        f: impl FnOnce(&mut SessionData),
+    ) -> Result<()> {
+        let mut changed = (0.0, false);
+        let callbacks = self.session.with(operation, |session| {
+            f(session);
+            changed = (session.volume, session.muted);
+            Ok(session.events.callbacks())
+        })?;
+        let (new_volume, new_mute) = changed;
+        notify(callbacks, |events| {
+            events.on_simple_volume_changed(new_volume, new_mute, event_context)
+        });
+        Ok(())
+    }
+}
+
+impl SimpleAudioVolumeBackend for MockSimpleAudioVolume {
+    fn get_master_volume(&self) -> Result<f32> {
+        self.session
+            .with("ISimpleAudioVolume::GetMasterVolume", |session| {
+                Ok(session.volume)
+            })
+    }
+
+    fn get_mute(&self) -> Result<bool> {
+        self.session
+            .with("ISimpleAudioVolume::GetMute", |session| Ok(session.muted))
+    }
+
+    fn set_master_volume(&self, volume_level: f32, event_context: Option<&Guid>) -> Result<()> {
+        const OPERATION: &str = "ISimpleAudioVolume::SetMasterVolume";
+        let volume_level = check_scalar(OPERATION, volume_level)?;
+        self.update(OPERATION, event_context, |session| {
+            session.volume = volume_level
+        })
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
+        self.update("ISimpleAudioVolume::SetMute", event_context, |session| {
+            session.muted = mute
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Registration {
+    NotificationClient,
+    AudioEndpointVolume(WinString),
+    SessionNotification(WinString),
+    DuckNotification(WinString),
+    AudioSessionEvents(WinString, WinString),
+}
+
+/// Unregisters a callback from a [`MockBackend`] when dropped.
+#[derive(Debug)]
+#[must_use = "callback will be unregistered when the handle is dropped"]
+pub struct MockCallbackHandle {
+    shared: Weak<RefCell<State>>,
+    registration: Registration,
+    id: u64,
+}
+
+impl MockCallbackHandle {
+    fn new(shared: &Shared, registration: Registration, id: u64) -> Self {
+        Self {
+            shared: Rc::downgrade(&shared.0),
+            registration,
+            id,
+        }
+    }
+
+    pub fn unregister(self) {
+        // Handled by Drop impl
+    }
+}
+
+impl Drop for MockCallbackHandle {
+    fn drop(&mut self) {
+        let shared = match self.shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+        let mut state = shared.borrow_mut();
+        let id = self.id;
+        // The callback may own other handles, so it must be dropped after the
+        // state is released.
+        let removed: Option<Box<dyn Any>> = match &self.registration {
+            Registration::NotificationClient => state
+                .notification_clients
+                .remove(id)
+                .map(|callback| Box::new(callback) as _),
+            Registration::AudioEndpointVolume(device_id) => state
+                .device(device_id)
+                .and_then(|device| device.volume_callbacks.remove(id))
+                .map(|callback| Box::new(callback) as _),
+            Registration::SessionNotification(device_id) => state
+                .device(device_id)
+                .and_then(|device| device.session_callbacks.remove(id))
+                .map(|callback| Box::new(callback) as _),
+            Registration::DuckNotification(device_id) => state
+                .device(device_id)
+                .and_then(|device| device.duck_callbacks.remove(id))
+                .map(|callback| Box::new(callback) as _),
+            Registration::AudioSessionEvents(device_id, instance_id) => state
+                .device(device_id)
+                .and_then(|device| device.session(instance_id))
+                .and_then(|session| session.events.remove(id))
+                .map(|callback| Box::new(callback) as _),
+        };
+        drop(state);
+        drop(removed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio_session_events::AudioSessionEventsBuilder, error::ErrorKind,
+        notification_client::NotificationClientBuilder, pkeys, winstr,
+    };
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn register_client(backend: &MockBackend, log: &Log) -> MockCallbackHandle {
+        let (removed, default_changed, property_changed) = (log.clone(), log.clone(), log.clone());
+        backend
+            .register_endpoint_notification(
+                NotificationClientBuilder::new()
+                    .on_device_removed(move |id| {
+                        removed.borrow_mut().push(format!("removed {}", id))
+                    })
+                    .on_default_device_changed(move |data_flow, role, id| {
+                        default_changed
+                            .borrow_mut()
+                            .push(format!("default {} {} {}", data_flow, role, id))
+                    })
+                    .on_property_value_changed(move |id, key| {
+                        property_changed
+                            .borrow_mut()
+                            .push(format!("property {} {}", id, key))
+                    }),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn remove_reassigns_default_device() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        backend.add_device(winstr!("headphones"), DataFlow::Render);
+        backend.add_device(winstr!("mic"), DataFlow::Capture);
+        let log = Log::default();
+        let _handle = register_client(&backend, &log);
+
+        speakers.remove().unwrap();
+
+        let mut expected = vec!["removed speakers".to_owned()];
+        for role in DeviceRole::ALL {
+            expected.push(format!("default Render {} headphones", role));
+            let device = backend
+                .get_default_audio_endpoint(DataFlow::Render, *role)
+                .unwrap();
+            assert_eq!(device.get_id().unwrap(), *winstr!("headphones"));
+            let device = backend
+                .get_default_audio_endpoint(DataFlow::Capture, *role)
+                .unwrap();
+            assert_eq!(device.get_id().unwrap(), *winstr!("mic"));
+        }
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn remove_last_device_clears_default() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let mic = backend.add_device(winstr!("mic"), DataFlow::Capture);
+        mic.set_state(DeviceState::Disabled).unwrap();
+        let other = backend.add_device(winstr!("other mic"), DataFlow::Capture);
+        other.set_state(DeviceState::Unplugged).unwrap();
+        let log = Log::default();
+        let _handle = register_client(&backend, &log);
+
+        speakers.remove().unwrap();
+        mic.remove().unwrap();
+
+        let mut expected = vec!["removed speakers".to_owned()];
+        expected.extend(
+            DeviceRole::ALL
+                .iter()
+                .map(|role| format!("default Render {} ", role)),
+        );
+        expected.push("removed mic".to_owned());
+        expected.extend(
+            DeviceRole::ALL
+                .iter()
+                .map(|role| format!("default Capture {} ", role)),
+        );
+        assert_eq!(*log.borrow(), expected);
+
+        for &data_flow in DataFlow::ALL {
+            let err = backend
+                .get_default_audio_endpoint(data_flow, DeviceRole::Console)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+        }
+    }
+
+    #[test]
+    fn removed_device_is_invalidated() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let volume = speakers.activate_audio_endpoint_volume().unwrap();
+        let store = speakers
+            .open_property_store(StorageAccessMode::Read)
+            .unwrap();
+        let session = speakers.add_session(winstr!("app"), 42).unwrap();
+        let reasons = Rc::new(RefCell::new(Vec::new()));
+        let _handle = {
+            let reasons = reasons.clone();
+            session
+                .register_audio_session_notification(
+                    AudioSessionEventsBuilder::new()
+                        .on_session_disconnected(move |reason| reasons.borrow_mut().push(reason)),
+                )
+                .unwrap()
+        };
+
+        speakers.remove().unwrap();
+
+        assert_eq!(
+            *reasons.borrow(),
+            [AudioSessionDisconnectReason::DeviceRemoval]
+        );
+        let errors = [
+            volume.get_mute().unwrap_err(),
+            volume.volume_step_up(None).unwrap_err(),
+            store.get_count().unwrap_err(),
+            session.get_state().unwrap_err(),
+        ];
+        for err in &errors {
+            assert_eq!(err.kind(), ErrorKind::DeviceInvalidated, "{}", err);
+        }
+        let err = backend.get_device(winstr!("speakers")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(speakers.remove().unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn setting_the_same_default_does_not_notify() {
+        let backend = MockBackend::new();
+        backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let log = Log::default();
+        let _handle = register_client(&backend, &log);
+
+        backend
+            .set_default_device(DataFlow::Render, DeviceRole::Console, winstr!("speakers"))
+            .unwrap();
+
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn read_only_store_denies_writes() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let key = pkeys::DEVICE_FRIENDLY_NAME.key();
+        let value = Property::Str(WinString::from("Speakers"));
+
+        let store = speakers
+            .open_property_store(StorageAccessMode::Read)
+            .unwrap();
+        let err = store.set_value(&key, &value).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AccessDenied);
+        assert_eq!(err.operation(), "IPropertyStore::SetValue");
+        assert_eq!(store.get_value(&key).unwrap(), Property::Empty);
+
+        let store = speakers
+            .open_property_store(StorageAccessMode::ReadWrite)
+            .unwrap();
+        let err = store.set_value(&key, &Property::Unsupported).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        store.set_value(&key, &value).unwrap();
+        assert_eq!(store.get_value(&key).unwrap(), value);
+    }
+
+    #[test]
+    fn commit_notifies_changed_keys_only() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let name = pkeys::DEVICE_FRIENDLY_NAME.key();
+        let description = pkeys::DEVICE_DESCRIPTION.key();
+        speakers
+            .set_property(&name, Property::Str(WinString::from("Speakers")))
+            .unwrap();
+        let log = Log::default();
+        let _handle = register_client(&backend, &log);
+
+        let store = speakers
+            .open_property_store(StorageAccessMode::ReadWrite)
+            .unwrap();
+        store
+            .set_value(&name, &Property::Str(WinString::from("Speakers")))
+            .unwrap();
+        store
+            .set_value(&description, &Property::Str(WinString::from("Line out")))
+            .unwrap();
+        let other = speakers
+            .open_property_store(StorageAccessMode::Read)
+            .unwrap();
+        assert_eq!(other.get_value(&description).unwrap(), Property::Empty);
+        assert!(log.borrow().is_empty());
+
+        store.commit().unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [format!("property speakers {}", description)]
+        );
+        assert_eq!(
+            other.get_value(&description).unwrap(),
+            Property::Str(WinString::from("Line out"))
+        );
+        store.commit().unwrap();
+        assert_eq!(log.borrow().len(), 1);
+    }
+
+    #[test]
+    fn handles_unregister_on_drop() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let volume = speakers.activate_audio_endpoint_volume().unwrap();
+        let log = Log::default();
+        let client = register_client(&backend, &log);
+        let notified = Rc::new(RefCell::new(0));
+        let volume_handle = {
+            let notified = notified.clone();
+            volume
+                .register_control_change_notify(move |_: &NotificationData| {
+                    *notified.borrow_mut() += 1
+                })
+                .unwrap()
+        };
+        assert_eq!(backend.registered_callbacks(), 2);
+
+        drop(client);
+        volume_handle.unregister();
+
+        assert_eq!(backend.registered_callbacks(), 0);
+        speakers.remove().unwrap();
+        volume.set_mute(true, None).unwrap_err();
+        assert!(log.borrow().is_empty());
+        assert_eq!(*notified.borrow(), 0);
+    }
+
+    #[test]
+    fn handle_outliving_backend_is_harmless() {
+        let backend = MockBackend::new();
+        let handle = register_client(&backend, &Log::default());
+        drop(backend);
+        drop(handle);
+    }
+
+    #[test]
+    fn volume_steps_are_clamped() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let volume = speakers.activate_audio_endpoint_volume().unwrap();
+        let step = || volume.get_volume_step_info().unwrap().current_step;
+        assert_eq!(volume.get_volume_step_info().unwrap().num_steps, 51);
+        assert_eq!(step(), 50);
+
+        volume.volume_step_up(None).unwrap();
+        assert_eq!(step(), 50);
+        assert_eq!(volume.get_master_volume_level_scalar().unwrap(), 1.0);
+
+        volume.volume_step_down(None).unwrap();
+        assert_eq!(step(), 49);
+
+        volume.set_master_volume_level_scalar(0.0, None).unwrap();
+        volume.volume_step_down(None).unwrap();
+        assert_eq!(step(), 0);
+        assert_eq!(volume.get_master_volume_level_scalar().unwrap(), 0.0);
+
+        volume.volume_step_up(None).unwrap();
+        assert_eq!(step(), 1);
+    }
+}
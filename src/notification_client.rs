@@ -1,3 +1,6 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
+
 use crate::bindings::*;
 use crate::bits::{DataFlow, DeviceRole, DeviceState};
 use crate::string::WinStr;
@@ -8,10 +11,14 @@ use crate::{
         System::PropertiesSystem::PROPERTYKEY,
     },
     property_store::PropertyKey,
+    util::Handler,
 };
 
 /// See also: [`IMMNotificationClient`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immnotificationclient)
 pub trait NotificationClient: 'static {
+    /// `device_id` is empty if there is no longer a default device for the
+    /// data flow and role, e.g. after the last one was removed.
+    ///
     /// See also: [`IMMNotificationClient::OnDefaultDeviceChanged`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immnotificationclient-ondefaultdevicechanged)
     fn on_default_device_changed(
         &mut self,
@@ -56,6 +63,143 @@ pub trait NotificationClient: 'static {
     }
 }
 
+type DefaultDeviceChangedFn = dyn FnMut(DataFlow, DeviceRole, &WinStr);
+type DeviceIdFn = dyn FnMut(&WinStr);
+type DeviceStateChangedFn = dyn FnMut(&WinStr, DeviceState);
+type PropertyValueChangedFn = dyn FnMut(&WinStr, PropertyKey);
+
+/// A [`NotificationClient`] made of a closure for each notification.
+///
+/// Notifications without a closure are ignored.
+///
+/// ```no_run
+/// use win32_coreaudio::{DeviceEnumerator, NotificationClientBuilder};
+///
+/// let enumerator = DeviceEnumerator::new()?;
+/// let _handle = enumerator.register_endpoint_notification(
+///     NotificationClientBuilder::new()
+///         .on_device_added(|device_id| println!("added {}", device_id))
+///         .on_device_removed(|device_id| println!("removed {}", device_id)),
+/// )?;
+/// # Ok::<(), win32_coreaudio::Error>(())
+/// ```
+#[derive(Default)]
+pub struct NotificationClientBuilder {
+    default_device_changed: Handler<DefaultDeviceChangedFn>,
+    device_added: Handler<DeviceIdFn>,
+    device_removed: Handler<DeviceIdFn>,
+    device_state_changed: Handler<DeviceStateChangedFn>,
+    property_value_changed: Handler<PropertyValueChangedFn>,
+}
+
+impl NotificationClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See also: [`NotificationClient::on_default_device_changed`]
+    pub fn on_default_device_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(DataFlow, DeviceRole, &WinStr) + 'static,
+    {
+        self.default_device_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`NotificationClient::on_device_added`]
+    pub fn on_device_added<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr) + 'static,
+    {
+        self.device_added = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`NotificationClient::on_device_removed`]
+    pub fn on_device_removed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr) + 'static,
+    {
+        self.device_removed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`NotificationClient::on_device_state_changed`]
+    pub fn on_device_state_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr, DeviceState) + 'static,
+    {
+        self.device_state_changed = Some(Box::new(f));
+        self
+    }
+
+    /// See also: [`NotificationClient::on_property_value_changed`]
+    pub fn on_property_value_changed<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WinStr, PropertyKey) + 'static,
+    {
+        self.property_value_changed = Some(Box::new(f));
+        self
+    }
+}
+
+impl NotificationClient for NotificationClientBuilder {
+    fn on_default_device_changed(
+        &mut self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.default_device_changed {
+            f(data_flow, role, device_id);
+        }
+        Ok(())
+    }
+
+    fn on_device_added(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        if let Some(f) = &mut self.device_added {
+            f(device_id);
+        }
+        Ok(())
+    }
+
+    fn on_device_removed(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        if let Some(f) = &mut self.device_removed {
+            f(device_id);
+        }
+        Ok(())
+    }
+
+    fn on_device_state_changed(
+        &mut self,
+        device_id: &WinStr,
+        state: DeviceState,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.device_state_changed {
+            f(device_id, state);
+        }
+        Ok(())
+    }
+
+    fn on_property_value_changed(
+        &mut self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> windows::Result<()> {
+        if let Some(f) = &mut self.property_value_changed {
+            f(device_id, property_key);
+        }
+        Ok(())
+    }
+}
+
+impl Debug for NotificationClientBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationClientBuilder")
+            .finish_non_exhaustive()
+    }
+}
+
 #[windows::implement(Windows::Win32::Media::Audio::CoreAudio::IMMNotificationClient)]
 pub(crate) struct NotificationClientWrapper {
     inner: Box<dyn NotificationClient>,
@@ -81,10 +225,16 @@ impl NotificationClientWrapper {
         role: ERole,
         device_id: PWSTR,
     ) -> windows::Result<()> {
+        // Windows passes NULL when there is no longer a default device.
+        let device_id = if device_id.is_null() {
+            crate::winstr!("")
+        } else {
+            unsafe { WinStr::from_pwstr(&device_id) }
+        };
         self.inner.on_default_device_changed(
-            DataFlow::from_raw(flow),
-            DeviceRole::from_raw(role),
-            unsafe { WinStr::from_pwstr(&device_id) },
+            DataFlow::try_from(flow.0)?,
+            DeviceRole::try_from(role.0)?,
+            device_id,
         )
     }
 
@@ -101,7 +251,7 @@ impl NotificationClientWrapper {
     fn OnDeviceStateChanged(&mut self, device_id: PWSTR, new_state: u32) -> windows::Result<()> {
         self.inner.on_device_state_changed(
             unsafe { WinStr::from_pwstr(&device_id) },
-            DeviceState::from_raw(new_state),
+            DeviceState::from(new_state),
         )
     }
 
@@ -116,3 +266,44 @@ impl NotificationClientWrapper {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::string::WinString;
+
+    #[test]
+    fn null_default_device_is_empty() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut wrapper = NotificationClientWrapper::new({
+            let changes = changes.clone();
+            NotificationClientBuilder::new().on_default_device_changed(
+                move |data_flow, role, device_id| {
+                    changes
+                        .borrow_mut()
+                        .push((data_flow, role, device_id.to_winstring()))
+                },
+            )
+        });
+
+        wrapper
+            .OnDefaultDeviceChanged(
+                EDataFlow(DataFlow::Capture.to_raw()),
+                ERole(DeviceRole::Communications.to_raw()),
+                PWSTR(std::ptr::null_mut()),
+            )
+            .unwrap();
+
+        assert_eq!(
+            *changes.borrow(),
+            [(
+                DataFlow::Capture,
+                DeviceRole::Communications,
+                WinString::from("")
+            )]
+        );
+    }
+}
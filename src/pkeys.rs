@@ -0,0 +1,223 @@
+//! Catalog of well-known property keys for audio endpoint devices.
+//!
+//! See also: [Audio Endpoint Properties](https://docs.microsoft.com/en-us/windows/win32/coreaudio/audio-endpoint-properties)
+
+use windows::Guid;
+
+use crate::{
+    bindings::Windows::Win32::System::SystemServices::{
+        DEVPKEY_DeviceInterface_FriendlyName, DEVPKEY_Device_DeviceDesc,
+        DEVPKEY_Device_FriendlyName,
+    },
+    bits::{EndpointFormFactor, JackSubType},
+    property_store::{Property, PropertyKey},
+    string::WinString,
+    typed_property::TypedPropertyKey,
+    wave_format::WaveFormat,
+};
+
+const fn key<T>(fmtid: Guid, pid: u32) -> TypedPropertyKey<T> {
+    TypedPropertyKey::new(PropertyKey::new(fmtid, pid))
+}
+
+/// `{1DA5D803-D492-4EDD-8C23-E0C0FFEE7F0E}`
+const AUDIO_ENDPOINT: Guid = Guid::from_values(
+    0x1da5d803,
+    0xd492,
+    0x4edd,
+    [0x8c, 0x23, 0xe0, 0xc0, 0xff, 0xee, 0x7f, 0x0e],
+);
+
+/// `{F19F064D-082C-4E27-BC73-6882A1BB8E4C}`
+const AUDIO_ENGINE_DEVICE: Guid = Guid::from_values(
+    0xf19f064d,
+    0x082c,
+    0x4e27,
+    [0xbc, 0x73, 0x68, 0x82, 0xa1, 0xbb, 0x8e, 0x4c],
+);
+
+/// `{E4870E26-3CC5-4CD2-BA46-CA0A9A70ED04}`
+const AUDIO_ENGINE_OEM: Guid = Guid::from_values(
+    0xe4870e26,
+    0x3cc5,
+    0x4cd2,
+    [0xba, 0x46, 0xca, 0x0a, 0x9a, 0x70, 0xed, 0x04],
+);
+
+/// `{A45C254E-DF1C-4EFD-8020-67D146A850E0}`
+const DEVICE: Guid = Guid::from_values(
+    0xa45c254e,
+    0xdf1c,
+    0x4efd,
+    [0x80, 0x20, 0x67, 0xd1, 0x46, 0xa8, 0x50, 0xe0],
+);
+
+/// `{8C7ED206-3F8A-4827-B3AB-AE9E1FAEFC6C}`
+const DEVICE_CONTAINER: Guid = Guid::from_values(
+    0x8c7ed206,
+    0x3f8a,
+    0x4827,
+    [0xb3, 0xab, 0xae, 0x9e, 0x1f, 0xae, 0xfc, 0x6c],
+);
+
+/// `{78C34FC8-104A-4ACA-9EA4-524D52996E57}`
+const DEVICE_INSTANCE: Guid = Guid::from_values(
+    0x78c34fc8,
+    0x104a,
+    0x4aca,
+    [0x9e, 0xa4, 0x52, 0x4d, 0x52, 0x99, 0x6e, 0x57],
+);
+
+/// `{259ABFFC-50A7-47CE-AF08-68C9A7D73366}`
+const DEVICE_CLASS: Guid = Guid::from_values(
+    0x259abffc,
+    0x50a7,
+    0x47ce,
+    [0xaf, 0x08, 0x68, 0xc9, 0xa7, 0xd7, 0x33, 0x66],
+);
+
+/// `PKEY_DeviceInterface_FriendlyName`
+pub const DEVICE_INTERFACE_FRIENDLY_NAME: TypedPropertyKey<WinString> =
+    TypedPropertyKey::new(PropertyKey::from_raw(DEVPKEY_DeviceInterface_FriendlyName));
+
+/// `PKEY_Device_DeviceDesc`
+pub const DEVICE_DESCRIPTION: TypedPropertyKey<WinString> =
+    TypedPropertyKey::new(PropertyKey::from_raw(DEVPKEY_Device_DeviceDesc));
+
+/// `PKEY_Device_FriendlyName`
+pub const DEVICE_FRIENDLY_NAME: TypedPropertyKey<WinString> =
+    TypedPropertyKey::new(PropertyKey::from_raw(DEVPKEY_Device_FriendlyName));
+
+/// `PKEY_Device_EnumeratorName`
+pub const DEVICE_ENUMERATOR_NAME: TypedPropertyKey<WinString> = key(DEVICE, 24);
+
+/// `PKEY_Device_InstanceId`
+pub const DEVICE_INSTANCE_ID: TypedPropertyKey<WinString> = key(DEVICE_INSTANCE, 256);
+
+/// `PKEY_Device_ContainerId`
+pub const DEVICE_CONTAINER_ID: TypedPropertyKey<Guid> = key(DEVICE_CONTAINER, 2);
+
+/// `PKEY_DeviceClass_IconPath`
+///
+/// Depending on the device, this may be a single string or a list of
+/// strings.
+pub const DEVICE_CLASS_ICON_PATH: TypedPropertyKey<Property> = key(DEVICE_CLASS, 12);
+
+/// `PKEY_AudioEndpoint_FormFactor`, the physical form of the endpoint.
+pub const AUDIO_ENDPOINT_FORM_FACTOR: TypedPropertyKey<EndpointFormFactor> = key(AUDIO_ENDPOINT, 0);
+
+/// `PKEY_AudioEndpoint_ControlPanelPageProvider`, the CLSID of the control
+/// panel extension, as a string.
+pub const AUDIO_ENDPOINT_CONTROL_PANEL_PAGE_PROVIDER: TypedPropertyKey<WinString> =
+    key(AUDIO_ENDPOINT, 1);
+
+/// `PKEY_AudioEndpoint_Association`, the KS node type GUID of the endpoint's
+/// pin category, as a string.
+pub const AUDIO_ENDPOINT_ASSOCIATION: TypedPropertyKey<WinString> = key(AUDIO_ENDPOINT, 2);
+
+/// `PKEY_AudioEndpoint_PhysicalSpeakers`, a speaker position mask.
+pub const AUDIO_ENDPOINT_PHYSICAL_SPEAKERS: TypedPropertyKey<u32> = key(AUDIO_ENDPOINT, 3);
+
+/// `PKEY_AudioEndpoint_GUID`, the endpoint GUID as a string.
+pub const AUDIO_ENDPOINT_GUID: TypedPropertyKey<WinString> = key(AUDIO_ENDPOINT, 4);
+
+/// `PKEY_AudioEndpoint_Disable_SysFx`, nonzero if system effects are disabled.
+pub const AUDIO_ENDPOINT_DISABLE_SYS_FX: TypedPropertyKey<u32> = key(AUDIO_ENDPOINT, 5);
+
+/// `PKEY_AudioEndpoint_FullRangeSpeakers`, a speaker position mask.
+pub const AUDIO_ENDPOINT_FULL_RANGE_SPEAKERS: TypedPropertyKey<u32> = key(AUDIO_ENDPOINT, 6);
+
+/// `PKEY_AudioEndpoint_Supports_EventDriven_Mode`, nonzero if the endpoint
+/// supports event-driven mode.
+pub const AUDIO_ENDPOINT_SUPPORTS_EVENT_DRIVEN_MODE: TypedPropertyKey<u32> = key(AUDIO_ENDPOINT, 7);
+
+/// `PKEY_AudioEndpoint_JackSubType`, the KS node type of the endpoint's jack.
+pub const AUDIO_ENDPOINT_JACK_SUB_TYPE: TypedPropertyKey<JackSubType> = key(AUDIO_ENDPOINT, 8);
+
+/// `PKEY_AudioEngine_DeviceFormat`, the `WAVEFORMATEX` of the default
+/// shared-mode format.
+pub const AUDIO_ENGINE_DEVICE_FORMAT: TypedPropertyKey<WaveFormat> = key(AUDIO_ENGINE_DEVICE, 0);
+
+/// `PKEY_AudioEngine_OEMFormat`, the `WAVEFORMATEX` of the default format
+/// specified by the driver.
+pub const AUDIO_ENGINE_OEM_FORMAT: TypedPropertyKey<WaveFormat> = key(AUDIO_ENGINE_OEM, 3);
+
+/// All keys in this module, with their Windows SDK names.
+pub const ALL: &[(&str, PropertyKey)] = &[
+    (
+        "PKEY_DeviceInterface_FriendlyName",
+        DEVICE_INTERFACE_FRIENDLY_NAME.key(),
+    ),
+    ("PKEY_Device_DeviceDesc", DEVICE_DESCRIPTION.key()),
+    ("PKEY_Device_FriendlyName", DEVICE_FRIENDLY_NAME.key()),
+    ("PKEY_Device_EnumeratorName", DEVICE_ENUMERATOR_NAME.key()),
+    ("PKEY_Device_InstanceId", DEVICE_INSTANCE_ID.key()),
+    ("PKEY_Device_ContainerId", DEVICE_CONTAINER_ID.key()),
+    ("PKEY_DeviceClass_IconPath", DEVICE_CLASS_ICON_PATH.key()),
+    (
+        "PKEY_AudioEndpoint_FormFactor",
+        AUDIO_ENDPOINT_FORM_FACTOR.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_ControlPanelPageProvider",
+        AUDIO_ENDPOINT_CONTROL_PANEL_PAGE_PROVIDER.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_Association",
+        AUDIO_ENDPOINT_ASSOCIATION.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_PhysicalSpeakers",
+        AUDIO_ENDPOINT_PHYSICAL_SPEAKERS.key(),
+    ),
+    ("PKEY_AudioEndpoint_GUID", AUDIO_ENDPOINT_GUID.key()),
+    (
+        "PKEY_AudioEndpoint_Disable_SysFx",
+        AUDIO_ENDPOINT_DISABLE_SYS_FX.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_FullRangeSpeakers",
+        AUDIO_ENDPOINT_FULL_RANGE_SPEAKERS.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_Supports_EventDriven_Mode",
+        AUDIO_ENDPOINT_SUPPORTS_EVENT_DRIVEN_MODE.key(),
+    ),
+    (
+        "PKEY_AudioEndpoint_JackSubType",
+        AUDIO_ENDPOINT_JACK_SUB_TYPE.key(),
+    ),
+    (
+        "PKEY_AudioEngine_DeviceFormat",
+        AUDIO_ENGINE_DEVICE_FORMAT.key(),
+    ),
+    ("PKEY_AudioEngine_OEMFormat", AUDIO_ENGINE_OEM_FORMAT.key()),
+];
+
+/// Looks up the Windows SDK name of a key, e.g.
+/// `PKEY_AudioEndpoint_FormFactor`.
+pub fn name_of(key: &PropertyKey) -> Option<&'static str> {
+    ALL.iter().find(|(_, k)| k == key).map(|&(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for (i, &(name, key)) in ALL.iter().enumerate() {
+            assert_eq!(name_of(&key), Some(name));
+            for &(other_name, other_key) in &ALL[..i] {
+                assert_ne!(key, other_key, "{} and {}", name, other_name);
+                assert_ne!(name, other_name);
+            }
+        }
+        assert_eq!(
+            name_of(&AUDIO_ENDPOINT_FORM_FACTOR),
+            Some("PKEY_AudioEndpoint_FormFactor")
+        );
+        assert_eq!(name_of(&PropertyKey::new(Guid::zeroed(), 0)), None);
+        assert_eq!(name_of(&PropertyKey::new(AUDIO_ENDPOINT, 100)), None);
+    }
+}
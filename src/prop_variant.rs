@@ -0,0 +1,378 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Debug, Formatter};
+
+use windows::Guid;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::{PSTR, PWSTR},
+        Storage::StructuredStorage::{
+            PROPVARIANT_0_0_0_abi, PROPVARIANT_0_0_abi, PropVariantClear, CALPWSTR, CAUB,
+            PROPVARIANT, PROPVARIANT_0,
+        },
+        System::Com::{CoTaskMemAlloc, CoTaskMemFree, BLOB},
+    },
+    error::{Error, Result, E_INVALIDARG, E_OUTOFMEMORY},
+    property_codec::{self, Payload, Shape},
+    property_store::Property,
+    string::WinStr,
+};
+
+/// An owned `PROPVARIANT`, which is cleared when dropped.
+///
+/// This is the raw form of a [`Property`]. Values can be borrowed from it
+/// without copying, and are valid for as long as the `PropVariant` is.
+///
+/// See also: [`PROPVARIANT`](https://docs.microsoft.com/en-us/windows/win32/api/propidlbase/ns-propidlbase-propvariant)
+pub struct PropVariant {
+    raw: PROPVARIANT,
+}
+
+impl PropVariant {
+    /// Creates a `PropVariant` containing a copy of the property's value.
+    ///
+    /// Returns an [`InvalidArgument`](crate::ErrorKind::InvalidArgument) error
+    /// if the property is [`Property::Unsupported`], which has no value to
+    /// copy, or is too large for a `PROPVARIANT` to count, and an
+    /// [`OutOfMemory`](crate::ErrorKind::OutOfMemory) error if the copy can't
+    /// be allocated.
+    pub fn new(property: &Property) -> Result<Self> {
+        type Data = PROPVARIANT_0_0_0_abi;
+        let (tag, payload) = property_codec::encode(property)
+            .ok_or_else(|| Error::from_code("PropVariant::new", E_INVALIDARG))?;
+        let data = match payload {
+            Payload::Empty => Data { uhVal: 0 },
+            Payload::Scalar(x) => Data { uhVal: x },
+            Payload::Guid(x) => Data {
+                puuid: co_task_mem_copy(&[x])?,
+            },
+            Payload::Bytes(x) if tag == property_codec::BLOB => Data {
+                blob: BLOB {
+                    cbSize: count(&x)?,
+                    pBlobData: co_task_mem_copy(&x)?,
+                },
+            },
+            Payload::Bytes(x) => Data {
+                caub: CAUB {
+                    cElems: count(&x)?,
+                    pElems: co_task_mem_copy(&x)?,
+                },
+            },
+            Payload::AnsiStr(mut x) => {
+                x.push(0);
+                Data {
+                    pszVal: PSTR(co_task_mem_copy(&x)?),
+                }
+            }
+            Payload::WideStr(mut x) => {
+                x.push(0);
+                Data {
+                    pwszVal: PWSTR(co_task_mem_copy(&x)?),
+                }
+            }
+            Payload::WideStrVector(x) => {
+                let len = count(&x)?;
+                let mut elems = Vec::with_capacity(x.len());
+                let copy = || {
+                    for mut s in x {
+                        s.push(0);
+                        elems.push(PWSTR(co_task_mem_copy(&s)?));
+                    }
+                    co_task_mem_copy(&elems)
+                };
+                match copy() {
+                    Ok(ptr) => Data {
+                        calpwstr: CALPWSTR {
+                            cElems: len,
+                            pElems: ptr,
+                        },
+                    },
+                    Err(err) => {
+                        // Free the strings copied before the allocation failed.
+                        for elem in elems {
+                            unsafe { CoTaskMemFree(elem.0 as _) };
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+        };
+        Ok(Self {
+            raw: PROPVARIANT {
+                Anonymous: PROPVARIANT_0 {
+                    Anonymous: PROPVARIANT_0_0_abi {
+                        vt: tag,
+                        wReserved1: 0,
+                        wReserved2: 0,
+                        wReserved3: 0,
+                        Anonymous: data,
+                    },
+                },
+            },
+        })
+    }
+
+    /// Takes ownership of a raw `PROPVARIANT`.
+    ///
+    /// # Safety
+    ///
+    /// - `raw` must be a valid `PROPVARIANT` that can be freed with
+    ///   `PropVariantClear`, and must not be used or freed elsewhere.
+    pub(crate) unsafe fn from_raw(raw: PROPVARIANT) -> Self {
+        Self { raw }
+    }
+
+    pub(crate) fn as_raw(&self) -> &PROPVARIANT {
+        &self.raw
+    }
+
+    fn data(&self) -> &PROPVARIANT_0_0_0_abi {
+        unsafe { &self.raw.Anonymous.Anonymous.Anonymous }
+    }
+
+    /// The `VARTYPE` tag of the value.
+    pub fn var_type(&self) -> u16 {
+        unsafe { self.raw.Anonymous.Anonymous.vt }
+    }
+
+    /// Copies the value into a [`Property`].
+    pub fn to_property(&self) -> Property {
+        let tag = self.var_type();
+        let payload = match property_codec::shape(tag) {
+            Shape::Empty | Shape::Unsupported => Payload::Empty,
+            Shape::Scalar => Payload::Scalar(unsafe { self.data().uhVal }),
+            Shape::Guid => match self.as_guid() {
+                Some(guid) => Payload::Guid(*guid),
+                None => Payload::Empty,
+            },
+            Shape::Blob | Shape::ByteVector => {
+                Payload::Bytes(self.as_bytes().unwrap_or_default().to_vec())
+            }
+            Shape::AnsiStr => Payload::AnsiStr(self.as_ansi_bytes().unwrap_or_default().to_vec()),
+            Shape::WideStr => Payload::WideStr(
+                self.as_winstr()
+                    .map(|s| s.as_slice().to_vec())
+                    .unwrap_or_default(),
+            ),
+            Shape::WideStrVector => Payload::WideStrVector(
+                self.winstrs()
+                    .into_iter()
+                    .flatten()
+                    .map(|s| s.as_slice().to_vec())
+                    .collect(),
+            ),
+        };
+        property_codec::decode(tag, payload)
+    }
+
+    /// Borrows the value of a `VT_CLSID`.
+    pub fn as_guid(&self) -> Option<&Guid> {
+        if self.var_type() != property_codec::CLSID {
+            return None;
+        }
+        unsafe { self.data().puuid.as_ref() }
+    }
+
+    /// Borrows the value of a `VT_BLOB` or a `VT_VECTOR | VT_UI1`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        let data = self.data();
+        unsafe {
+            match property_codec::shape(self.var_type()) {
+                Shape::Blob => Some(slice_or_empty(data.blob.pBlobData, data.blob.cbSize)),
+                Shape::ByteVector => Some(slice_or_empty(data.caub.pElems, data.caub.cElems)),
+                _ => None,
+            }
+        }
+    }
+
+    /// Borrows the value of a `VT_LPSTR`, without its null terminator.
+    pub fn as_ansi_bytes(&self) -> Option<&[u8]> {
+        if self.var_type() != property_codec::LPSTR {
+            return None;
+        }
+        Some(unsafe { nul_terminated(self.data().pszVal.0) })
+    }
+
+    /// Borrows the value of a `VT_LPWSTR`.
+    pub fn as_winstr(&self) -> Option<&WinStr> {
+        if self.var_type() != property_codec::LPWSTR {
+            return None;
+        }
+        let pwstr = unsafe { &self.data().pwszVal };
+        if pwstr.is_null() {
+            return None;
+        }
+        Some(unsafe { WinStr::from_pwstr(pwstr) })
+    }
+
+    /// Borrows the elements of a `VT_VECTOR | VT_LPWSTR`.
+    pub fn winstrs(&self) -> Option<impl Iterator<Item = &WinStr> + '_> {
+        if self.var_type() != property_codec::VECTOR_LPWSTR {
+            return None;
+        }
+        let vector = unsafe { &self.data().calpwstr };
+        let elems = unsafe { slice_or_empty(vector.pElems, vector.cElems) };
+        Some(
+            elems
+                .iter()
+                .filter(|pwstr| !pwstr.is_null())
+                .map(|pwstr| unsafe { WinStr::from_pwstr(pwstr) }),
+        )
+    }
+}
+
+impl TryFrom<&Property> for PropVariant {
+    type Error = Error;
+
+    fn try_from(property: &Property) -> Result<Self> {
+        Self::new(property)
+    }
+}
+
+impl Debug for PropVariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropVariant")
+            .field("var_type", &self.var_type())
+            .field("value", &self.to_property())
+            .finish()
+    }
+}
+
+impl Drop for PropVariant {
+    fn drop(&mut self) {
+        // Values stored inline don't own any memory.
+        if let Shape::Empty | Shape::Scalar = property_codec::shape(self.var_type()) {
+            return;
+        }
+        unsafe { PropVariantClear(&mut self.raw).ok() };
+    }
+}
+
+/// Borrows a counted array, which may be null if it is empty.
+///
+/// # Safety
+///
+/// - `ptr` must be null, or point to `len` valid elements.
+unsafe fn slice_or_empty<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+    if ptr.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(ptr, len as usize) }
+    }
+}
+
+/// Borrows a null-terminated string, not including the terminator.
+///
+/// # Safety
+///
+/// - `ptr` must be null, or point to a valid null-terminated string.
+unsafe fn nul_terminated<'a, T>(ptr: *const T) -> &'a [T]
+where
+    T: Copy + Default + PartialEq,
+{
+    if ptr.is_null() {
+        return &[];
+    }
+    let mut len = 0;
+    while unsafe { *ptr.add(len) } != T::default() {
+        len += 1;
+    }
+    unsafe { std::slice::from_raw_parts(ptr, len) }
+}
+
+/// Gets the length of a counted array as stored in a `PROPVARIANT`.
+fn count<T>(data: &[T]) -> Result<u32> {
+    data.len()
+        .try_into()
+        .map_err(|_| Error::from_code("PropVariant::new", E_INVALIDARG))
+}
+
+/// Copies the data into a new buffer allocated with `CoTaskMemAlloc`.
+///
+/// Returns null if the data is empty.
+fn co_task_mem_copy<T: Copy>(data: &[T]) -> Result<*mut T> {
+    if data.is_empty() {
+        return Ok(std::ptr::null_mut());
+    }
+    let ptr = unsafe { CoTaskMemAlloc(std::mem::size_of_val(data)) } as *mut T;
+    if ptr.is_null() {
+        return Err(Error::from_code("PropVariant::new", E_OUTOFMEMORY));
+    }
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len()) };
+    Ok(ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    #[cfg(windows)]
+    use crate::{string::WinString, winstr};
+
+    #[test]
+    fn unsupported_is_an_error() {
+        let err = PropVariant::new(&Property::Unsupported).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert_eq!(err.operation(), "PropVariant::new");
+        assert!(PropVariant::try_from(&Property::Unsupported).is_err());
+    }
+
+    #[test]
+    fn scalar_round_trip() {
+        for property in [
+            Property::Empty,
+            Property::U32(7),
+            Property::F32(0.5),
+            Property::Bool(true),
+        ] {
+            let value = PropVariant::new(&property).unwrap();
+            assert_eq!(value.to_property(), property);
+            assert_eq!(value.as_guid(), None);
+            assert_eq!(value.as_winstr(), None);
+        }
+    }
+
+    // Heap values are allocated with `CoTaskMemAlloc` and freed with
+    // `PropVariantClear`, which are only available on Windows.
+    #[cfg(windows)]
+    #[test]
+    fn heap_round_trip() {
+        let guid = Guid::from_values(
+            0x1da5d803,
+            0xd492,
+            0x4edd,
+            [0x8c, 0x23, 0xe0, 0xc0, 0xff, 0xee, 0x7f, 0x0e],
+        );
+        for property in [
+            Property::Str(WinString::from("Speakers")),
+            Property::Str(WinString::from("")),
+            Property::StrVec(vec![WinString::from("a"), WinString::from("bc")]),
+            Property::StrVec(vec![]),
+            Property::Blob(vec![1, 2, 3]),
+            Property::Blob(vec![]),
+            Property::Bytes(vec![4, 5]),
+            Property::AnsiStr(b"ansi".to_vec()),
+            Property::Guid(guid),
+        ] {
+            let value = PropVariant::new(&property).unwrap();
+            assert_eq!(value.to_property(), property);
+        }
+
+        let value = PropVariant::new(&Property::Str(WinString::from("Speakers"))).unwrap();
+        assert_eq!(value.as_winstr(), Some(winstr!("Speakers")));
+
+        let value = PropVariant::new(&Property::StrVec(vec![
+            WinString::from("a"),
+            WinString::from("bc"),
+        ]))
+        .unwrap();
+        let strs: Vec<&WinStr> = value.winstrs().unwrap().collect();
+        assert_eq!(strs, [winstr!("a"), winstr!("bc")]);
+
+        let value = PropVariant::new(&Property::Blob(vec![1, 2, 3])).unwrap();
+        assert_eq!(value.as_bytes(), Some(&[1, 2, 3][..]));
+
+        let value = PropVariant::new(&Property::Guid(guid)).unwrap();
+        assert_eq!(value.as_guid(), Some(&guid));
+    }
+}
@@ -0,0 +1,275 @@
+//! Mapping between [`Property`] values and `PROPVARIANT` type tags and
+//! payloads.
+//!
+//! This layer is pure Rust; reading and writing the payloads in the memory of
+//! an actual `PROPVARIANT` is handled by [`prop_variant`](crate::prop_variant).
+
+use windows::Guid;
+
+use crate::{
+    bindings::Windows::Win32::System::OleAutomation::{
+        VT_BLOB, VT_BOOL, VT_CLSID, VT_EMPTY, VT_FILETIME, VT_I1, VT_I2, VT_I4, VT_I8, VT_INT,
+        VT_LPSTR, VT_LPWSTR, VT_NULL, VT_R4, VT_R8, VT_UI1, VT_UI2, VT_UI4, VT_UI8, VT_UINT,
+        VT_VECTOR,
+    },
+    property_store::Property,
+    string::WinString,
+};
+
+pub(crate) const EMPTY: u16 = VT_EMPTY.0 as u16;
+pub(crate) const NULL: u16 = VT_NULL.0 as u16;
+pub(crate) const I1: u16 = VT_I1.0 as u16;
+pub(crate) const UI1: u16 = VT_UI1.0 as u16;
+pub(crate) const I2: u16 = VT_I2.0 as u16;
+pub(crate) const UI2: u16 = VT_UI2.0 as u16;
+pub(crate) const I4: u16 = VT_I4.0 as u16;
+pub(crate) const UI4: u16 = VT_UI4.0 as u16;
+pub(crate) const INT: u16 = VT_INT.0 as u16;
+pub(crate) const UINT: u16 = VT_UINT.0 as u16;
+pub(crate) const I8: u16 = VT_I8.0 as u16;
+pub(crate) const UI8: u16 = VT_UI8.0 as u16;
+pub(crate) const R4: u16 = VT_R4.0 as u16;
+pub(crate) const R8: u16 = VT_R8.0 as u16;
+pub(crate) const BOOL: u16 = VT_BOOL.0 as u16;
+pub(crate) const LPWSTR: u16 = VT_LPWSTR.0 as u16;
+pub(crate) const CLSID: u16 = VT_CLSID.0 as u16;
+pub(crate) const BLOB: u16 = VT_BLOB.0 as u16;
+pub(crate) const FILETIME: u16 = VT_FILETIME.0 as u16;
+pub(crate) const LPSTR: u16 = VT_LPSTR.0 as u16;
+pub(crate) const VECTOR_LPWSTR: u16 = (VT_VECTOR.0 | VT_LPWSTR.0) as u16;
+pub(crate) const VECTOR_UI1: u16 = (VT_VECTOR.0 | VT_UI1.0) as u16;
+
+/// `VARIANT_TRUE`, as stored in the low 16 bits of a scalar payload.
+const VARIANT_TRUE: u64 = 0xFFFF;
+
+/// Which member of the `PROPVARIANT` union holds the payload for a type tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shape {
+    /// No payload.
+    Empty,
+    /// An inline value of up to 8 bytes.
+    Scalar,
+    /// `puuid`
+    Guid,
+    /// `blob`
+    Blob,
+    /// `pszVal`
+    AnsiStr,
+    /// `pwszVal`
+    WideStr,
+    /// `calpwstr`
+    WideStrVector,
+    /// `caub`
+    ByteVector,
+    /// The type is not supported by this crate.
+    Unsupported,
+}
+
+/// The contents of a `PROPVARIANT`, copied out of its union.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Payload {
+    Empty,
+    /// The raw bits of an inline value. Values smaller than 8 bytes are stored
+    /// in the low bits.
+    Scalar(u64),
+    Guid(Guid),
+    /// The contents of either a blob or a byte vector.
+    Bytes(Vec<u8>),
+    /// An ANSI string, without its null terminator.
+    AnsiStr(Vec<u8>),
+    /// A wide string, without its null terminator.
+    WideStr(Vec<u16>),
+    WideStrVector(Vec<Vec<u16>>),
+}
+
+pub(crate) fn shape(tag: u16) -> Shape {
+    match tag {
+        EMPTY | NULL => Shape::Empty,
+        I1 | UI1 | I2 | UI2 | I4 | UI4 | INT | UINT | I8 | UI8 | R4 | R8 | BOOL | FILETIME => {
+            Shape::Scalar
+        }
+        CLSID => Shape::Guid,
+        BLOB => Shape::Blob,
+        LPSTR => Shape::AnsiStr,
+        LPWSTR => Shape::WideStr,
+        VECTOR_LPWSTR => Shape::WideStrVector,
+        VECTOR_UI1 => Shape::ByteVector,
+        _ => Shape::Unsupported,
+    }
+}
+
+/// Converts a type tag and the payload read from the matching union member
+/// into a property.
+pub(crate) fn decode(tag: u16, payload: Payload) -> Property {
+    match (tag, payload) {
+        (EMPTY, _) => Property::Empty,
+        (NULL, _) => Property::Null,
+        (I1, Payload::Scalar(x)) => Property::I8(x as u8 as i8),
+        (UI1, Payload::Scalar(x)) => Property::U8(x as u8),
+        (I2, Payload::Scalar(x)) => Property::I16(x as u16 as i16),
+        (UI2, Payload::Scalar(x)) => Property::U16(x as u16),
+        (I4 | INT, Payload::Scalar(x)) => Property::I32(x as u32 as i32),
+        (UI4 | UINT, Payload::Scalar(x)) => Property::U32(x as u32),
+        (I8, Payload::Scalar(x)) => Property::I64(x as i64),
+        (UI8, Payload::Scalar(x)) => Property::U64(x),
+        (R4, Payload::Scalar(x)) => Property::F32(f32::from_bits(x as u32)),
+        (R8, Payload::Scalar(x)) => Property::F64(f64::from_bits(x)),
+        (BOOL, Payload::Scalar(x)) => Property::Bool(x as u16 != 0),
+        (FILETIME, Payload::Scalar(x)) => Property::FileTime(x),
+        (CLSID, Payload::Guid(x)) => Property::Guid(x),
+        (BLOB, Payload::Bytes(x)) => Property::Blob(x),
+        (LPSTR, Payload::AnsiStr(x)) => Property::AnsiStr(x),
+        (LPWSTR, Payload::WideStr(x)) => match WinString::from_vec(x) {
+            Ok(x) => Property::Str(x),
+            Err(_) => Property::Unsupported,
+        },
+        (VECTOR_LPWSTR, Payload::WideStrVector(x)) => {
+            match x.into_iter().map(WinString::from_vec).collect() {
+                Ok(x) => Property::StrVec(x),
+                Err(_) => Property::Unsupported,
+            }
+        }
+        (VECTOR_UI1, Payload::Bytes(x)) => Property::Bytes(x),
+        _ => Property::Unsupported,
+    }
+}
+
+/// Converts a property into a type tag and the payload to write into the
+/// matching union member.
+///
+/// Returns `None` for [`Property::Unsupported`].
+pub(crate) fn encode(property: &Property) -> Option<(u16, Payload)> {
+    let scalar = |tag, x| Some((tag, Payload::Scalar(x)));
+    match property {
+        Property::Empty => Some((EMPTY, Payload::Empty)),
+        Property::Null => Some((NULL, Payload::Empty)),
+        Property::I8(x) => scalar(I1, *x as u8 as u64),
+        Property::U8(x) => scalar(UI1, *x as u64),
+        Property::I16(x) => scalar(I2, *x as u16 as u64),
+        Property::U16(x) => scalar(UI2, *x as u64),
+        Property::I32(x) => scalar(I4, *x as u32 as u64),
+        Property::U32(x) => scalar(UI4, *x as u64),
+        Property::I64(x) => scalar(I8, *x as u64),
+        Property::U64(x) => scalar(UI8, *x),
+        Property::F32(x) => scalar(R4, x.to_bits() as u64),
+        Property::F64(x) => scalar(R8, x.to_bits()),
+        Property::Bool(x) => scalar(BOOL, if *x { VARIANT_TRUE } else { 0 }),
+        Property::FileTime(x) => scalar(FILETIME, *x),
+        Property::Guid(x) => Some((CLSID, Payload::Guid(*x))),
+        Property::Blob(x) => Some((BLOB, Payload::Bytes(x.clone()))),
+        Property::AnsiStr(x) => Some((LPSTR, Payload::AnsiStr(x.clone()))),
+        Property::Str(x) => Some((LPWSTR, Payload::WideStr(x.as_slice().to_vec()))),
+        Property::StrVec(x) => Some((
+            VECTOR_LPWSTR,
+            Payload::WideStrVector(x.iter().map(|s| s.as_slice().to_vec()).collect()),
+        )),
+        Property::Bytes(x) => Some((VECTOR_UI1, Payload::Bytes(x.clone()))),
+        Property::Unsupported => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supported() -> Vec<(u16, Shape, Property)> {
+        vec![
+            (EMPTY, Shape::Empty, Property::Empty),
+            (NULL, Shape::Empty, Property::Null),
+            (I1, Shape::Scalar, Property::I8(-2)),
+            (UI1, Shape::Scalar, Property::U8(0xFE)),
+            (I2, Shape::Scalar, Property::I16(-300)),
+            (UI2, Shape::Scalar, Property::U16(0xFFFE)),
+            (I4, Shape::Scalar, Property::I32(-70000)),
+            (UI4, Shape::Scalar, Property::U32(0xFFFF_FFFE)),
+            (I8, Shape::Scalar, Property::I64(i64::MIN)),
+            (UI8, Shape::Scalar, Property::U64(u64::MAX)),
+            (R4, Shape::Scalar, Property::F32(-0.25)),
+            (R8, Shape::Scalar, Property::F64(1e300)),
+            (BOOL, Shape::Scalar, Property::Bool(true)),
+            (BOOL, Shape::Scalar, Property::Bool(false)),
+            (
+                FILETIME,
+                Shape::Scalar,
+                Property::FileTime(132_000_000_000_000_000),
+            ),
+            (
+                CLSID,
+                Shape::Guid,
+                Property::Guid(Guid::from_values(1, 2, 3, [4, 5, 6, 7, 8, 9, 10, 11])),
+            ),
+            (BLOB, Shape::Blob, Property::Blob(vec![0, 1, 2, 0xFF])),
+            (
+                LPSTR,
+                Shape::AnsiStr,
+                Property::AnsiStr(b"Speakers".to_vec()),
+            ),
+            (LPWSTR, Shape::WideStr, Property::Str("Speakers 😀".into())),
+            (
+                VECTOR_LPWSTR,
+                Shape::WideStrVector,
+                Property::StrVec(vec!["a".into(), "".into(), "b".into()]),
+            ),
+            (
+                VECTOR_UI1,
+                Shape::ByteVector,
+                Property::Bytes(vec![1, 2, 3]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for (tag, expected_shape, property) in supported() {
+            let (encoded_tag, payload) = encode(&property).unwrap();
+            assert_eq!(encoded_tag, tag, "{:?}", property);
+            assert_eq!(shape(tag), expected_shape, "{:?}", property);
+            assert_eq!(decode(tag, payload), property);
+        }
+    }
+
+    #[test]
+    fn scalar_bits() {
+        assert_eq!(encode(&Property::I8(-1)), Some((I1, Payload::Scalar(0xFF))));
+        assert_eq!(
+            encode(&Property::Bool(true)),
+            Some((BOOL, Payload::Scalar(VARIANT_TRUE)))
+        );
+        // Any nonzero VARIANT_BOOL is true.
+        assert_eq!(decode(BOOL, Payload::Scalar(1)), Property::Bool(true));
+        // Bits above the size of the type are ignored.
+        assert_eq!(decode(UI2, Payload::Scalar(0xDEAD_0001)), Property::U16(1));
+    }
+
+    #[test]
+    fn int_and_uint_decode_as_32_bit() {
+        assert_eq!(shape(INT), Shape::Scalar);
+        assert_eq!(shape(UINT), Shape::Scalar);
+        assert_eq!(decode(INT, Payload::Scalar(0xFFFF_FFFF)), Property::I32(-1));
+        assert_eq!(decode(UINT, Payload::Scalar(7)), Property::U32(7));
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(encode(&Property::Unsupported), None);
+
+        let vt_bstr = 8;
+        assert_eq!(shape(vt_bstr), Shape::Unsupported);
+        assert_eq!(decode(vt_bstr, Payload::Scalar(0)), Property::Unsupported);
+
+        // Payload does not match the tag.
+        assert_eq!(decode(LPWSTR, Payload::Scalar(0)), Property::Unsupported);
+        // Strings with interior nulls cannot be represented.
+        assert_eq!(
+            decode(LPWSTR, Payload::WideStr(vec![0x61, 0, 0x62])),
+            Property::Unsupported
+        );
+        assert_eq!(
+            decode(
+                VECTOR_LPWSTR,
+                Payload::WideStrVector(vec![vec![0x61], vec![0]])
+            ),
+            Property::Unsupported
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{btree_map, BTreeMap};
+use std::iter::FromIterator;
+
+use crate::property_store::{Property, PropertyKey};
+
+/// A copy of every value in a [`PropertyStore`](crate::PropertyStore),
+/// ordered by key.
+///
+/// Snapshots are plain data. They can be compared with [`diff`](Self::diff)
+/// long after the store they were taken from has been released.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct PropertySnapshot {
+    entries: BTreeMap<PropertyKey, Property>,
+}
+
+impl PropertySnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &PropertyKey) -> Option<&Property> {
+        self.entries.get(key)
+    }
+
+    pub fn contains_key(&self, key: &PropertyKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Sets the value of a key, returning the previous value if there was
+    /// one.
+    pub fn insert(&mut self, key: PropertyKey, value: Property) -> Option<Property> {
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &PropertyKey) -> Option<Property> {
+        self.entries.remove(key)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, PropertyKey, Property> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> btree_map::Keys<'_, PropertyKey, Property> {
+        self.entries.keys()
+    }
+
+    /// Compares this snapshot against a newer one.
+    pub fn diff(&self, new: &PropertySnapshot) -> PropertyDiff {
+        let mut diff = PropertyDiff::default();
+        for (key, old_value) in &self.entries {
+            match new.entries.get(key) {
+                None => {
+                    diff.removed.insert(*key, old_value.clone());
+                }
+                Some(new_value) if new_value != old_value => {
+                    diff.changed.insert(
+                        *key,
+                        PropertyChange {
+                            old: old_value.clone(),
+                            new: new_value.clone(),
+                        },
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, new_value) in &new.entries {
+            if !self.entries.contains_key(key) {
+                diff.added.insert(*key, new_value.clone());
+            }
+        }
+        diff
+    }
+}
+
+impl FromIterator<(PropertyKey, Property)> for PropertySnapshot {
+    fn from_iter<I: IntoIterator<Item = (PropertyKey, Property)>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<(PropertyKey, Property)> for PropertySnapshot {
+    fn extend<I: IntoIterator<Item = (PropertyKey, Property)>>(&mut self, iter: I) {
+        self.entries.extend(iter)
+    }
+}
+
+impl IntoIterator for PropertySnapshot {
+    type IntoIter = btree_map::IntoIter<PropertyKey, Property>;
+    type Item = (PropertyKey, Property);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PropertySnapshot {
+    type IntoIter = btree_map::Iter<'a, PropertyKey, Property>;
+    type Item = (&'a PropertyKey, &'a Property);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// The differences between two [`PropertySnapshot`]s, as returned by
+/// [`PropertySnapshot::diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyDiff {
+    /// Keys that are only present in the new snapshot, with their new values.
+    pub added: PropertySnapshot,
+    /// Keys that are only present in the old snapshot, with their old values.
+    pub removed: PropertySnapshot,
+    /// Keys that are present in both snapshots with different values.
+    pub changed: BTreeMap<PropertyKey, PropertyChange>,
+}
+
+impl PropertyDiff {
+    /// Whether the two snapshots were equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A value that differs between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyChange {
+    pub old: Property,
+    pub new: Property,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkeys;
+
+    fn snapshot(entries: &[(PropertyKey, Property)]) -> PropertySnapshot {
+        entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn identical() {
+        let old = snapshot(&[
+            (pkeys::DEVICE_FRIENDLY_NAME.key(), Property::U32(1)),
+            (pkeys::AUDIO_ENDPOINT_GUID.key(), Property::Bool(true)),
+        ]);
+        assert!(old.diff(&old.clone()).is_empty());
+        assert_eq!(old.diff(&old), PropertyDiff::default());
+        assert!(PropertySnapshot::new()
+            .diff(&PropertySnapshot::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_changed() {
+        let kept = pkeys::DEVICE_DESCRIPTION.key();
+        let changed = pkeys::DEVICE_FRIENDLY_NAME.key();
+        let removed = pkeys::DEVICE_INSTANCE_ID.key();
+        let added = pkeys::AUDIO_ENDPOINT_FORM_FACTOR.key();
+        let old = snapshot(&[
+            (kept, Property::U32(1)),
+            (changed, Property::U32(2)),
+            (removed, Property::U32(3)),
+        ]);
+        let new = snapshot(&[
+            (kept, Property::U32(1)),
+            (changed, Property::I32(2)),
+            (added, Property::U32(4)),
+        ]);
+
+        let diff = old.diff(&new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added, snapshot(&[(added, Property::U32(4))]));
+        assert_eq!(diff.removed, snapshot(&[(removed, Property::U32(3))]));
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[&changed],
+            PropertyChange {
+                old: Property::U32(2),
+                new: Property::I32(2),
+            }
+        );
+
+        // Diffing the other way swaps the roles.
+        let reverse = new.diff(&old);
+        assert_eq!(reverse.added, diff.removed);
+        assert_eq!(reverse.removed, diff.added);
+        assert_eq!(
+            reverse.changed[&changed],
+            PropertyChange {
+                old: Property::I32(2),
+                new: Property::U32(2),
+            }
+        );
+    }
+
+    #[test]
+    fn ordered_by_key() {
+        let mut keys: Vec<PropertyKey> = pkeys::ALL.iter().map(|&(_, key)| key).collect();
+        let old: PropertySnapshot = keys
+            .iter()
+            .rev()
+            .map(|&key| (key, Property::U32(0)))
+            .collect();
+        let new: PropertySnapshot = keys.iter().map(|&key| (key, Property::U32(1))).collect();
+        keys.sort();
+
+        assert_eq!(old.keys().copied().collect::<Vec<_>>(), keys);
+        let diff = old.diff(&new);
+        assert_eq!(diff.changed.keys().copied().collect::<Vec<_>>(), keys);
+        let diff = PropertySnapshot::new().diff(&old);
+        assert_eq!(diff.added.keys().copied().collect::<Vec<_>>(), keys);
+        let diff = old.diff(&PropertySnapshot::new());
+        assert_eq!(diff.removed.keys().copied().collect::<Vec<_>>(), keys);
+    }
+}
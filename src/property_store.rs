@@ -1,24 +1,33 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::str::FromStr;
+
+use windows::Guid;
+
 use crate::{
-    bindings::Windows::Win32::{
-        Storage::StructuredStorage::{
-            PROPVARIANT_0_0_0_abi, PROPVARIANT_0_0_abi, PROPVARIANT, PROPVARIANT_0,
-        },
-        System::{
-            OleAutomation::{
-                VARENUM, VT_BOOL, VT_EMPTY, VT_I1, VT_I2, VT_I4, VT_I8, VT_INT, VT_LPWSTR, VT_NULL,
-                VT_R4, VT_R8, VT_UI1, VT_UI2, VT_UI4, VT_UI8, VT_UINT,
-            },
-            PropertiesSystem::{IPropertyStore, PropVariantToStringAlloc, PROPERTYKEY},
-            SystemServices::CHAR,
-        },
-    },
+    bindings::Windows::Win32::System::PropertiesSystem::{IPropertyStore, PROPERTYKEY},
+    error::{Context, Result},
+    pkeys,
+    prop_variant::PropVariant,
+    property_snapshot::PropertySnapshot,
     string::WinString,
+    typed_property::{FromProperty, IntoProperty, PropertyError, TypedPropertyKey},
+    util::{guid_to_string, parse_guid, GuidFields},
 };
 
+/// See also: [`PROPERTYKEY`](https://docs.microsoft.com/en-us/windows/win32/api/wtypes/ns-wtypes-propertykey)
 #[derive(Debug, Clone, Copy)]
 pub struct PropertyKey(PROPERTYKEY);
 
 impl PropertyKey {
+    pub const fn new(fmtid: Guid, pid: u32) -> Self {
+        Self(PROPERTYKEY { fmtid, pid })
+    }
+
     pub(crate) const fn from_raw(raw: PROPERTYKEY) -> Self {
         Self(raw)
     }
@@ -26,10 +35,97 @@ impl PropertyKey {
     pub(crate) fn as_raw(&self) -> &PROPERTYKEY {
         &self.0
     }
+
+    /// The format ID of the property set that contains the property.
+    pub fn fmtid(&self) -> Guid {
+        self.0.fmtid
+    }
+
+    /// The ID of the property within its property set.
+    pub fn pid(&self) -> u32 {
+        self.0.pid
+    }
+
+    fn sort_key(&self) -> (GuidFields, u32) {
+        (GuidFields::of(&self.0.fmtid), self.0.pid)
+    }
+
+    /// The Windows SDK name of this key, if it is one of the well-known keys
+    /// in [`pkeys`](crate::pkeys).
+    pub fn name(&self) -> Option<&'static str> {
+        pkeys::name_of(self)
+    }
+}
+
+impl PartialEq for PropertyKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
 }
 
+impl Eq for PropertyKey {}
+
+impl PartialOrd for PropertyKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders keys by format ID, then by property ID.
+impl Ord for PropertyKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl Hash for PropertyKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sort_key().hash(state)
+    }
+}
+
+/// Formats the key in its canonical form, `{fmtid} pid`, as used by
+/// [`PSStringFromPropertyKey`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-psstringfrompropertykey).
+impl Display for PropertyKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", guid_to_string(&self.0.fmtid), self.0.pid)
+    }
+}
+
+/// Parses a key in its canonical form, `{fmtid} pid`.
+impl FromStr for PropertyKey {
+    type Err = ParsePropertyKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (fmtid, pid) = s.trim().split_once(' ').ok_or(ParsePropertyKeyError)?;
+        if !fmtid.starts_with('{') {
+            return Err(ParsePropertyKeyError);
+        }
+        Ok(Self::new(
+            parse_guid(fmtid).ok_or(ParsePropertyKeyError)?,
+            pid.trim_start()
+                .parse()
+                .map_err(|_| ParsePropertyKeyError)?,
+        ))
+    }
+}
+
+/// An error returned when parsing a [`PropertyKey`] from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParsePropertyKeyError;
+
+impl Display for ParsePropertyKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid property key, expected `{fmtid} pid`")
+    }
+}
+
+impl Error for ParsePropertyKeyError {}
+
 /// See also: [`PROPVARIANT`](https://docs.microsoft.com/en-us/windows/win32/api/propidlbase/ns-propidlbase-propvariant)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Property {
     Empty,
     Null,
@@ -45,95 +141,23 @@ pub enum Property {
     F64(f64),
     Bool(bool),
     Str(WinString),
+    /// `VT_CLSID`
+    Guid(#[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::guid"))] Guid),
+    /// `VT_BLOB`
+    Blob(Vec<u8>),
+    /// `VT_FILETIME`, in 100-nanosecond intervals since January 1, 1601 (UTC).
+    FileTime(u64),
+    /// `VT_LPSTR`, a string in the system ANSI code page, without its null
+    /// terminator.
+    AnsiStr(Vec<u8>),
+    /// `VT_VECTOR | VT_LPWSTR`
+    StrVec(Vec<WinString>),
+    /// `VT_VECTOR | VT_UI1`
+    Bytes(Vec<u8>),
     /// The property contains a type that is not yet supported by this crate.
     Unsupported,
 }
 
-impl Property {
-    pub(crate) fn from_raw(raw: PROPVARIANT) -> Self {
-        let raw_inner = unsafe { raw.Anonymous.Anonymous };
-        let tag = VARENUM(raw_inner.vt as _);
-        let value = raw_inner.Anonymous;
-        if tag == VT_EMPTY {
-            Self::Empty
-        } else if tag == VT_NULL {
-            Self::Null
-        } else if tag == VT_I1 {
-            Self::I8(unsafe { value.cVal.0 as _ })
-        } else if tag == VT_UI1 {
-            Self::U8(unsafe { value.bVal })
-        } else if tag == VT_I2 {
-            Self::I16(unsafe { value.iVal })
-        } else if tag == VT_UI2 {
-            Self::U16(unsafe { value.uiVal })
-        } else if tag == VT_I4 {
-            Self::I32(unsafe { value.lVal })
-        } else if tag == VT_UI4 {
-            Self::U32(unsafe { value.ulVal })
-        } else if tag == VT_INT {
-            Self::I32(unsafe { value.intVal })
-        } else if tag == VT_UINT {
-            Self::U32(unsafe { value.uintVal })
-        } else if tag == VT_I8 {
-            Self::I64(unsafe { value.hVal })
-        } else if tag == VT_UI8 {
-            Self::U64(unsafe { value.uhVal })
-        } else if tag == VT_R4 {
-            Self::F32(unsafe { value.fltVal })
-        } else if tag == VT_R8 {
-            Self::F64(unsafe { value.dblVal })
-        } else if tag == VT_BOOL {
-            Self::Bool(unsafe { value.boolVal != 0 })
-        } else if tag == VT_LPWSTR {
-            Self::Str(unsafe { WinString::from_com_pwstr(PropVariantToStringAlloc(&raw).unwrap()) })
-        } else {
-            Self::Unsupported
-        }
-    }
-
-    pub(crate) fn to_raw(&self) -> PROPVARIANT {
-        type Data = PROPVARIANT_0_0_0_abi;
-        let (tag, data) = match self {
-            Property::Empty => (VT_EMPTY, Data { bVal: 0 }),
-            Property::Null => (VT_NULL, Data { bVal: 0 }),
-            Property::I8(x) => (
-                VT_I1,
-                Data {
-                    cVal: CHAR(*x as _),
-                },
-            ),
-            Property::U8(x) => (VT_UI1, Data { bVal: *x }),
-            Property::I16(x) => (VT_I2, Data { iVal: *x }),
-            Property::U16(x) => (VT_UI2, Data { uiVal: *x }),
-            Property::I32(x) => (VT_I4, Data { lVal: *x }),
-            Property::U32(x) => (VT_UI4, Data { ulVal: *x }),
-            Property::I64(x) => (VT_I8, Data { hVal: *x }),
-            Property::U64(x) => (VT_UI8, Data { uhVal: *x }),
-            Property::F32(x) => (VT_R4, Data { fltVal: *x }),
-            Property::F64(x) => (VT_R8, Data { dblVal: *x }),
-            Property::Bool(x) => (VT_BOOL, Data { boolVal: *x as _ }),
-            Property::Str(x) => (
-                VT_LPWSTR,
-                Data {
-                    pwszVal: x.as_pwstr(),
-                },
-            ),
-            Property::Unsupported => panic!("cannot convert unsupported"),
-        };
-        PROPVARIANT {
-            Anonymous: PROPVARIANT_0 {
-                Anonymous: PROPVARIANT_0_0_abi {
-                    vt: tag.0 as _,
-                    wReserved1: 0,
-                    wReserved2: 0,
-                    wReserved3: 0,
-                    Anonymous: data,
-                },
-            },
-        }
-    }
-}
-
 /// See also: [`IPropertyStore`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nn-propsys-ipropertystore)
 #[derive(Debug, Clone)]
 pub struct PropertyStore {
@@ -146,27 +170,131 @@ impl PropertyStore {
     }
 
     /// See also: [`IPropertyStore::Commit`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-commit)
-    pub fn commit(&self) -> windows::Result<()> {
-        unsafe { self.inner.Commit() }
+    pub fn commit(&self) -> Result<()> {
+        unsafe { self.inner.Commit() }.context("IPropertyStore::Commit")
     }
 
     /// See also: [`IPropertyStore::GetAt`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getat)
-    pub fn get_at(&self, index: u32) -> windows::Result<PropertyKey> {
+    pub fn get_at(&self, index: u32) -> Result<PropertyKey> {
         unsafe { self.inner.GetAt(index).map(PropertyKey::from_raw) }
+            .context("IPropertyStore::GetAt")
     }
 
     /// See also: [`IPropertyStore::GetCount`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getcount)
-    pub fn get_count(&self) -> windows::Result<u32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> Result<u32> {
+        unsafe { self.inner.GetCount() }.context("IPropertyStore::GetCount")
+    }
+
+    /// Reads every property in the store.
+    ///
+    /// Properties whose key or value cannot be read are left out, so one
+    /// unreadable property doesn't hide the rest. Returns an error only if
+    /// the number of properties cannot be read.
+    pub fn snapshot(&self) -> Result<PropertySnapshot> {
+        Ok((0..self.get_count()?)
+            .filter_map(|index| {
+                let key = self.get_at(index).ok()?;
+                Some((key, self.get_value(&key).ok()?))
+            })
+            .collect())
     }
 
     /// See also: [`IPropertyStore::GetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getvalue)
-    pub fn get_value(&self, key: &PropertyKey) -> windows::Result<Property> {
-        unsafe { self.inner.GetValue(key.as_raw()).map(Property::from_raw) }
+    pub fn get_value(&self, key: &PropertyKey) -> Result<Property> {
+        self.get_prop_variant(key).map(|x| x.to_property())
+    }
+
+    /// Gets the value of a property and converts it to the key's type.
+    ///
+    /// Returns `None` if the property is empty, or an error if it has a
+    /// different type than expected.
+    pub fn get<T>(&self, key: &TypedPropertyKey<T>) -> Result<Option<T>, PropertyError>
+    where
+        T: FromProperty,
+    {
+        key.decode(self.get_value(key)?)
+    }
+
+    /// Sets the value of a property of the key's type.
+    pub fn set<T>(&self, key: &TypedPropertyKey<T>, value: T) -> Result<()>
+    where
+        T: IntoProperty,
+    {
+        self.set_value(key, &value.into_property())
+    }
+
+    /// Gets the raw value of a property, without copying it.
+    ///
+    /// See also: [`IPropertyStore::GetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getvalue)
+    pub fn get_prop_variant(&self, key: &PropertyKey) -> Result<PropVariant> {
+        unsafe {
+            self.inner
+                .GetValue(key.as_raw())
+                .map(|raw| PropVariant::from_raw(raw))
+        }
+        .context("IPropertyStore::GetValue")
+    }
+
+    /// Returns an [`InvalidArgument`](crate::ErrorKind::InvalidArgument) error
+    /// if the property is [`Property::Unsupported`].
+    ///
+    /// See also: [`IPropertyStore::SetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-setvalue)
+    pub fn set_value(&self, key: &PropertyKey, property: &Property) -> Result<()> {
+        self.set_prop_variant(key, &PropVariant::new(property)?)
     }
 
+    /// Sets the raw value of a property.
+    ///
     /// See also: [`IPropertyStore::SetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-setvalue)
-    pub fn set_value(&self, key: &PropertyKey, property: &Property) -> windows::Result<()> {
-        unsafe { self.inner.SetValue(key.as_raw(), &property.to_raw()) }
+    pub fn set_prop_variant(&self, key: &PropertyKey, value: &PropVariant) -> Result<()> {
+        unsafe { self.inner.SetValue(key.as_raw(), value.as_raw()) }
+            .context("IPropertyStore::SetValue")
+    }
+}
+
+impl<'a> IntoIterator for &'a PropertyStore {
+    type IntoIter = PropertyIter<'a>;
+    type Item = (PropertyKey, Result<Property>);
+
+    fn into_iter(self) -> Self::IntoIter {
+        PropertyIter::new(self)
     }
 }
+
+/// An iterator over the keys of a [`PropertyStore`] and their values.
+///
+/// An error reading a value is yielded with its key, and iteration continues
+/// with the next property. Keys that cannot be read are skipped, and nothing
+/// is yielded if the number of properties cannot be read.
+#[derive(Debug, Clone)]
+pub struct PropertyIter<'a> {
+    inner: &'a PropertyStore,
+    range: Range<u32>,
+}
+
+impl<'a> PropertyIter<'a> {
+    pub(crate) fn new(inner: &'a PropertyStore) -> Self {
+        Self {
+            inner,
+            range: 0..inner.get_count().unwrap_or(0),
+        }
+    }
+}
+
+impl<'a> Iterator for PropertyIter<'a> {
+    type Item = (PropertyKey, Result<Property>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = self.inner;
+        self.range.find_map(|index| {
+            let key = inner.get_at(index).ok()?;
+            Some((key, inner.get_value(&key)))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.range.len()))
+    }
+}
+
+impl<'a> FusedIterator for PropertyIter<'a> {}
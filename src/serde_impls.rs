@@ -0,0 +1,497 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! In human-readable formats, strings are serialized as UTF-16 decoded
+//! strings where possible. A string that is not valid UTF-16 is serialized as
+//! a sequence of code units instead, so that opaque identifiers survive a
+//! round trip unchanged. Enums are serialized by variant name. An `Unknown`
+//! value has no name, so it is serialized as its raw value instead: a number,
+//! or a braced GUID for [`JackSubType`].
+//!
+//! Formats that are not self-describing, such as bincode, cannot tell these
+//! representations apart when deserializing. In those, strings are always a
+//! sequence of code units, and enums with an `Unknown` variant are always
+//! their raw value.
+
+use std::convert::TryFrom;
+use std::fmt::{self, Formatter};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use windows::Guid;
+
+use crate::{
+    bits::{AudioSessionState, DataFlow, DeviceRole, DeviceState, EndpointFormFactor, JackSubType},
+    property_store::PropertyKey,
+    string::{WinStr, WinString},
+    util::parse_guid,
+};
+
+impl Serialize for WinStr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() {
+            return serializer.collect_seq(self.as_slice());
+        }
+        match self.to_string() {
+            Ok(s) => serializer.serialize_str(&s),
+            Err(_) => serializer.collect_seq(self.as_slice()),
+        }
+    }
+}
+
+impl Serialize for WinString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.as_winstr().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WinString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct WinStringVisitor;
+
+        impl<'de> Visitor<'de> for WinStringVisitor {
+            type Value = WinString;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                f.write_str("a string or a sequence of UTF-16 code units")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(unit) = seq.next_element()? {
+                    vec.push(unit);
+                }
+                WinString::from_vec(vec).map_err(de::Error::custom)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(WinStringVisitor)
+        } else {
+            deserializer.deserialize_seq(WinStringVisitor)
+        }
+    }
+}
+
+/// Serializes as the canonical `{fmtid} pid` form.
+impl Serialize for PropertyKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PropertyKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Serializes a [`Guid`](windows::Guid) in its braced string form, for use
+/// with `#[serde(with = "...")]`.
+pub(crate) mod guid {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use windows::Guid;
+
+    use crate::util::{guid_to_string, parse_guid};
+
+    pub(crate) fn serialize<S>(guid: &Guid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&guid_to_string(guid))
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Guid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        parse_guid(&s).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"a GUID"))
+    }
+}
+
+/// Implements serde traits for `map_enum!` types, using the variant names.
+///
+/// Types with an `Unknown(raw)` variant serialize unknown values as the raw
+/// value, and accept either a name or a raw value when deserializing. In
+/// formats that are not human-readable, they always use the raw value.
+macro_rules! serde_enum {
+    ($($name:ident),* $(,)?; $($unknown_name:ident: $repr:ty),* $(,)?) => {
+        $(
+            impl Serialize for $name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    serializer.serialize_str(self.name())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    let s = String::deserialize(deserializer)?;
+                    Self::from_name(&s).ok_or_else(|| de::Error::unknown_variant(&s, Self::NAMES))
+                }
+            }
+        )*
+
+        $(
+            impl Serialize for $unknown_name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    match self {
+                        _ if !serializer.is_human_readable() => {
+                            RawValue::serialize(&self.to_raw(), serializer)
+                        }
+                        Self::Unknown(raw) => RawValue::serialize(raw, serializer),
+                        _ => serializer.serialize_str(self.name()),
+                    }
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $unknown_name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    struct EnumVisitor;
+
+                    impl<'de> Visitor<'de> for EnumVisitor {
+                        type Value = $unknown_name;
+
+                        fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                            write!(
+                                f,
+                                "a {} name or {}",
+                                stringify!($unknown_name),
+                                <$repr as RawValue>::EXPECTING,
+                            )
+                        }
+
+                        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                        where
+                            E: de::Error,
+                        {
+                            $unknown_name::from_name(v)
+                                .or_else(|| <$repr>::parse(v).map(Into::into))
+                                .ok_or_else(|| E::unknown_variant(v, $unknown_name::NAMES))
+                        }
+
+                        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+                        where
+                            E: de::Error,
+                        {
+                            <$repr>::from_u64(v)
+                                .map(Into::into)
+                                .ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+                        }
+
+                        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+                        where
+                            E: de::Error,
+                        {
+                            <$repr>::from_i64(v)
+                                .map(Into::into)
+                                .ok_or_else(|| E::invalid_value(de::Unexpected::Signed(v), &self))
+                        }
+                    }
+
+                    if deserializer.is_human_readable() {
+                        deserializer.deserialize_any(EnumVisitor)
+                    } else {
+                        <$repr as RawValue>::deserialize_raw(deserializer, EnumVisitor)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// The raw value of an `Unknown(raw)` variant.
+trait RawValue: Sized {
+    const EXPECTING: &'static str;
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    /// Deserializes the type's own representation, for formats that are not
+    /// self-describing.
+    fn deserialize_raw<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Visitor<'de>;
+
+    fn parse(_s: &str) -> Option<Self> {
+        None
+    }
+
+    fn from_u64(_v: u64) -> Option<Self> {
+        None
+    }
+
+    fn from_i64(_v: i64) -> Option<Self> {
+        None
+    }
+}
+
+impl RawValue for u32 {
+    const EXPECTING: &'static str = "an unsigned 32-bit integer";
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(*self)
+    }
+
+    fn deserialize_raw<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Visitor<'de>,
+    {
+        deserializer.deserialize_u32(visitor)
+    }
+
+    fn from_u64(v: u64) -> Option<Self> {
+        TryFrom::try_from(v).ok()
+    }
+
+    fn from_i64(v: i64) -> Option<Self> {
+        TryFrom::try_from(v).ok()
+    }
+}
+
+impl RawValue for i32 {
+    const EXPECTING: &'static str = "a 32-bit integer";
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i32(*self)
+    }
+
+    fn deserialize_raw<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Visitor<'de>,
+    {
+        deserializer.deserialize_i32(visitor)
+    }
+
+    fn from_u64(v: u64) -> Option<Self> {
+        TryFrom::try_from(v).ok()
+    }
+
+    fn from_i64(v: i64) -> Option<Self> {
+        TryFrom::try_from(v).ok()
+    }
+}
+
+/// Uses the braced string form, as [`guid`] does.
+impl RawValue for Guid {
+    const EXPECTING: &'static str = "a GUID";
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        guid::serialize(self, serializer)
+    }
+
+    fn deserialize_raw<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Visitor<'de>,
+    {
+        deserializer.deserialize_str(visitor)
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        parse_guid(s)
+    }
+}
+
+serde_enum!(
+    DataFlow,
+    DeviceRole;
+    DeviceState: u32,
+    AudioSessionState: i32,
+    EndpointFormFactor: u32,
+    JackSubType: Guid,
+);
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::json;
+    use windows::Guid;
+
+    use crate::{
+        bits::{
+            AudioSessionState, DataFlow, DeviceRole, DeviceState, EndpointFormFactor, JackSubType,
+        },
+        pkeys,
+        property_store::{Property, PropertyKey},
+        string::WinString,
+    };
+
+    fn round_trip<T>(value: &T, expected: serde_json::Value)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let json = serde_json::to_value(value).unwrap();
+        assert_eq!(json, expected);
+        assert_eq!(&serde_json::from_value::<T>(json).unwrap(), value);
+    }
+
+    #[test]
+    fn winstring() {
+        round_trip(&WinString::from("Speakers"), json!("Speakers"));
+        let unpaired = WinString::from_vec(vec![0x61, 0xD800]).unwrap();
+        round_trip(&unpaired, json!([0x61, 0xD800]));
+        assert!(serde_json::from_value::<WinString>(json!("a\0b")).is_err());
+    }
+
+    #[test]
+    fn property_key() {
+        round_trip(
+            &pkeys::DEVICE_FRIENDLY_NAME.key(),
+            json!("{A45C254E-DF1C-4EFD-8020-67D146A850E0} 14"),
+        );
+        assert!(serde_json::from_value::<PropertyKey>(json!("14")).is_err());
+    }
+
+    #[test]
+    fn property() {
+        let guid = Guid::from_values(
+            0xa45c254e,
+            0xdf1c,
+            0x4efd,
+            [0x80, 0x20, 0x67, 0xd1, 0x46, 0xa8, 0x50, 0xe0],
+        );
+        round_trip(&Property::Empty, json!("Empty"));
+        round_trip(&Property::U32(7), json!({ "U32": 7 }));
+        round_trip(&Property::F32(0.5), json!({ "F32": 0.5 }));
+        round_trip(
+            &Property::Str("Speakers".into()),
+            json!({ "Str": "Speakers" }),
+        );
+        round_trip(
+            &Property::Guid(guid),
+            json!({ "Guid": "{A45C254E-DF1C-4EFD-8020-67D146A850E0}" }),
+        );
+        round_trip(&Property::Blob(vec![1, 2]), json!({ "Blob": [1, 2] }));
+        round_trip(
+            &Property::StrVec(vec!["a".into(), "b".into()]),
+            json!({ "StrVec": ["a", "b"] }),
+        );
+        round_trip(&Property::Unsupported, json!("Unsupported"));
+    }
+
+    #[test]
+    fn enums() {
+        fn all<T>(values: &[T])
+        where
+            T: Serialize + DeserializeOwned + PartialEq + Debug + ToString,
+        {
+            for value in values {
+                round_trip(value, json!(value.to_string()));
+            }
+        }
+
+        all(DataFlow::ALL);
+        all(DeviceRole::ALL);
+        all(DeviceState::ALL);
+        all(AudioSessionState::ALL);
+        all(EndpointFormFactor::ALL);
+        all(JackSubType::ALL);
+
+        assert!(serde_json::from_value::<DataFlow>(json!("Sideways")).is_err());
+        assert!(serde_json::from_value::<DeviceState>(json!("Sideways")).is_err());
+    }
+
+    /// Round trips through bincode, which is not self-describing.
+    fn bincode_round_trip<T>(value: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let bytes = bincode::serialize(value).unwrap();
+        assert_eq!(&bincode::deserialize::<T>(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn not_self_describing() {
+        bincode_round_trip(&WinString::from("Speakers"));
+        bincode_round_trip(&WinString::from_vec(vec![0x61, 0xD800]).unwrap());
+        bincode_round_trip(&pkeys::DEVICE_FRIENDLY_NAME.key());
+        bincode_round_trip(&Property::Str("Speakers".into()));
+        bincode_round_trip(&DataFlow::Capture);
+        bincode_round_trip(&DeviceState::Active);
+        bincode_round_trip(&DeviceState::Unknown(0x20));
+        bincode_round_trip(&AudioSessionState::Unknown(-1));
+        bincode_round_trip(&EndpointFormFactor::Headphones);
+        bincode_round_trip(&JackSubType::ALL[0]);
+        bincode_round_trip(&JackSubType::Unknown(Guid::from_values(1, 2, 3, [4; 8])));
+
+        // Known values use the raw value too.
+        assert_eq!(
+            bincode::serialize(&DeviceState::Active).unwrap(),
+            bincode::serialize(&1u32).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_enums() {
+        round_trip(&DeviceState::Unknown(0x20), json!(0x20));
+        round_trip(&AudioSessionState::Unknown(-1), json!(-1));
+        round_trip(&EndpointFormFactor::Unknown(99), json!(99));
+        round_trip(
+            &JackSubType::Unknown(Guid::from_values(1, 2, 3, [4; 8])),
+            json!("{00000001-0002-0003-0404-040404040404}"),
+        );
+
+        // Raw values of known variants are accepted too.
+        assert_eq!(
+            serde_json::from_value::<DeviceState>(json!(1)).unwrap(),
+            DeviceState::Active
+        );
+        assert!(serde_json::from_value::<DeviceState>(json!(-1)).is_err());
+        assert!(serde_json::from_value::<JackSubType>(json!(1)).is_err());
+    }
+}
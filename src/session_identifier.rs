@@ -0,0 +1,310 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use windows::Guid;
+
+use crate::{
+    endpoint_id::EndpointId,
+    string::{WinStr, WinString},
+    util::parse_guid,
+};
+
+/// A parsed audio session identifier, as returned by
+/// [`AudioSessionControl2::get_session_identifier`] and
+/// [`AudioSessionControl2::get_session_instance_identifier`].
+///
+/// Session identifiers have the form `endpoint|executable%bgrouping`, where
+/// `endpoint` is the [endpoint ID](EndpointId) of the session's device,
+/// `executable` is the device path of the process's executable (or `#` for
+/// the system sounds session), and `grouping` is the grouping parameter of
+/// the session. Session instance identifiers also have an instance suffix,
+/// separated by another `|`, which identifies the process.
+///
+/// The original string is preserved exactly, and is reproduced by
+/// [`Display`] and [`to_winstring`](Self::to_winstring). Comparisons are
+/// exact; note that Windows does not guarantee the case of the executable
+/// path, so consider [`CaseInsensitive`](crate::string::CaseInsensitive) if
+/// these are used as keys.
+///
+/// [`AudioSessionControl2::get_session_identifier`]: crate::AudioSessionControl2::get_session_identifier
+/// [`AudioSessionControl2::get_session_instance_identifier`]: crate::AudioSessionControl2::get_session_instance_identifier
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionIdentifier {
+    endpoint: String,
+    executable: String,
+    grouping: String,
+    instance: Option<String>,
+}
+
+impl SessionIdentifier {
+    const SYSTEM_SOUNDS: &'static str = "#";
+
+    /// The raw endpoint ID of the session's device.
+    pub fn endpoint_id_str(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The endpoint ID of the session's device, if it is well-formed.
+    pub fn endpoint_id(&self) -> Option<EndpointId> {
+        self.endpoint.parse().ok()
+    }
+
+    /// The device path of the executable that owns the session, e.g.
+    /// `\Device\HarddiskVolume3\Windows\explorer.exe`.
+    ///
+    /// Returns `None` for the system sounds session.
+    pub fn executable_path(&self) -> Option<&str> {
+        if self.is_system_sounds() {
+            None
+        } else {
+            Some(&self.executable)
+        }
+    }
+
+    /// The file name of the executable that owns the session, e.g.
+    /// `explorer.exe`.
+    ///
+    /// Returns `None` for the system sounds session.
+    pub fn file_name(&self) -> Option<&str> {
+        self.executable_path()
+            .map(|path| path.rsplit('\\').next().unwrap_or(path))
+    }
+
+    /// Whether this is the identifier of the system sounds session.
+    pub fn is_system_sounds(&self) -> bool {
+        self.executable == Self::SYSTEM_SOUNDS
+    }
+
+    /// The raw grouping parameter of the session.
+    pub fn grouping_str(&self) -> &str {
+        &self.grouping
+    }
+
+    /// The grouping parameter of the session, if it is a GUID.
+    pub fn grouping_param(&self) -> Option<Guid> {
+        if self.grouping.starts_with('{') {
+            parse_guid(&self.grouping)
+        } else {
+            None
+        }
+    }
+
+    /// The raw instance suffix, if this is a session instance identifier,
+    /// e.g. `1%b1234`.
+    pub fn instance_suffix(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// Whether this is a session instance identifier, rather than a session
+    /// identifier.
+    pub fn is_instance(&self) -> bool {
+        self.instance.is_some()
+    }
+
+    /// The ID of the process that owns the session, if this is a session
+    /// instance identifier of a process-specific session.
+    pub fn process_id(&self) -> Option<u32> {
+        let instance = self.instance.as_deref()?;
+        let (_, pid) = instance.rsplit_once("%b")?;
+        pid.parse().ok()
+    }
+
+    /// Returns the session identifier without the instance suffix.
+    pub fn without_instance(&self) -> Self {
+        Self {
+            instance: None,
+            ..self.clone()
+        }
+    }
+
+    /// Formats the identifier as a `WinString`.
+    pub fn to_winstring(&self) -> WinString {
+        self.to_string().into()
+    }
+}
+
+impl Display for SessionIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}%b{}",
+            self.endpoint, self.executable, self.grouping
+        )?;
+        if let Some(instance) = &self.instance {
+            write!(f, "|{}", instance)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SessionIdentifier {
+    type Err = ParseSessionIdentifierError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '|');
+        let endpoint = parts.next().ok_or(ParseSessionIdentifierError)?;
+        let session = parts.next().ok_or(ParseSessionIdentifierError)?;
+        let instance = parts.next();
+        // The executable path may contain `%b` itself, but the grouping
+        // parameter does not.
+        let (executable, grouping) = session
+            .rsplit_once("%b")
+            .ok_or(ParseSessionIdentifierError)?;
+        Ok(Self {
+            endpoint: endpoint.to_owned(),
+            executable: executable.to_owned(),
+            grouping: grouping.to_owned(),
+            instance: instance.map(ToOwned::to_owned),
+        })
+    }
+}
+
+impl TryFrom<&WinStr> for SessionIdentifier {
+    type Error = ParseSessionIdentifierError;
+
+    fn try_from(value: &WinStr) -> Result<Self, Self::Error> {
+        value
+            .to_string()
+            .map_err(|_| ParseSessionIdentifierError)?
+            .parse()
+    }
+}
+
+/// An error returned when parsing a [`SessionIdentifier`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseSessionIdentifierError;
+
+impl Display for ParseSessionIdentifierError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid session identifier, expected `endpoint|executable%bgrouping`")
+    }
+}
+
+impl Error for ParseSessionIdentifierError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bits::DataFlow, winstr};
+
+    const ENDPOINT: &str = "{0.0.0.00000000}.{3a8b6c4e-2f1d-4e5a-9b7c-0d1e2f3a4b5c}";
+    const GROUPING: &str = "{5C4D2D1B-5B3B-4B0A-9F5A-2A6A0F1E3C7D}";
+
+    fn grouping() -> Guid {
+        Guid::from_values(
+            0x5c4d2d1b,
+            0x5b3b,
+            0x4b0a,
+            [0x9f, 0x5a, 0x2a, 0x6a, 0x0f, 0x1e, 0x3c, 0x7d],
+        )
+    }
+
+    fn parse(s: &str) -> SessionIdentifier {
+        let id: SessionIdentifier = s.parse().unwrap();
+        assert_eq!(id.to_string(), s);
+        id
+    }
+
+    #[test]
+    fn session_identifier() {
+        let id = parse(&format!(
+            r"{}|\Device\HarddiskVolume3\Windows\explorer.exe%b{}",
+            ENDPOINT, GROUPING
+        ));
+        assert_eq!(id.endpoint_id_str(), ENDPOINT);
+        assert_eq!(
+            id.endpoint_id().map(|endpoint| endpoint.flow()),
+            Some(DataFlow::Render)
+        );
+        assert_eq!(
+            id.executable_path(),
+            Some(r"\Device\HarddiskVolume3\Windows\explorer.exe")
+        );
+        assert_eq!(id.file_name(), Some("explorer.exe"));
+        assert!(!id.is_system_sounds());
+        assert_eq!(id.grouping_str(), GROUPING);
+        assert_eq!(id.grouping_param(), Some(grouping()));
+        assert!(!id.is_instance());
+        assert_eq!(id.instance_suffix(), None);
+        assert_eq!(id.process_id(), None);
+    }
+
+    #[test]
+    fn system_sounds() {
+        let id = parse(&format!("{}|#%b{}", ENDPOINT, GROUPING));
+        assert!(id.is_system_sounds());
+        assert_eq!(id.executable_path(), None);
+        assert_eq!(id.file_name(), None);
+        assert_eq!(id.grouping_param(), Some(grouping()));
+
+        let id = parse(&format!("{}|#%b{}|1%b#", ENDPOINT, GROUPING));
+        assert!(id.is_system_sounds());
+        assert_eq!(id.instance_suffix(), Some("1%b#"));
+        assert_eq!(id.process_id(), None);
+    }
+
+    #[test]
+    fn instance_identifier() {
+        let session = format!(
+            r"{}|\Device\HarddiskVolume3\app.exe%b{}",
+            ENDPOINT, GROUPING
+        );
+        let id = parse(&format!("{}|1%b1234", session));
+        assert!(id.is_instance());
+        assert_eq!(id.instance_suffix(), Some("1%b1234"));
+        assert_eq!(id.process_id(), Some(1234));
+        assert_eq!(id.without_instance(), parse(&session));
+        assert_eq!(id.without_instance().to_string(), session);
+        assert_eq!(
+            SessionIdentifier::try_from(id.to_winstring().as_winstr()),
+            Ok(id)
+        );
+    }
+
+    #[test]
+    fn percent_b_in_executable_path() {
+        let id = parse(&format!(
+            r"{}|\Device\HarddiskVolume3\100%bass\app.exe%b{}|1%b42",
+            ENDPOINT, GROUPING
+        ));
+        assert_eq!(
+            id.executable_path(),
+            Some(r"\Device\HarddiskVolume3\100%bass\app.exe")
+        );
+        assert_eq!(id.file_name(), Some("app.exe"));
+        assert_eq!(id.grouping_param(), Some(grouping()));
+        assert_eq!(id.process_id(), Some(42));
+    }
+
+    #[test]
+    fn nonstandard_parts() {
+        let id = parse("speakers|app.exe%bgroup|instance");
+        assert_eq!(id.endpoint_id(), None);
+        assert_eq!(id.grouping_param(), None);
+        assert_eq!(id.process_id(), None);
+    }
+
+    #[test]
+    fn malformed() {
+        for s in [
+            "",
+            ENDPOINT,
+            "speakers|app.exe",
+            "speakers|app.exe|1%b42",
+            "speakers%bgroup",
+        ] {
+            assert_eq!(
+                s.parse::<SessionIdentifier>(),
+                Err(ParseSessionIdentifierError),
+                "{:?}",
+                s
+            );
+        }
+        let unpaired = WinString::from_vec(vec![b'a' as u16, 0xD800]).unwrap();
+        assert!(SessionIdentifier::try_from(unpaired.as_winstr()).is_err());
+        assert!(SessionIdentifier::try_from(winstr!("a|b%bc")).is_ok());
+    }
+}
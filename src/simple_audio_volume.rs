@@ -1,7 +1,9 @@
 use windows::Guid;
 
 use crate::{
-    bindings::Windows::Win32::Media::Audio::CoreAudio::ISimpleAudioVolume, util::as_raw_or_null,
+    bindings::Windows::Win32::Media::Audio::CoreAudio::ISimpleAudioVolume,
+    error::{Context, Result},
+    util::as_raw_or_null,
 };
 
 /// See also: [`ISimpleAudioVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nn-audioclient-isimpleaudiovolume)
@@ -16,29 +18,27 @@ impl SimpleAudioVolume {
     }
 
     /// See also: [`ISimpleAudioVolume::GetMasterVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-getmastervolume)
-    pub fn get_master_volume(&self) -> windows::Result<f32> {
-        unsafe { self.inner.GetMasterVolume() }
+    pub fn get_master_volume(&self) -> Result<f32> {
+        unsafe { self.inner.GetMasterVolume() }.context("ISimpleAudioVolume::GetMasterVolume")
     }
 
     /// See also: [`ISimpleAudioVolume::GetMute`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-getmute)
-    pub fn get_mute(&self) -> windows::Result<bool> {
-        unsafe { self.inner.GetMute().map(Into::into) }
+    pub fn get_mute(&self) -> Result<bool> {
+        unsafe { self.inner.GetMute().map(Into::into) }.context("ISimpleAudioVolume::GetMute")
     }
 
     /// See also: [`ISimpleAudioVolume::SetMasterVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-setmastervolume)
-    pub fn set_master_volume(
-        &self,
-        volume_level: f32,
-        event_context: Option<&Guid>,
-    ) -> windows::Result<()> {
+    pub fn set_master_volume(&self, volume_level: f32, event_context: Option<&Guid>) -> Result<()> {
         unsafe {
             self.inner
                 .SetMasterVolume(volume_level, as_raw_or_null(event_context))
         }
+        .context("ISimpleAudioVolume::SetMasterVolume")
     }
 
     /// See also: [`ISimpleAudioVolume::SetMute`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-setmute)
-    pub fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> windows::Result<()> {
+    pub fn set_mute(&self, mute: bool, event_context: Option<&Guid>) -> Result<()> {
         unsafe { self.inner.SetMute(mute, as_raw_or_null(event_context)) }
+            .context("ISimpleAudioVolume::SetMute")
     }
 }
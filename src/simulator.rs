@@ -0,0 +1,1219 @@
+//! Scripted scenarios for the [`mock`](crate::mock) backend, on a virtual
+//! clock.
+//!
+//! A [`Scenario`] is a list of timed steps, such as plugging in a headset,
+//! changing the default device or an application opening a session, and
+//! checks on the resulting state. A [`Simulator`] runs scenarios against a
+//! [`MockBackend`]. Each step happens at its scheduled [`VirtualClock`] time,
+//! and the callbacks registered with the backend are called in order while
+//! the step runs.
+//!
+//! [`EventRecorder`] implements every callback trait and records each event
+//! with the time it was delivered, so the order and timing can be asserted
+//! afterwards:
+//!
+//! ```
+//! use std::time::Duration;
+//! use win32_coreaudio::{
+//!     backend::{Backend, DeviceEnumeratorBackend},
+//!     simulator::{Event, Scenario, Simulator},
+//!     winstr, DataFlow, DeviceEvent, DeviceRole, DeviceState,
+//! };
+//!
+//! let mut sim = Simulator::new();
+//! sim.schedule(Scenario::new().add_device("speakers", DataFlow::Render));
+//! sim.run().unwrap();
+//!
+//! let recorder = sim.recorder();
+//! let enumerator = sim.backend().device_enumerator()?;
+//! let _handle = enumerator.register_endpoint_notification(recorder.clone())?;
+//!
+//! sim.schedule(
+//!     Scenario::new()
+//!         .wait(Duration::from_secs(5))
+//!         .plug_in_headset("headset.render", "headset.capture")
+//!         .expect_default_device(DataFlow::Render, DeviceRole::Console, "headset.render")
+//!         .wait(Duration::from_secs(60))
+//!         .unplug("headset.render")
+//!         .expect_default_device(DataFlow::Render, DeviceRole::Console, "speakers"),
+//! );
+//! sim.run().unwrap();
+//!
+//! let removed = recorder
+//!     .events()
+//!     .into_iter()
+//!     .find(|recorded| {
+//!         recorded.event
+//!             == Event::Device(DeviceEvent::DeviceStateChanged {
+//!                 device_id: winstr!("headset.render").to_winstring(),
+//!                 state: DeviceState::NotPresent,
+//!             })
+//!     })
+//!     .unwrap();
+//! assert_eq!(removed.time, Duration::from_secs(65));
+//! # Ok::<(), win32_coreaudio::Error>(())
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::rc::Rc;
+use std::time::Duration;
+
+use windows::Guid;
+
+use crate::{
+    audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
+    audio_session_events::AudioSessionEvents,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{
+        AudioEndpointVolumeBackend, AudioSessionControlBackend, DeviceBackend,
+        DeviceEnumeratorBackend, SessionNotification,
+    },
+    bits::{
+        AudioSessionDisconnectReason, AudioSessionState, DataFlow, DataFlowMask, DeviceRole,
+        DeviceState, DeviceStateMask,
+    },
+    error::{Error, ErrorKind, Result},
+    events::{DeviceEvent, DuckEvent, Forward, SessionEvent, VolumeEvent},
+    mock::{MockAudioSessionControl, MockBackend, MockDevice},
+    notification_client::NotificationClient,
+    property_store::{Property, PropertyKey},
+    string::{WinStr, WinString},
+};
+
+/// The simulated time of a [`Simulator`].
+///
+/// Clones share the same time, so callbacks can hold one to find out when
+/// they are called.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock(Rc<Cell<Duration>>);
+
+impl VirtualClock {
+    /// The time since the simulator was created.
+    pub fn now(&self) -> Duration {
+        self.0.get()
+    }
+
+    fn set(&self, time: Duration) {
+        self.0.set(time);
+    }
+}
+
+type Action = Box<dyn FnOnce(&mut Simulator) -> std::result::Result<(), ScenarioErrorKind>>;
+
+struct Step {
+    time: Duration,
+    description: String,
+    action: Action,
+}
+
+impl Debug for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Step")
+            .field("time", &self.time)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// A list of timed steps to run on a [`Simulator`].
+///
+/// Steps are added at the current time of the scenario, which starts at zero
+/// and is moved forward with [`wait`](Self::wait). Times are relative to when
+/// the scenario is [scheduled](Simulator::schedule). Steps at the same time run
+/// in the order they were added.
+///
+/// Devices are identified by their ID, and sessions by the name given to
+/// [`open_session`](Self::open_session).
+#[derive(Debug, Default)]
+#[must_use = "a scenario does nothing until it is scheduled on a simulator"]
+pub struct Scenario {
+    cursor: Duration,
+    steps: Vec<Step>,
+}
+
+impl Scenario {
+    /// Creates an empty scenario.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the time of the following steps forward.
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.cursor += duration;
+        self
+    }
+
+    /// Sets the time of the following steps, relative to the start of the
+    /// scenario.
+    pub fn at(mut self, time: Duration) -> Self {
+        self.cursor = time;
+        self
+    }
+
+    fn step(
+        mut self,
+        description: String,
+        action: impl FnOnce(&mut Simulator) -> std::result::Result<(), ScenarioErrorKind> + 'static,
+    ) -> Self {
+        self.steps.push(Step {
+            time: self.cursor,
+            description,
+            action: Box::new(action),
+        });
+        self
+    }
+
+    /// Runs a custom step.
+    pub fn then<F>(self, description: &str, f: F) -> Self
+    where
+        F: FnOnce(&mut Simulator) -> Result<()> + 'static,
+    {
+        self.step(description.to_owned(), |sim| Ok(f(sim)?))
+    }
+
+    /// Checks the state of the simulator. The scenario fails if `f` returns
+    /// `false`.
+    pub fn check<F>(self, description: &str, f: F) -> Self
+    where
+        F: FnOnce(&Simulator) -> Result<bool> + 'static,
+    {
+        self.step(description.to_owned(), |sim| {
+            if f(sim)? {
+                Ok(())
+            } else {
+                Err(ScenarioErrorKind::CheckFailed(
+                    "check returned false".to_owned(),
+                ))
+            }
+        })
+    }
+
+    /// Adds a new, active device, as a driver would when it is installed.
+    ///
+    /// See [`MockBackend::add_device`].
+    pub fn add_device(self, device_id: &str, data_flow: DataFlow) -> Self {
+        let id = WinString::from(device_id);
+        self.step(format!("add device {}", device_id), move |sim| {
+            sim.backend.add_device(&id, data_flow);
+            Ok(())
+        })
+    }
+
+    /// Removes a device. Its sessions are disconnected.
+    ///
+    /// See [`MockDevice::remove`].
+    pub fn remove_device(self, device_id: &str) -> Self {
+        let id = WinString::from(device_id);
+        self.step(format!("remove device {}", device_id), move |sim| {
+            Ok(sim.device(&id)?.remove()?)
+        })
+    }
+
+    /// Changes the state of a device, without changing the default devices
+    /// or its sessions.
+    pub fn set_device_state(self, device_id: &str, state: DeviceState) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("set state of {} to {}", device_id, state),
+            move |sim| Ok(sim.device(&id)?.set_state(state)?),
+        )
+    }
+
+    /// Changes the default device for a data flow and role.
+    pub fn set_default_device(
+        self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &str,
+    ) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("set default {} {} device to {}", data_flow, role, device_id),
+            move |sim| Ok(sim.backend.set_default_device(data_flow, role, &id)?),
+        )
+    }
+
+    /// Sets a property of a device.
+    pub fn set_property(self, device_id: &str, key: PropertyKey, value: Property) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("set property {} of {}", key, device_id),
+            move |sim| Ok(sim.device(&id)?.set_property(&key, value)?),
+        )
+    }
+
+    /// Sets the master volume of a device, as the volume mixer would.
+    pub fn set_master_volume(self, device_id: &str, level: f32) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("set master volume of {} to {}", device_id, level),
+            move |sim| {
+                let volume = sim.device(&id)?.activate_audio_endpoint_volume()?;
+                Ok(volume.set_master_volume_level_scalar(level, None)?)
+            },
+        )
+    }
+
+    /// Mutes or unmutes a device, as the volume mixer would.
+    pub fn set_mute(self, device_id: &str, mute: bool) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("set mute of {} to {}", device_id, mute),
+            move |sim| {
+                let volume = sim.device(&id)?.activate_audio_endpoint_volume()?;
+                Ok(volume.set_mute(mute, None)?)
+            },
+        )
+    }
+
+    /// Plugs in a headset with a render and a capture endpoint, which become
+    /// the default devices for every role.
+    ///
+    /// Endpoints that do not exist yet are added, as on the first time a USB
+    /// headset is plugged in. Existing endpoints become active.
+    pub fn plug_in_headset(self, render_id: &str, capture_id: &str) -> Self {
+        let render = WinString::from(render_id);
+        let capture = WinString::from(capture_id);
+        self.step(format!("plug in headset {}", render_id), move |sim| {
+            for (id, data_flow) in [(render, DataFlow::Render), (capture, DataFlow::Capture)] {
+                sim.activate_device(&id, data_flow)?;
+                for &role in DeviceRole::ALL {
+                    sim.backend.set_default_device(data_flow, role, &id)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Unplugs a device, as when a USB device is disconnected.
+    ///
+    /// Its sessions are disconnected, the default devices it was used for
+    /// move to another active device, and its state becomes
+    /// [`NotPresent`](DeviceState::NotPresent).
+    pub fn unplug(self, device_id: &str) -> Self {
+        let id = WinString::from(device_id);
+        self.step(format!("unplug {}", device_id), move |sim| {
+            sim.deactivate_device(&id, DeviceState::NotPresent, None)
+        })
+    }
+
+    /// Switches a Bluetooth device between profiles, e.g. from A2DP to
+    /// Hands-Free when an application starts using its microphone.
+    ///
+    /// The endpoint for the new profile becomes active (and is added if it
+    /// does not exist yet) and takes over the default devices of the old one.
+    /// The old endpoint's sessions are disconnected and its state becomes
+    /// [`Unplugged`](DeviceState::Unplugged).
+    pub fn bluetooth_profile_flip(self, from_id: &str, to_id: &str) -> Self {
+        let from = WinString::from(from_id);
+        let to = WinString::from(to_id);
+        self.step(
+            format!("switch Bluetooth profile from {} to {}", from_id, to_id),
+            move |sim| {
+                sim.activate_device(&to, DataFlow::Render)?;
+                sim.deactivate_device(&from, DeviceState::Unplugged, Some(&to))
+            },
+        )
+    }
+
+    /// An application opens an audio stream on a device, creating a session
+    /// that becomes active.
+    ///
+    /// `name` is used as the session identifier, and to refer to the session
+    /// in later steps.
+    pub fn open_session(self, device_id: &str, name: &str, process_id: u32) -> Self {
+        let id = WinString::from(device_id);
+        let name = name.to_owned();
+        self.step(
+            format!("open session {} on {}", name, device_id),
+            move |sim| {
+                let session = sim
+                    .device(&id)?
+                    .add_session(&WinString::from(name.as_str()), process_id)?;
+                sim.sessions.push((name, id, session.clone()));
+                Ok(session.set_state(AudioSessionState::Active)?)
+            },
+        )
+    }
+
+    /// Changes the state of a session, e.g. when its application pauses
+    /// playback.
+    pub fn set_session_state(self, name: &str, state: AudioSessionState) -> Self {
+        let name = name.to_owned();
+        self.step(
+            format!("set state of session {} to {}", name, state),
+            move |sim| Ok(sim.find_session(&name)?.set_state(state)?),
+        )
+    }
+
+    /// An application closes its audio stream, making the session inactive
+    /// and then expired.
+    pub fn close_session(self, name: &str) -> Self {
+        let name = name.to_owned();
+        self.step(format!("close session {}", name), move |sim| {
+            let session = sim.find_session(&name)?;
+            session.set_state(AudioSessionState::Inactive)?;
+            Ok(session.set_state(AudioSessionState::Expired)?)
+        })
+    }
+
+    /// Disconnects a session.
+    pub fn disconnect_session(self, name: &str, reason: AudioSessionDisconnectReason) -> Self {
+        let name = name.to_owned();
+        self.step(
+            format!("disconnect session {} ({})", name, reason),
+            move |sim| Ok(sim.find_session(&name)?.disconnect(reason)?),
+        )
+    }
+
+    /// Removes the device of an active session while its application is
+    /// using it, as when a USB device is pulled out mid-stream.
+    ///
+    /// The session is disconnected with
+    /// [`DeviceRemoval`](AudioSessionDisconnectReason::DeviceRemoval), after
+    /// which calls on it fail with
+    /// [`DeviceInvalidated`](ErrorKind::DeviceInvalidated). The step fails if
+    /// the session is not active.
+    pub fn invalidate_session_device(self, name: &str) -> Self {
+        let name = name.to_owned();
+        self.step(
+            format!("invalidate device of session {}", name),
+            move |sim| {
+                let (device_id, session) = sim.find_session_entry(&name)?;
+                let state = session.get_state()?;
+                expect_eq(
+                    state == AudioSessionState::Active,
+                    AudioSessionState::Active,
+                    state,
+                )?;
+                Ok(sim.device(device_id)?.remove()?)
+            },
+        )
+    }
+
+    /// Checks the default device for a data flow and role.
+    pub fn expect_default_device(
+        self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &str,
+    ) -> Self {
+        let expected = WinString::from(device_id);
+        self.step(
+            format!("expect default {} {} device {}", data_flow, role, device_id),
+            move |sim| {
+                let actual = sim
+                    .backend
+                    .get_default_audio_endpoint(data_flow, role)?
+                    .get_id()?;
+                expect_eq(actual.eq_ignore_case(&expected), &expected, &actual)
+            },
+        )
+    }
+
+    /// Checks the state of a device.
+    pub fn expect_device_state(self, device_id: &str, state: DeviceState) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("expect state of {} to be {}", device_id, state),
+            move |sim| {
+                let actual = sim.device(&id)?.get_state()?;
+                expect_eq(actual == state, state, actual)
+            },
+        )
+    }
+
+    /// Checks the state of a session.
+    pub fn expect_session_state(self, name: &str, state: AudioSessionState) -> Self {
+        let name = name.to_owned();
+        self.step(
+            format!("expect state of session {} to be {}", name, state),
+            move |sim| {
+                let actual = sim.find_session(&name)?.get_state()?;
+                expect_eq(actual == state, state, actual)
+            },
+        )
+    }
+
+    /// Checks that calls on a session fail with
+    /// [`DeviceInvalidated`](ErrorKind::DeviceInvalidated), as they do once its
+    /// device is removed.
+    pub fn expect_session_invalidated(self, name: &str) -> Self {
+        let name = name.to_owned();
+        self.step(
+            format!("expect session {} to be invalidated", name),
+            move |sim| match sim.find_session(&name)?.get_state() {
+                Err(e) if e.kind() == ErrorKind::DeviceInvalidated => Ok(()),
+                Err(e) => Err(e.into()),
+                Ok(state) => Err(ScenarioErrorKind::CheckFailed(format!(
+                    "expected the session to be invalidated, found state {}",
+                    state
+                ))),
+            },
+        )
+    }
+
+    /// Checks the master volume of a device.
+    pub fn expect_master_volume(self, device_id: &str, level: f32) -> Self {
+        let id = WinString::from(device_id);
+        self.step(
+            format!("expect master volume of {} to be {}", device_id, level),
+            move |sim| {
+                let volume = sim.device(&id)?.activate_audio_endpoint_volume()?;
+                let actual = volume.get_master_volume_level_scalar()?;
+                expect_eq((actual - level).abs() < 1e-6, level, actual)
+            },
+        )
+    }
+}
+
+fn expect_eq(
+    matches: bool,
+    expected: impl Display,
+    actual: impl Display,
+) -> std::result::Result<(), ScenarioErrorKind> {
+    if matches {
+        Ok(())
+    } else {
+        Err(ScenarioErrorKind::CheckFailed(format!(
+            "expected {}, found {}",
+            expected, actual
+        )))
+    }
+}
+
+/// Runs [`Scenario`]s against a [`MockBackend`] on a [`VirtualClock`].
+#[derive(Debug, Default)]
+pub struct Simulator {
+    backend: MockBackend,
+    clock: VirtualClock,
+    queue: Vec<Step>,
+    sessions: Vec<(String, WinString, MockAudioSessionControl)>,
+}
+
+impl Simulator {
+    /// Creates a simulator with an empty audio system, at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The simulated audio system.
+    pub fn backend(&self) -> &MockBackend {
+        &self.backend
+    }
+
+    /// The clock that steps are scheduled on.
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// The current simulated time.
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// Creates an [`EventRecorder`] that timestamps events with this
+    /// simulator's clock.
+    pub fn recorder(&self) -> EventRecorder {
+        EventRecorder::new(self.clock.clone())
+    }
+
+    /// Gets a device by its ID.
+    pub fn device(&self, device_id: &WinStr) -> Result<MockDevice> {
+        self.backend.get_device(device_id)
+    }
+
+    /// Gets a session opened by [`Scenario::open_session`] by its name.
+    ///
+    /// If several sessions were opened with the same name, this is the most
+    /// recent one.
+    pub fn session(&self, name: &str) -> Option<&MockAudioSessionControl> {
+        self.find_session_entry(name)
+            .ok()
+            .map(|(_, session)| session)
+    }
+
+    fn find_session(
+        &self,
+        name: &str,
+    ) -> std::result::Result<&MockAudioSessionControl, ScenarioErrorKind> {
+        self.find_session_entry(name).map(|(_, session)| session)
+    }
+
+    /// Finds a session and the ID of the device it was opened on.
+    fn find_session_entry(
+        &self,
+        name: &str,
+    ) -> std::result::Result<(&WinStr, &MockAudioSessionControl), ScenarioErrorKind> {
+        self.sessions
+            .iter()
+            .rev()
+            .find(|(session_name, _, _)| session_name == name)
+            .map(|(_, device_id, session)| (&**device_id, session))
+            .ok_or_else(|| ScenarioErrorKind::UnknownSession(name.to_owned()))
+    }
+
+    /// Schedules the steps of a scenario, relative to the current time.
+    pub fn schedule(&mut self, scenario: Scenario) {
+        let now = self.now();
+        for mut step in scenario.steps {
+            step.time += now;
+            let index = self
+                .queue
+                .partition_point(|queued| queued.time <= step.time);
+            self.queue.insert(index, step);
+        }
+    }
+
+    /// The number of steps that have not run yet.
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Moves the clock forward, running the steps that are due.
+    ///
+    /// If a step fails, the clock stops at the time of that step, and the
+    /// following steps stay scheduled.
+    pub fn advance(&mut self, duration: Duration) -> std::result::Result<(), ScenarioError> {
+        let until = self.now() + duration;
+        while self.queue.first().map_or(false, |step| step.time <= until) {
+            self.run_next()?;
+        }
+        self.clock.set(until);
+        Ok(())
+    }
+
+    /// Runs every scheduled step, moving the clock to the time of the last
+    /// one.
+    pub fn run(&mut self) -> std::result::Result<(), ScenarioError> {
+        while !self.queue.is_empty() {
+            self.run_next()?;
+        }
+        Ok(())
+    }
+
+    fn run_next(&mut self) -> std::result::Result<(), ScenarioError> {
+        let Step {
+            time,
+            description,
+            action,
+        } = self.queue.remove(0);
+        self.clock.set(time);
+        action(self).map_err(|kind| ScenarioError {
+            time,
+            step: description,
+            kind,
+        })
+    }
+
+    /// Adds a device, or makes an existing device active.
+    fn activate_device(&mut self, id: &WinStr, data_flow: DataFlow) -> Result<()> {
+        match self.device(id) {
+            Ok(device) => device.set_state(DeviceState::Active),
+            Err(_) => {
+                self.backend.add_device(id, data_flow);
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes a device out of use: its sessions are disconnected, its default
+    /// roles move to `replacement` (or another active device), and its state
+    /// changes.
+    fn deactivate_device(
+        &mut self,
+        id: &WinStr,
+        state: DeviceState,
+        replacement: Option<&WinStr>,
+    ) -> std::result::Result<(), ScenarioErrorKind> {
+        let device = self.device(id)?;
+        for (_, device_id, session) in &self.sessions {
+            if device_id.eq_ignore_case(id) && session.get_state()? != AudioSessionState::Expired {
+                session.disconnect(AudioSessionDisconnectReason::DeviceRemoval)?;
+            }
+        }
+        for (data_flow, mask) in [
+            (DataFlow::Render, DataFlowMask::Render),
+            (DataFlow::Capture, DataFlowMask::Capture),
+        ] {
+            for &role in DeviceRole::ALL {
+                let is_default = match self.backend.get_default_audio_endpoint(data_flow, role) {
+                    Ok(default) => default.get_id()?.eq_ignore_case(id),
+                    Err(_) => false,
+                };
+                if !is_default {
+                    continue;
+                }
+                let new_default = match replacement {
+                    Some(replacement) => Some(replacement.to_winstring()),
+                    None => self
+                        .backend
+                        .enum_audio_endpoints(mask, DeviceStateMask::ACTIVE)?
+                        .iter()
+                        .map(DeviceBackend::get_id)
+                        .collect::<Result<Vec<_>>>()?
+                        .into_iter()
+                        .find(|other| !other.eq_ignore_case(id)),
+                };
+                if let Some(new_default) = new_default {
+                    self.backend
+                        .set_default_device(data_flow, role, &new_default)?;
+                }
+            }
+        }
+        device.set_state(state)?;
+        Ok(())
+    }
+}
+
+/// An error from a step of a [`Scenario`].
+#[derive(Debug)]
+pub struct ScenarioError {
+    time: Duration,
+    step: String,
+    kind: ScenarioErrorKind,
+}
+
+impl ScenarioError {
+    /// The simulated time of the failed step.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// The description of the failed step.
+    pub fn step(&self) -> &str {
+        &self.step
+    }
+
+    pub fn kind(&self) -> &ScenarioErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "step `{}` at {:?} failed: ", self.step, self.time)?;
+        match &self.kind {
+            ScenarioErrorKind::Backend(e) => write!(f, "{}", e),
+            ScenarioErrorKind::UnknownSession(name) => write!(f, "no session named {}", name),
+            ScenarioErrorKind::CheckFailed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl StdError for ScenarioError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.kind {
+            ScenarioErrorKind::Backend(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The reason a step of a [`Scenario`] failed.
+#[derive(Debug)]
+pub enum ScenarioErrorKind {
+    /// A call to the mock backend failed.
+    Backend(Error),
+    /// No session was opened with this name.
+    UnknownSession(String),
+    /// A check did not match the state of the simulator.
+    CheckFailed(String),
+}
+
+impl From<Error> for ScenarioErrorKind {
+    fn from(e: Error) -> Self {
+        Self::Backend(e)
+    }
+}
+
+/// An event delivered to an [`EventRecorder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// The simulated time when the event was delivered.
+    pub time: Duration,
+    /// The source given to [`EventRecorder::with_source`].
+    pub source: Rc<str>,
+    pub event: Event,
+}
+
+/// A callback made by the mock backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A call to a [`NotificationClient`].
+    Device(DeviceEvent),
+    /// A call to a [`SessionNotification`], with the session identifier of
+    /// the new session.
+    SessionCreated { session_identifier: WinString },
+    /// A call to an [`AudioEndpointVolumeCallback`].
+    Volume(VolumeEvent),
+    /// A call to an [`AudioSessionEvents`].
+    Session(SessionEvent),
+    /// A call to an [`AudioVolumeDuckNotification`].
+    Duck(DuckEvent),
+}
+
+/// Records the events of every callback it is registered as, in the order
+/// they are delivered.
+///
+/// Clones share the same list of events. Use
+/// [`with_source`](Self::with_source) to tell apart the events of different
+/// registrations.
+#[derive(Debug, Clone)]
+pub struct EventRecorder {
+    clock: VirtualClock,
+    source: Rc<str>,
+    events: Rc<RefCell<Vec<RecordedEvent>>>,
+}
+
+impl EventRecorder {
+    /// Creates a recorder that timestamps events with `clock`.
+    pub fn new(clock: VirtualClock) -> Self {
+        Self {
+            clock,
+            source: Rc::from(""),
+            events: Rc::default(),
+        }
+    }
+
+    /// Creates a recorder that shares this one's events, and tags its own
+    /// events with `source`.
+    pub fn with_source(&self, source: &str) -> Self {
+        Self {
+            clock: self.clock.clone(),
+            source: Rc::from(source),
+            events: self.events.clone(),
+        }
+    }
+
+    /// The events recorded so far.
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Removes and returns the events recorded so far.
+    pub fn take(&self) -> Vec<RecordedEvent> {
+        std::mem::take(&mut *self.events.borrow_mut())
+    }
+
+    fn record(&self, event: Event) {
+        self.events.borrow_mut().push(RecordedEvent {
+            time: self.clock.now(),
+            source: self.source.clone(),
+            event,
+        });
+    }
+
+    /// Records the owned events of a callback trait as `wrap(event)`.
+    fn forward<E>(&self, wrap: fn(E) -> Event) -> Forward<impl FnMut(E)> {
+        let recorder = self.clone();
+        Forward(move |event| recorder.record(wrap(event)))
+    }
+}
+
+impl NotificationClient for EventRecorder {
+    fn on_default_device_changed(
+        &mut self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> windows::Result<()> {
+        self.forward(Event::Device)
+            .on_default_device_changed(data_flow, role, device_id)
+    }
+
+    fn on_device_added(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        self.forward(Event::Device).on_device_added(device_id)
+    }
+
+    fn on_device_removed(&mut self, device_id: &WinStr) -> windows::Result<()> {
+        self.forward(Event::Device).on_device_removed(device_id)
+    }
+
+    fn on_device_state_changed(
+        &mut self,
+        device_id: &WinStr,
+        state: DeviceState,
+    ) -> windows::Result<()> {
+        self.forward(Event::Device)
+            .on_device_state_changed(device_id, state)
+    }
+
+    fn on_property_value_changed(
+        &mut self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> windows::Result<()> {
+        self.forward(Event::Device)
+            .on_property_value_changed(device_id, property_key)
+    }
+}
+
+impl SessionNotification<MockAudioSessionControl> for EventRecorder {
+    fn on_session_created(&mut self, new_session: MockAudioSessionControl) -> windows::Result<()> {
+        self.record(Event::SessionCreated {
+            session_identifier: new_session.get_session_identifier()?,
+        });
+        Ok(())
+    }
+}
+
+impl AudioEndpointVolumeCallback for EventRecorder {
+    fn on_notify(&mut self, data: &NotificationData) -> windows::Result<()> {
+        self.forward(Event::Volume).on_notify(data)
+    }
+}
+
+impl AudioSessionEvents for EventRecorder {
+    fn on_channel_volume_changed(
+        &mut self,
+        new_volume_levels: &[f32],
+        changed_channel: usize,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session).on_channel_volume_changed(
+            new_volume_levels,
+            changed_channel,
+            event_context,
+        )
+    }
+
+    fn on_display_name_changed(
+        &mut self,
+        new_display_name: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session)
+            .on_display_name_changed(new_display_name, event_context)
+    }
+
+    fn on_grouping_param_changed(
+        &mut self,
+        new_grouping_param: Option<&Guid>,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session)
+            .on_grouping_param_changed(new_grouping_param, event_context)
+    }
+
+    fn on_icon_path_changed(
+        &mut self,
+        new_icon_path: &WinStr,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session)
+            .on_icon_path_changed(new_icon_path, event_context)
+    }
+
+    fn on_session_disconnected(
+        &mut self,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session)
+            .on_session_disconnected(disconnect_reason)
+    }
+
+    fn on_simple_volume_changed(
+        &mut self,
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<&Guid>,
+    ) -> windows::Result<()> {
+        self.forward(Event::Session)
+            .on_simple_volume_changed(new_volume, new_mute, event_context)
+    }
+
+    fn on_state_changed(&mut self, new_state: AudioSessionState) -> windows::Result<()> {
+        self.forward(Event::Session).on_state_changed(new_state)
+    }
+}
+
+impl AudioVolumeDuckNotification for EventRecorder {
+    fn on_volume_duck_notification(
+        &mut self,
+        session_id: &WinStr,
+        num_communication_sessions: u32,
+    ) -> windows::Result<()> {
+        self.forward(Event::Duck)
+            .on_volume_duck_notification(session_id, num_communication_sessions)
+    }
+
+    fn on_volume_unduck_notification(&mut self, session_id: &WinStr) -> windows::Result<()> {
+        self.forward(Event::Duck)
+            .on_volume_unduck_notification(session_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{AudioSessionManagerBackend, Backend},
+        mock::MockCallbackHandle,
+        winstr,
+    };
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn device(event: DeviceEvent) -> Event {
+        Event::Device(event)
+    }
+
+    fn added(device_id: &str) -> Event {
+        device(DeviceEvent::DeviceAdded {
+            device_id: WinString::from(device_id),
+        })
+    }
+
+    fn default_changed(data_flow: DataFlow, role: DeviceRole, device_id: &str) -> Event {
+        device(DeviceEvent::DefaultDeviceChanged {
+            data_flow,
+            role,
+            device_id: WinString::from(device_id),
+        })
+    }
+
+    fn state_changed(device_id: &str, state: DeviceState) -> Event {
+        device(DeviceEvent::DeviceStateChanged {
+            device_id: WinString::from(device_id),
+            state,
+        })
+    }
+
+    fn session_state(new_state: AudioSessionState) -> Event {
+        Event::Session(SessionEvent::StateChanged { new_state })
+    }
+
+    fn disconnected(disconnect_reason: AudioSessionDisconnectReason) -> Event {
+        Event::Session(SessionEvent::SessionDisconnected { disconnect_reason })
+    }
+
+    /// The recorded events, without their sources.
+    fn timeline(recorder: &EventRecorder) -> Vec<(Duration, Event)> {
+        recorder
+            .take()
+            .into_iter()
+            .map(|recorded| (recorded.time, recorded.event))
+            .collect()
+    }
+
+    /// Creates a simulator with one device, and a recorder registered for
+    /// its device notifications.
+    fn setup(device_id: &str) -> (Simulator, EventRecorder, MockCallbackHandle) {
+        let mut sim = Simulator::new();
+        sim.schedule(Scenario::new().add_device(device_id, DataFlow::Render));
+        sim.run().unwrap();
+        let recorder = sim.recorder();
+        let handle = sim
+            .backend()
+            .device_enumerator()
+            .unwrap()
+            .register_endpoint_notification(recorder.clone())
+            .unwrap();
+        (sim, recorder, handle)
+    }
+
+    /// Opens a session, and registers `recorder` for its events.
+    fn open_session(
+        sim: &mut Simulator,
+        recorder: &EventRecorder,
+        device_id: &str,
+        name: &str,
+    ) -> MockCallbackHandle {
+        sim.schedule(Scenario::new().open_session(device_id, name, 42));
+        sim.run().unwrap();
+        recorder.take();
+        sim.session(name)
+            .unwrap()
+            .register_audio_session_notification(recorder.with_source(name))
+            .unwrap()
+    }
+
+    #[test]
+    fn hotplug() {
+        let (mut sim, recorder, _handle) = setup("speakers");
+        sim.schedule(
+            Scenario::new()
+                .wait(secs(5))
+                .plug_in_headset("headset.render", "headset.capture")
+                .wait(secs(60))
+                .unplug("headset.render")
+                .expect_default_device(DataFlow::Render, DeviceRole::Console, "speakers")
+                .expect_default_device(DataFlow::Capture, DeviceRole::Console, "headset.capture")
+                .expect_device_state("headset.render", DeviceState::NotPresent),
+        );
+        sim.run().unwrap();
+
+        let mut expected = vec![(secs(5), added("headset.render"))];
+        for &role in DeviceRole::ALL {
+            expected.push((
+                secs(5),
+                default_changed(DataFlow::Render, role, "headset.render"),
+            ));
+        }
+        expected.push((secs(5), added("headset.capture")));
+        for &role in DeviceRole::ALL {
+            expected.push((
+                secs(5),
+                default_changed(DataFlow::Capture, role, "headset.capture"),
+            ));
+        }
+        for &role in DeviceRole::ALL {
+            expected.push((
+                secs(65),
+                default_changed(DataFlow::Render, role, "speakers"),
+            ));
+        }
+        expected.push((
+            secs(65),
+            state_changed("headset.render", DeviceState::NotPresent),
+        ));
+        assert_eq!(timeline(&recorder), expected);
+        assert_eq!(sim.now(), secs(65));
+    }
+
+    #[test]
+    fn bluetooth_profile_flip() {
+        let (mut sim, recorder, _handle) = setup("bt.a2dp");
+        let _session_handle = open_session(&mut sim, &recorder, "bt.a2dp", "music");
+        sim.schedule(
+            Scenario::new()
+                .wait(secs(10))
+                .bluetooth_profile_flip("bt.a2dp", "bt.hfp")
+                .expect_default_device(DataFlow::Render, DeviceRole::Communications, "bt.hfp")
+                .expect_device_state("bt.a2dp", DeviceState::Unplugged)
+                .expect_session_state("music", AudioSessionState::Expired),
+        );
+        sim.run().unwrap();
+
+        let mut expected = vec![(secs(10), added("bt.hfp"))];
+        expected.push((
+            secs(10),
+            disconnected(AudioSessionDisconnectReason::DeviceRemoval),
+        ));
+        for &role in DeviceRole::ALL {
+            expected.push((secs(10), default_changed(DataFlow::Render, role, "bt.hfp")));
+        }
+        expected.push((secs(10), state_changed("bt.a2dp", DeviceState::Unplugged)));
+        let events = recorder.events();
+        assert_eq!(&*events[1].source, "music");
+        assert_eq!(timeline(&recorder), expected);
+    }
+
+    #[test]
+    fn session_lifecycle() {
+        let (mut sim, recorder, _handle) = setup("speakers");
+        let manager = sim
+            .device(winstr!("speakers"))
+            .unwrap()
+            .activate_audio_session_manager2()
+            .unwrap();
+        let _created_handle = manager
+            .register_session_notification(recorder.clone())
+            .unwrap();
+        let handles = Rc::new(RefCell::new(Vec::new()));
+        let session_recorder = recorder.with_source("game");
+        let session_handles = handles.clone();
+        sim.schedule(
+            Scenario::new()
+                .wait(secs(1))
+                .open_session("speakers", "game", 7)
+                .then("listen to session game", move |sim| {
+                    let session = sim.session("game").unwrap();
+                    session_handles
+                        .borrow_mut()
+                        .push(session.register_audio_session_notification(session_recorder)?);
+                    Ok(())
+                })
+                .wait(secs(2))
+                .set_session_state("game", AudioSessionState::Inactive)
+                .wait(secs(1))
+                .set_session_state("game", AudioSessionState::Active)
+                .wait(secs(2))
+                .close_session("game")
+                .expect_session_state("game", AudioSessionState::Expired),
+        );
+        sim.run().unwrap();
+
+        assert_eq!(
+            timeline(&recorder),
+            [
+                (
+                    secs(1),
+                    Event::SessionCreated {
+                        session_identifier: WinString::from("game"),
+                    },
+                ),
+                (secs(3), session_state(AudioSessionState::Inactive)),
+                (secs(4), session_state(AudioSessionState::Active)),
+                (secs(6), session_state(AudioSessionState::Inactive)),
+                (secs(6), session_state(AudioSessionState::Expired)),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalidation_mid_session() {
+        let (mut sim, recorder, _handle) = setup("speakers");
+        let _session_handle = open_session(&mut sim, &recorder, "speakers", "call");
+        sim.schedule(
+            Scenario::new()
+                .wait(secs(2))
+                .invalidate_session_device("call")
+                .expect_session_invalidated("call"),
+        );
+        sim.run().unwrap();
+
+        let mut expected = vec![(
+            secs(2),
+            device(DeviceEvent::DeviceRemoved {
+                device_id: WinString::from("speakers"),
+            }),
+        )];
+        for &role in DeviceRole::ALL {
+            expected.push((secs(2), default_changed(DataFlow::Render, role, "")));
+        }
+        expected.push((
+            secs(2),
+            disconnected(AudioSessionDisconnectReason::DeviceRemoval),
+        ));
+        assert_eq!(timeline(&recorder), expected);
+        let session = sim.session("call").unwrap();
+        let err = session.get_simple_audio_volume().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceInvalidated);
+    }
+
+    #[test]
+    fn invalidation_requires_active_session() {
+        let (mut sim, _recorder, _handle) = setup("speakers");
+        sim.schedule(
+            Scenario::new()
+                .open_session("speakers", "call", 42)
+                .expect_session_invalidated("call"),
+        );
+        let err = sim.run().unwrap_err();
+        assert_eq!(err.step(), "expect session call to be invalidated");
+        assert!(matches!(err.kind(), ScenarioErrorKind::CheckFailed(_)));
+
+        sim.schedule(
+            Scenario::new()
+                .wait(secs(1))
+                .close_session("call")
+                .invalidate_session_device("call"),
+        );
+        let err = sim.run().unwrap_err();
+        assert_eq!(err.time(), secs(1));
+        assert!(matches!(err.kind(), ScenarioErrorKind::CheckFailed(_)));
+        assert_eq!(
+            sim.device(winstr!("speakers"))
+                .unwrap()
+                .get_state()
+                .unwrap(),
+            DeviceState::Active
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Notification registrations as asynchronous [`Stream`]s, with the `stream`
+//! feature.
+//!
+//! Each `register_*_stream` method registers a callback that queues its
+//! events, and returns an [`EventStream`] of the queued events. The stream
+//! owns the registration, so dropping it unregisters the callback.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::events::Forward;
+
+/// What an [`EventStream`] does with events that arrive faster than they
+/// are consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backpressure {
+    /// Queues every event.
+    Unbounded,
+    /// Queues at most `capacity` events, discarding the oldest event to make
+    /// room for a new one.
+    DropOldest { capacity: NonZeroUsize },
+}
+
+struct Queue<E> {
+    events: VecDeque<E>,
+    backpressure: Backpressure,
+    dropped: u64,
+    /// Whether the backend released the callback.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl<E> Queue<E> {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn lock<E>(queue: &Mutex<Queue<E>>) -> MutexGuard<'_, Queue<E>> {
+    queue.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The half of an [`EventStream`] that is registered as the callback.
+///
+/// It only holds a weak reference, so events that arrive while the stream is
+/// being dropped are discarded, and it closes the stream if the backend
+/// releases the callback first.
+struct Sender<E> {
+    queue: Weak<Mutex<Queue<E>>>,
+}
+
+impl<E> Sender<E> {
+    fn send(&self, event: E) {
+        let queue = match self.queue.upgrade() {
+            Some(queue) => queue,
+            None => return,
+        };
+        let mut queue = lock(&queue);
+        if let Backpressure::DropOldest { capacity } = queue.backpressure {
+            if queue.events.len() == capacity.get() {
+                queue.events.pop_front();
+                queue.dropped += 1;
+            }
+        }
+        queue.events.push_back(event);
+        queue.wake();
+    }
+}
+
+impl<E> Drop for Sender<E> {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.upgrade() {
+            let mut queue = lock(&queue);
+            queue.closed = true;
+            queue.wake();
+        }
+    }
+}
+
+/// Registers a callback that queues events, and returns the stream that
+/// takes the events from the queue.
+pub(crate) fn event_stream<H, E, R>(
+    backpressure: Backpressure,
+    register: R,
+) -> crate::Result<EventStream<H, E>>
+where
+    R: FnOnce(Forward<Box<dyn FnMut(E)>>) -> crate::Result<H>,
+    E: 'static,
+{
+    let queue = Arc::new(Mutex::new(Queue {
+        events: VecDeque::new(),
+        backpressure,
+        dropped: 0,
+        closed: false,
+        waker: None,
+    }));
+    let sender = Sender {
+        queue: Arc::downgrade(&queue),
+    };
+    let callback: Box<dyn FnMut(E)> = Box::new(move |event| sender.send(event));
+    let handle = register(Forward(callback))?;
+    Ok(EventStream {
+        queue,
+        _handle: handle,
+    })
+}
+
+/// A [`Stream`] of the events of a notification registration.
+///
+/// The stream owns the registration handle `H`, so dropping the stream
+/// unregisters the callback. If the backend releases the callback first, the
+/// stream ends once the events queued before that have been taken.
+///
+/// The `register_*_stream` methods of the wrapper types return it as an
+/// `impl Stream`, and those of the [`backend`](crate::backend) traits return
+/// it by name.
+///
+/// COM registration handles are not `Send`, so a stream of COM notifications
+/// must be consumed on the thread that registered it, e.g. on a
+/// `current_thread` runtime or in a tokio `LocalSet`. The streams do not
+/// depend on a particular runtime:
+///
+/// ```no_run
+/// use std::num::NonZeroUsize;
+///
+/// use futures::StreamExt;
+/// use win32_coreaudio::{Backpressure, DeviceEnumerator, DeviceEvent};
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> win32_coreaudio::Result<()> {
+///     let enumerator = DeviceEnumerator::new()?;
+///     let capacity = NonZeroUsize::new(64).unwrap();
+///     let mut events = enumerator
+///         .register_endpoint_notification_stream(Backpressure::DropOldest { capacity })?;
+///     while let Some(event) = events.next().await {
+///         if let DeviceEvent::DefaultDeviceChanged { device_id, .. } = event {
+///             println!("default device changed to {}", device_id);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct EventStream<H, E> {
+    queue: Arc<Mutex<Queue<E>>>,
+    /// Unregisters the callback when the stream is dropped.
+    _handle: H,
+}
+
+impl<H, E> EventStream<H, E> {
+    /// The number of events that were discarded because of
+    /// [`Backpressure::DropOldest`].
+    pub fn dropped(&self) -> u64 {
+        lock(&self.queue).dropped
+    }
+}
+
+impl<H, E> Stream for EventStream<H, E> {
+    type Item = E;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<E>> {
+        let mut queue = lock(&self.queue);
+        match queue.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let queue = lock(&self.queue);
+        let queued = queue.events.len();
+        (queued, if queue.closed { Some(queued) } else { None })
+    }
+}
+
+impl<H, E> Debug for EventStream<H, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let queue = lock(&self.queue);
+        f.debug_struct("EventStream")
+            .field("backpressure", &queue.backpressure)
+            .field("queued", &queue.events.len())
+            .field("dropped", &queue.dropped)
+            .field("closed", &queue.closed)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{
+        backend::{AudioEndpointVolumeBackend, DeviceBackend, DeviceEnumeratorBackend},
+        bits::{DataFlow, DeviceState},
+        events::DeviceEvent,
+        mock::MockBackend,
+        winstr,
+    };
+
+    fn state_changed(state: DeviceState) -> DeviceEvent {
+        DeviceEvent::DeviceStateChanged {
+            device_id: winstr!("speakers").to_winstring(),
+            state,
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_events_in_order() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let events = backend
+            .register_endpoint_notification_stream(Backpressure::Unbounded)
+            .unwrap();
+        let states = [
+            DeviceState::Unplugged,
+            DeviceState::Disabled,
+            DeviceState::Active,
+        ];
+
+        // Mock registrations are not `Send`, like COM ones, so the consumer
+        // runs on this thread.
+        let local = tokio::task::LocalSet::new();
+        let consumer = local.spawn_local(events.take(states.len()).collect::<Vec<_>>());
+        local
+            .run_until(async {
+                for &state in &states {
+                    speakers.set_state(state).unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+            .await;
+
+        let expected: Vec<_> = states.iter().copied().map(state_changed).collect();
+        assert_eq!(local.run_until(consumer).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_discards_and_counts() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let volume = speakers.activate_audio_endpoint_volume().unwrap();
+        let capacity = NonZeroUsize::new(2).unwrap();
+        let mut events = volume
+            .register_control_change_notify_stream(Backpressure::DropOldest { capacity })
+            .unwrap();
+
+        for &level in &[0.1, 0.2, 0.3, 0.4, 0.5] {
+            volume.set_master_volume_level_scalar(level, None).unwrap();
+        }
+
+        assert_eq!(events.dropped(), 3);
+        assert_eq!(events.size_hint(), (2, None));
+        assert_eq!(events.next().await.unwrap().master_volume, 0.4);
+        assert_eq!(events.next().await.unwrap().master_volume, 0.5);
+        volume.set_mute(true, None).unwrap();
+        assert!(events.next().await.unwrap().muted);
+        assert_eq!(events.dropped(), 3);
+    }
+
+    #[tokio::test]
+    async fn dropping_the_stream_discards_events() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let volume = speakers.activate_audio_endpoint_volume().unwrap();
+        let events = backend
+            .register_endpoint_notification_stream(Backpressure::Unbounded)
+            .unwrap();
+        let volume_events = volume
+            .register_control_change_notify_stream(Backpressure::Unbounded)
+            .unwrap();
+        assert_eq!(backend.registered_callbacks(), 2);
+
+        speakers.set_state(DeviceState::Disabled).unwrap();
+        drop(events);
+        assert_eq!(backend.registered_callbacks(), 1);
+        drop(volume_events);
+        assert_eq!(backend.registered_callbacks(), 0);
+
+        // Nothing is listening anymore.
+        speakers.set_state(DeviceState::Active).unwrap();
+        volume.set_mute(true, None).unwrap();
+    }
+
+    #[tokio::test]
+    async fn releasing_the_callback_ends_the_stream() {
+        let backend = MockBackend::new();
+        let speakers = backend.add_device(winstr!("speakers"), DataFlow::Render);
+        let mut events = backend
+            .register_endpoint_notification_stream(Backpressure::Unbounded)
+            .unwrap();
+
+        speakers.set_state(DeviceState::Disabled).unwrap();
+        drop(speakers);
+        drop(backend);
+
+        assert_eq!(events.size_hint(), (1, Some(1)));
+        assert_eq!(
+            events.next().await,
+            Some(state_changed(DeviceState::Disabled))
+        );
+        assert_eq!(events.next().await, None);
+    }
+}
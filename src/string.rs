@@ -2,18 +2,22 @@
 
 use std::borrow::Borrow;
 use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
+use std::cmp::Ordering;
 use std::convert::TryInto;
-use std::fmt::{self, Debug};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Debug, Display};
 use std::fmt::{Formatter, Write};
 use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
 use std::ops::Deref;
+use std::str::FromStr;
 
 use crate::bindings::Windows::Win32::{
-    Foundation::PWSTR,
-    Globalization::lstrlenW,
-    System::{Com::CoTaskMemFree, Memory::LocalFree},
-    UI::Shell::StrDupW,
+    Foundation::PWSTR, Globalization::lstrlenW, System::Com::CoTaskMemFree,
 };
+#[cfg(windows)]
+use crate::bindings::Windows::Win32::{System::Memory::LocalFree, UI::Shell::StrDupW};
 
 /// A borrowed string value that is valid only for the defined lifetime.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -34,6 +38,21 @@ impl WinStr {
         unsafe { &*(slice as *const [u16] as *const Self) }
     }
 
+    /// Wrap the given null-terminated buffer.
+    ///
+    /// This is used by the [`winstr!`] macro; prefer that for building
+    /// string literals.
+    ///
+    /// # Safety
+    ///
+    /// - `buf` must end with a null code unit, and must not contain any other
+    ///   null code units.
+    #[doc(hidden)]
+    pub const unsafe fn from_slice_with_nul_unchecked(buf: &[u16]) -> &Self {
+        let slice = unsafe { std::slice::from_raw_parts(buf.as_ptr(), buf.len() - 1) };
+        unsafe { &*(slice as *const [u16] as *const Self) }
+    }
+
     /// Gets a PWSTR pointer to the underlying string.
     ///
     /// # Safety
@@ -50,6 +69,10 @@ impl WinStr {
         self.slice.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     pub fn as_slice(&self) -> &[u16] {
         &self.slice
     }
@@ -64,6 +87,7 @@ impl WinStr {
         self.slice.as_ptr()
     }
 
+    #[cfg(windows)]
     pub fn to_winstring(&self) -> WinString {
         let pwstr = unsafe { StrDupW(self.as_pwstr()) };
         if pwstr.is_null() {
@@ -72,9 +96,221 @@ impl WinStr {
         unsafe { WinString::from_local_pwstr(pwstr) }
     }
 
+    /// Copies this string into a buffer owned by Rust.
+    ///
+    /// `StrDupW` is only available on Windows. Other targets build this crate
+    /// for the [`mock`](crate::mock) backend, which copies strings to deliver
+    /// them to callbacks.
+    #[cfg(not(windows))]
+    pub fn to_winstring(&self) -> WinString {
+        WinString::from_vec(self.slice.to_vec()).expect("string contains no interior nulls")
+    }
+
+    /// Decodes this string as UTF-16.
+    ///
+    /// Returns an error pointing to the first unpaired surrogate if the
+    /// string is not valid UTF-16.
+    pub fn to_string(&self) -> Result<String, Utf16Error> {
+        self.chars().collect()
+    }
+
     pub fn to_string_lossy(&self) -> String {
         String::from_utf16_lossy(self.as_slice())
     }
+
+    /// Converts this string into an `OsString` without losing any
+    /// information, even if it is not valid UTF-16.
+    ///
+    /// On non-Windows Unix platforms, unpaired surrogates are preserved by
+    /// encoding the string as [WTF-8](https://simonsapin.github.io/wtf-8/),
+    /// which converting back with `WinString::from` will reverse.
+    #[cfg(windows)]
+    pub fn to_os_string(&self) -> OsString {
+        use std::os::windows::ffi::OsStringExt;
+
+        OsString::from_wide(self.as_slice())
+    }
+
+    /// Converts this string into an `OsString` without losing any
+    /// information, even if it is not valid UTF-16.
+    ///
+    /// On non-Windows Unix platforms, unpaired surrogates are preserved by
+    /// encoding the string as [WTF-8](https://simonsapin.github.io/wtf-8/),
+    /// which converting back with `WinString::from` will reverse.
+    #[cfg(unix)]
+    pub fn to_os_string(&self) -> OsString {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut bytes = Vec::with_capacity(self.len());
+        for (_, c) in self.char_indices() {
+            match c {
+                Ok(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Err(e) => {
+                    let unit = e.unpaired_surrogate();
+                    bytes.extend_from_slice(&[
+                        0xE0 | (unit >> 12) as u8,
+                        0x80 | ((unit >> 6) & 0x3F) as u8,
+                        0x80 | (unit & 0x3F) as u8,
+                    ]);
+                }
+            }
+        }
+        OsString::from_vec(bytes)
+    }
+
+    /// Converts this string into an `OsString`, replacing unpaired surrogates
+    /// with [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+    #[cfg(not(any(windows, unix)))]
+    pub fn to_os_string(&self) -> OsString {
+        self.to_string_lossy().into()
+    }
+
+    /// Returns an iterator over the characters of this string.
+    ///
+    /// Each unpaired surrogate is yielded as an error.
+    pub fn chars(&self) -> Chars<'_> {
+        Chars {
+            inner: self.char_indices(),
+        }
+    }
+
+    /// Returns an iterator over the characters of this string, and the index
+    /// of the code unit that each one starts at.
+    ///
+    /// Each unpaired surrogate is yielded as an error.
+    pub fn char_indices(&self) -> CharIndices<'_> {
+        CharIndices {
+            slice: self.as_slice(),
+            index: 0,
+        }
+    }
+
+    /// Compares two strings for equality, ignoring case.
+    ///
+    /// Case folding follows the same ordinal rules as
+    /// [`CompareStringOrdinal`](https://docs.microsoft.com/en-us/windows/win32/api/stringapiset/nf-stringapiset-comparestringordinal)
+    /// with `bIgnoreCase` set: each UTF-16 code unit is compared after mapping
+    /// it to its simple uppercase form. Characters outside the Basic
+    /// Multilingual Plane, and those without a single-code-unit uppercase
+    /// form, are compared as-is.
+    pub fn eq_ignore_case(&self, other: &WinStr) -> bool {
+        self.len() == other.len() && units_eq_ignore_case(self.as_slice(), other.as_slice())
+    }
+
+    /// Compares two strings in ordinal order, ignoring case.
+    ///
+    /// See [`eq_ignore_case`](Self::eq_ignore_case) for the case folding
+    /// rules.
+    pub fn cmp_ignore_case(&self, other: &WinStr) -> Ordering {
+        let a = self.slice.iter().map(|&c| upcase(c));
+        let b = other.slice.iter().map(|&c| upcase(c));
+        a.cmp(b)
+    }
+
+    /// Returns `true` if this string starts with `prefix`.
+    pub fn starts_with(&self, prefix: &WinStr) -> bool {
+        self.slice.starts_with(&prefix.slice)
+    }
+
+    /// Returns `true` if this string starts with `prefix`, ignoring case.
+    pub fn starts_with_ignore_case(&self, prefix: &WinStr) -> bool {
+        self.len() >= prefix.len()
+            && units_eq_ignore_case(&self.slice[..prefix.len()], &prefix.slice)
+    }
+
+    /// Returns `true` if this string ends with `suffix`.
+    pub fn ends_with(&self, suffix: &WinStr) -> bool {
+        self.slice.ends_with(&suffix.slice)
+    }
+
+    /// Returns `true` if this string ends with `suffix`, ignoring case.
+    pub fn ends_with_ignore_case(&self, suffix: &WinStr) -> bool {
+        self.len() >= suffix.len()
+            && units_eq_ignore_case(&self.slice[self.len() - suffix.len()..], &suffix.slice)
+    }
+
+    /// Returns the code unit index of the first occurrence of `needle`.
+    pub fn find(&self, needle: &WinStr) -> Option<usize> {
+        find_units(&self.slice, &needle.slice, |a, b| a == b)
+    }
+
+    /// Returns the code unit index of the first occurrence of `needle`,
+    /// ignoring case.
+    pub fn find_ignore_case(&self, needle: &WinStr) -> Option<usize> {
+        find_units(&self.slice, &needle.slice, units_eq_ignore_case)
+    }
+
+    /// Returns `true` if `needle` occurs in this string.
+    pub fn contains(&self, needle: &WinStr) -> bool {
+        self.find(needle).is_some()
+    }
+
+    /// Returns `true` if `needle` occurs in this string, ignoring case.
+    pub fn contains_ignore_case(&self, needle: &WinStr) -> bool {
+        self.find_ignore_case(needle).is_some()
+    }
+
+    /// Returns an iterator over the substrings separated by `separator`.
+    ///
+    /// The substrings are yielded as raw code unit slices, because they are
+    /// not null-terminated and cannot be passed to the Windows API as-is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `separator` is empty.
+    pub fn split<'a>(&'a self, separator: &'a WinStr) -> Split<'a> {
+        assert!(!separator.is_empty(), "separator must not be empty");
+        Split {
+            rest: Some(&self.slice),
+            separator: &separator.slice,
+        }
+    }
+}
+
+/// Maps a code unit to its simple uppercase form, if it has one that is
+/// also a single code unit.
+fn upcase(unit: u16) -> u16 {
+    if unit < 0x80 {
+        return (unit as u8).to_ascii_uppercase() as u16;
+    }
+    let c = match char::from_u32(unit as u32) {
+        Some(c) => c,
+        // Surrogate
+        None => return unit,
+    };
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if (u as u32) < 0x10000 => u as u16,
+        _ => unit,
+    }
+}
+
+fn units_eq_ignore_case(a: &[u16], b: &[u16]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| upcase(a) == upcase(b))
+}
+
+fn find_units(
+    haystack: &[u16],
+    needle: &[u16],
+    eq: impl Fn(&[u16], &[u16]) -> bool,
+) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| eq(window, needle))
+}
+
+/// Displays the string, replacing unpaired surrogates with
+/// [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+impl Display for WinStr {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for c in self.chars() {
+            f.write_char(c.unwrap_or(REPLACEMENT_CHARACTER))?;
+        }
+        Ok(())
+    }
 }
 
 impl Debug for WinStr {
@@ -111,6 +347,104 @@ impl PartialOrd<WinString> for WinStr {
     }
 }
 
+/// Creates a `&'static WinStr` from a string literal.
+///
+/// The string is encoded as UTF-16 at compile time, and a null terminator is
+/// appended. Strings containing interior null characters are rejected with a
+/// compile error.
+///
+/// ```
+/// use win32_coreaudio::{string::WinStr, winstr};
+///
+/// const DISPLAY_NAME: &WinStr = winstr!("System Sounds");
+/// assert_eq!(DISPLAY_NAME.to_string_lossy(), "System Sounds");
+/// ```
+#[macro_export]
+macro_rules! winstr {
+    ($s:expr) => {{
+        const INPUT: &str = $s;
+        const LEN: usize = $crate::string::__private::utf16_len(INPUT) + 1;
+        const BUF: [u16; LEN] = $crate::string::__private::encode_utf16(INPUT);
+        const WINSTR: &$crate::string::WinStr =
+            unsafe { $crate::string::WinStr::from_slice_with_nul_unchecked(&BUF) };
+        WINSTR
+    }};
+}
+
+pub use crate::winstr;
+
+/// Compile-time helpers for the [`winstr!`] macro.
+#[doc(hidden)]
+pub mod __private {
+    /// Decodes the code point starting at byte `i`, returning it along with the
+    /// index of the next code point.
+    const fn decode_utf8(bytes: &[u8], i: usize) -> (u32, usize) {
+        let b0 = bytes[i] as u32;
+        if b0 < 0x80 {
+            (b0, i + 1)
+        } else if b0 < 0xE0 {
+            (((b0 & 0x1F) << 6) | (bytes[i + 1] as u32 & 0x3F), i + 2)
+        } else if b0 < 0xF0 {
+            (
+                ((b0 & 0x0F) << 12)
+                    | ((bytes[i + 1] as u32 & 0x3F) << 6)
+                    | (bytes[i + 2] as u32 & 0x3F),
+                i + 3,
+            )
+        } else {
+            (
+                ((b0 & 0x07) << 18)
+                    | ((bytes[i + 1] as u32 & 0x3F) << 12)
+                    | ((bytes[i + 2] as u32 & 0x3F) << 6)
+                    | (bytes[i + 3] as u32 & 0x3F),
+                i + 4,
+            )
+        }
+    }
+
+    /// Number of UTF-16 code units needed to encode `s`, not including the
+    /// null terminator.
+    pub const fn utf16_len(s: &str) -> usize {
+        let bytes = s.as_bytes();
+        let mut i = 0;
+        let mut len = 0;
+        while i < bytes.len() {
+            let (c, next) = decode_utf8(bytes, i);
+            len += if c >= 0x10000 { 2 } else { 1 };
+            i = next;
+        }
+        len
+    }
+
+    /// Encodes `s` as null-terminated UTF-16.
+    ///
+    /// `N` must be `utf16_len(s) + 1`.
+    pub const fn encode_utf16<const N: usize>(s: &str) -> [u16; N] {
+        let bytes = s.as_bytes();
+        let mut buf = [0; N];
+        let mut i = 0;
+        let mut j = 0;
+        while i < bytes.len() {
+            let (c, next) = decode_utf8(bytes, i);
+            if c == 0 {
+                panic!("winstr! literal contains an interior null character");
+            }
+            if c >= 0x10000 {
+                let c = c - 0x10000;
+                buf[j] = 0xD800 | (c >> 10) as u16;
+                buf[j + 1] = 0xDC00 | (c & 0x3FF) as u16;
+                j += 2;
+            } else {
+                buf[j] = c as u16;
+                j += 1;
+            }
+            i = next;
+        }
+        assert!(j + 1 == N, "buffer length does not match encoded length");
+        buf
+    }
+}
+
 /// An owned string value that will be automatically freed when dropped.
 pub struct WinString {
     winstr: *const WinStr,
@@ -147,6 +481,7 @@ impl WinString {
     /// API calls, and should be mentioned in the function documentation. For
     /// example, see [`StrDupW`](https://docs.microsoft.com/en-us/windows/win32/api/shlwapi/nf-shlwapi-strdupw).
     /// If this is not the case, create a borrowed [`WinStr`] instead.
+    #[cfg(windows)]
     pub(crate) unsafe fn from_local_pwstr(pwstr: PWSTR) -> Self {
         Self {
             winstr: unsafe { WinStr::from_pwstr(&pwstr) },
@@ -154,10 +489,34 @@ impl WinString {
         }
     }
 
+    /// Create a string from a buffer of UTF-16 code units, allocated and owned
+    /// by Rust.
+    ///
+    /// The buffer should not include a null terminator; one will be appended.
+    /// Returns an error if the buffer contains an interior null code unit.
+    pub fn from_vec(mut vec: Vec<u16>) -> Result<Self, NulError> {
+        if let Some(index) = vec.iter().position(|&c| c == 0) {
+            return Err(NulError { index, vec });
+        }
+        vec.push(0);
+        let winstr = &vec[..vec.len() - 1] as *const [u16] as *const WinStr;
+        Ok(Self {
+            winstr,
+            alloc: StringAlloc::Rust(vec),
+        })
+    }
+
     /// Borrow this string as a `WinStr`.
     pub fn as_winstr(&self) -> &WinStr {
         unsafe { &*self.winstr }
     }
+
+    /// Decodes this string as UTF-16.
+    ///
+    /// This shadows [`ToString::to_string`]; see [`WinStr::to_string`].
+    pub fn to_string(&self) -> Result<String, Utf16Error> {
+        self.as_winstr().to_string()
+    }
 }
 
 impl Deref for WinString {
@@ -168,6 +527,12 @@ impl Deref for WinString {
     }
 }
 
+impl AsRef<WinStr> for WinStr {
+    fn as_ref(&self) -> &WinStr {
+        self
+    }
+}
+
 impl AsRef<WinStr> for WinString {
     fn as_ref(&self) -> &WinStr {
         self.as_winstr()
@@ -186,9 +551,126 @@ impl Debug for WinString {
     }
 }
 
+impl Display for WinString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_winstr())
+    }
+}
+
 impl Clone for WinString {
     fn clone(&self) -> Self {
-        self.as_winstr().to_winstring()
+        match &self.alloc {
+            StringAlloc::Rust(vec) => {
+                let vec = vec[..vec.len() - 1].to_vec();
+                Self::from_vec(vec).expect("string contains no interior nulls")
+            }
+            _ => self.as_winstr().to_winstring(),
+        }
+    }
+}
+
+/// Converts a Rust string into a UTF-16 `WinString`.
+///
+/// # Panics
+///
+/// Panics if the string contains an interior null character. Use
+/// [`str::parse`] to handle this case as an error.
+impl From<&str> for WinString {
+    fn from(s: &str) -> Self {
+        s.encode_utf16().collect()
+    }
+}
+
+/// Converts a Rust string into a UTF-16 `WinString`.
+///
+/// # Panics
+///
+/// Panics if the string contains an interior null character. Use
+/// [`str::parse`] to handle this case as an error.
+impl From<String> for WinString {
+    fn from(s: String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+/// Converts an OS string into a `WinString`.
+///
+/// This is lossless for any string produced by [`WinStr::to_os_string`]. On
+/// Unix platforms, bytes that are not valid [WTF-8](https://simonsapin.github.io/wtf-8/)
+/// are replaced with [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+///
+/// # Panics
+///
+/// Panics if the string contains an interior null character.
+impl From<&OsStr> for WinString {
+    #[cfg(windows)]
+    fn from(s: &OsStr) -> Self {
+        use std::os::windows::ffi::OsStrExt;
+
+        s.encode_wide().collect()
+    }
+
+    #[cfg(unix)]
+    fn from(s: &OsStr) -> Self {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut wide = Vec::with_capacity(s.len());
+        let mut rest = s.as_bytes();
+        while !rest.is_empty() {
+            let (valid, invalid) = match std::str::from_utf8(rest) {
+                Ok(valid) => (valid, &[][..]),
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    (std::str::from_utf8(valid).unwrap(), invalid)
+                }
+            };
+            wide.extend(valid.encode_utf16());
+            rest = match *invalid {
+                [] => invalid,
+                // Surrogate code point, encoded by WTF-8.
+                [0xED, b1 @ 0xA0..=0xBF, b2 @ 0x80..=0xBF, ..] => {
+                    wide.push(0xD000 | ((b1 as u16 & 0x3F) << 6) | (b2 as u16 & 0x3F));
+                    &invalid[3..]
+                }
+                _ => {
+                    wide.push(REPLACEMENT_CHARACTER as u16);
+                    let error_len = std::str::from_utf8(invalid)
+                        .unwrap_err()
+                        .error_len()
+                        .unwrap_or(invalid.len());
+                    &invalid[error_len..]
+                }
+            };
+        }
+        wide.into_iter().collect()
+    }
+
+    #[cfg(not(any(windows, unix)))]
+    fn from(s: &OsStr) -> Self {
+        s.to_string_lossy().encode_utf16().collect()
+    }
+}
+
+impl FromStr for WinString {
+    type Err = NulError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_vec(s.encode_utf16().collect())
+    }
+}
+
+/// Collects UTF-16 code units into a `WinString`.
+///
+/// # Panics
+///
+/// Panics if any of the code units are null. Use [`WinString::from_vec`] to
+/// handle this case as an error.
+impl FromIterator<u16> for WinString {
+    fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
+        match Self::from_vec(iter.into_iter().collect()) {
+            Ok(s) => s,
+            Err(e) => panic!("{}", e),
+        }
     }
 }
 
@@ -236,9 +718,13 @@ impl Drop for WinString {
             StringAlloc::Com => unsafe {
                 CoTaskMemFree(self.as_pwstr().0 as _);
             },
+            #[cfg(windows)]
             StringAlloc::Local => unsafe {
                 LocalFree(self.as_pwstr().0 as _);
             },
+            StringAlloc::Rust(_) => {
+                // Freed when the vec is dropped.
+            }
         }
     }
 }
@@ -250,5 +736,341 @@ unsafe impl Sync for WinString {}
 #[derive(Debug)]
 enum StringAlloc {
     Com,
+    #[cfg(windows)]
     Local,
+    /// Null-terminated buffer owned by Rust.
+    Rust(Vec<u16>),
+}
+
+/// An iterator over the substrings of a [`WinStr`], separated by another
+/// string.
+///
+/// Created by [`WinStr::split`].
+#[derive(Debug, Clone)]
+pub struct Split<'a> {
+    rest: Option<&'a [u16]>,
+    separator: &'a [u16],
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a [u16];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+        match find_units(rest, self.separator, |a, b| a == b) {
+            Some(index) => {
+                self.rest = Some(&rest[index + self.separator.len()..]);
+                Some(&rest[..index])
+            }
+            None => {
+                self.rest = None;
+                Some(rest)
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for Split<'a> {}
+
+/// A wrapper that compares, orders and hashes strings ignoring case.
+///
+/// This is useful as a key in maps and sets of identifiers that Windows
+/// treats as case-insensitive, like endpoint IDs. See
+/// [`WinStr::eq_ignore_case`] for the case folding rules.
+///
+/// A `&CaseInsensitive<WinStr>` can be borrowed from a
+/// `CaseInsensitive<WinString>`, so maps can be queried without allocating:
+///
+/// ```
+/// use std::collections::HashSet;
+/// use win32_coreaudio::{string::{CaseInsensitive, WinString}, winstr};
+///
+/// let mut set = HashSet::new();
+/// set.insert(CaseInsensitive(WinString::from("{0.0.0.00000000}.{ABCDEF}")));
+/// assert!(set.contains(CaseInsensitive::new(winstr!("{0.0.0.00000000}.{abcdef}"))));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct CaseInsensitive<T: ?Sized>(pub T);
+
+impl CaseInsensitive<WinStr> {
+    /// Wraps a borrowed string.
+    pub fn new(s: &WinStr) -> &Self {
+        // Safety: `CaseInsensitive` is a transparent wrapper.
+        unsafe { &*(s as *const WinStr as *const Self) }
+    }
+}
+
+impl<T> CaseInsensitive<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: ?Sized> Deref for CaseInsensitive<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<CaseInsensitive<WinStr>> for CaseInsensitive<WinString> {
+    fn borrow(&self) -> &CaseInsensitive<WinStr> {
+        CaseInsensitive::new(self.0.as_winstr())
+    }
+}
+
+impl<T: ?Sized + AsRef<WinStr>> PartialEq for CaseInsensitive<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ref().eq_ignore_case(other.0.as_ref())
+    }
+}
+
+impl<T: ?Sized + AsRef<WinStr>> Eq for CaseInsensitive<T> {}
+
+impl<T: ?Sized + AsRef<WinStr>> PartialOrd for CaseInsensitive<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: ?Sized + AsRef<WinStr>> Ord for CaseInsensitive<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.as_ref().cmp_ignore_case(other.0.as_ref())
+    }
+}
+
+impl<T: ?Sized + AsRef<WinStr>> Hash for CaseInsensitive<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let s = self.0.as_ref();
+        state.write_usize(s.len());
+        for &unit in s.as_slice() {
+            state.write_u16(upcase(unit));
+        }
+    }
+}
+
+/// An iterator over the characters of a [`WinStr`].
+///
+/// Created by [`WinStr::chars`].
+#[derive(Debug, Clone)]
+pub struct Chars<'a> {
+    inner: CharIndices<'a>,
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = Result<char, Utf16Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, c)| c)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> FusedIterator for Chars<'a> {}
+
+/// An iterator over the characters of a [`WinStr`] and their code unit
+/// indices.
+///
+/// Created by [`WinStr::char_indices`].
+#[derive(Debug, Clone)]
+pub struct CharIndices<'a> {
+    slice: &'a [u16],
+    index: usize,
+}
+
+impl<'a> Iterator for CharIndices<'a> {
+    type Item = (usize, Result<char, Utf16Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.index;
+        let unit = *self.slice.get(index)?;
+        let unpaired = Utf16Error { index, unit };
+        let c = match unit {
+            0xD800..=0xDBFF => match self.slice.get(index + 1) {
+                Some(&low @ 0xDC00..=0xDFFF) => {
+                    self.index += 1;
+                    let c = 0x10000 + (((unit as u32) & 0x3FF) << 10) + ((low as u32) & 0x3FF);
+                    Ok(char::from_u32(c).unwrap())
+                }
+                _ => Err(unpaired),
+            },
+            0xDC00..=0xDFFF => Err(unpaired),
+            _ => Ok(char::from_u32(unit as u32).unwrap()),
+        };
+        self.index += 1;
+        Some((index, c))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.slice.len() - self.index;
+        ((remaining + 1) / 2, Some(remaining))
+    }
+}
+
+impl<'a> FusedIterator for CharIndices<'a> {}
+
+/// An error indicating that a [`WinStr`] is not valid UTF-16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utf16Error {
+    index: usize,
+    unit: u16,
+}
+
+impl Utf16Error {
+    /// The index of the unpaired surrogate, in UTF-16 code units.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The value of the unpaired surrogate.
+    pub fn unpaired_surrogate(&self) -> u16 {
+        self.unit
+    }
+}
+
+impl Display for Utf16Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unpaired surrogate {:#06x} found in string at position {}",
+            self.unit, self.index
+        )
+    }
+}
+
+impl Error for Utf16Error {}
+
+/// An error indicating that a string contained an interior null code unit,
+/// which cannot be represented in a null-terminated `WinString`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NulError {
+    index: usize,
+    vec: Vec<u16>,
+}
+
+impl NulError {
+    /// The position of the first null code unit in the original buffer.
+    pub fn nul_position(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the buffer that failed to convert.
+    pub fn into_vec(self) -> Vec<u16> {
+        self.vec
+    }
+}
+
+impl Display for NulError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "null code unit found in string at position {}",
+            self.index
+        )
+    }
+}
+
+impl Error for NulError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_vec_rejects_interior_nul() {
+        let err = WinString::from_vec(vec![b'a' as u16, 0, b'b' as u16]).unwrap_err();
+        assert_eq!(err.nul_position(), 1);
+        assert_eq!(err.into_vec(), [b'a' as u16, 0, b'b' as u16]);
+
+        assert!("a\0b".parse::<WinString>().is_err());
+        assert!(std::panic::catch_unwind(|| WinString::from("a\0b")).is_err());
+    }
+
+    #[test]
+    fn from_vec_appends_terminator() {
+        let s = WinString::from_vec(vec![b'h' as u16, b'i' as u16]).unwrap();
+        assert_eq!(s.len(), 2);
+        assert_eq!(unsafe { *s.as_ptr().add(2) }, 0);
+    }
+
+    #[test]
+    fn string_round_trip() {
+        for text in ["", "System Sounds", "héllo", "a😀b"] {
+            let s = WinString::from(text);
+            assert_eq!(s.as_slice(), text.encode_utf16().collect::<Vec<_>>());
+            assert_eq!(s.to_string().unwrap(), text);
+            assert_eq!(s.to_string_lossy(), text);
+            assert_eq!(WinString::from(text.to_owned()), s);
+            assert_eq!(text.parse::<WinString>().unwrap(), s);
+        }
+    }
+
+    #[test]
+    fn unpaired_surrogate() {
+        let s = WinString::from_vec(vec![b'a' as u16, 0xD800, b'b' as u16]).unwrap();
+        let err = s.to_string().unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(err.unpaired_surrogate(), 0xD800);
+        assert_eq!(s.to_string_lossy(), "a\u{FFFD}b");
+        assert_eq!(WinString::from(s.to_os_string().as_os_str()), s);
+    }
+
+    #[test]
+    fn to_winstring_copies() {
+        let s = WinString::from("speakers");
+        let copy = s.to_winstring();
+        assert_eq!(copy, s);
+        assert_ne!(copy.as_ptr(), s.as_ptr());
+        assert_eq!(s.clone(), s);
+    }
+
+    #[test]
+    fn winstr_is_const() {
+        const EMPTY: &WinStr = winstr!("");
+        const NAME: &WinStr = winstr!("Speakers");
+        const WIDE: &WinStr = winstr!("a😀é");
+        const LEN: usize = __private::utf16_len("a😀é");
+        const BUF: [u16; LEN + 1] = __private::encode_utf16("a😀é");
+
+        assert!(EMPTY.is_empty());
+        assert_eq!(NAME.to_string().unwrap(), "Speakers");
+        assert_eq!(LEN, 4);
+        assert_eq!(BUF, [0x61, 0xD83D, 0xDE00, 0xE9, 0]);
+        assert_eq!(WIDE.as_slice(), &BUF[..LEN]);
+        assert_eq!(unsafe { *WIDE.as_ptr().add(LEN) }, 0);
+    }
+
+    #[test]
+    fn compare_ignore_case() {
+        let id = winstr!("{0.0.0.00000000}.{ABCDEF}");
+        assert!(id.eq_ignore_case(winstr!("{0.0.0.00000000}.{abcdef}")));
+        assert!(!id.eq_ignore_case(winstr!("{0.0.0.00000000}.{abcde}")));
+        assert!(winstr!("Ärger").eq_ignore_case(winstr!("äRGER")));
+        // No single-code-unit uppercase form, so compared as-is.
+        assert!(!winstr!("ß").eq_ignore_case(winstr!("SS")));
+        assert_eq!(winstr!("a").cmp_ignore_case(winstr!("B")), Ordering::Less);
+        assert_eq!(
+            winstr!("ab").cmp_ignore_case(winstr!("AB")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn search_ignore_case() {
+        let s = winstr!("Headset Microphone");
+        assert!(s.starts_with_ignore_case(winstr!("HEADSET")));
+        assert!(!s.starts_with(winstr!("HEADSET")));
+        assert!(s.ends_with_ignore_case(winstr!("microphone")));
+        assert_eq!(s.find_ignore_case(winstr!("MICRO")), Some(8));
+        assert_eq!(s.find(winstr!("MICRO")), None);
+        assert_eq!(s.find(winstr!("")), Some(0));
+        assert!(s.contains_ignore_case(winstr!("set mic")));
+        let parts: Vec<_> = s.split(winstr!(" ")).collect();
+        assert_eq!(parts.len(), 2);
+    }
 }
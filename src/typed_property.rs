@@ -0,0 +1,290 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use windows::Guid;
+
+use crate::{
+    property_store::{Property, PropertyKey},
+    string::WinString,
+};
+
+/// A [`PropertyKey`] whose value is known to have the type `T`.
+///
+/// Use with [`PropertyStore::get`](crate::PropertyStore::get) and
+/// [`PropertyStore::set`](crate::PropertyStore::set) to convert values
+/// automatically. It dereferences to the untyped key, so it can also be used
+/// anywhere a `PropertyKey` is expected.
+pub struct TypedPropertyKey<T> {
+    key: PropertyKey,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedPropertyKey<T> {
+    pub const fn new(key: PropertyKey) -> Self {
+        Self {
+            key,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The untyped key.
+    pub const fn key(&self) -> PropertyKey {
+        self.key
+    }
+
+    /// Converts a value read for this key.
+    ///
+    /// Returns `None` if the property is empty, or an error if it has a
+    /// different type than expected.
+    pub(crate) fn decode(&self, property: Property) -> Result<Option<T>, PropertyError>
+    where
+        T: FromProperty,
+    {
+        match property {
+            Property::Empty => Ok(None),
+            property => {
+                T::from_property(property)
+                    .map(Some)
+                    .map_err(|found| PropertyError::TypeMismatch {
+                        key: self.key,
+                        expected: T::EXPECTED,
+                        found,
+                    })
+            }
+        }
+    }
+}
+
+impl<T> Clone for TypedPropertyKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedPropertyKey<T> {}
+
+impl<T> Debug for TypedPropertyKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedPropertyKey")
+            .field(&self.key)
+            .field(&std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+impl<T> Deref for TypedPropertyKey<T> {
+    type Target = PropertyKey;
+
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+impl<T> From<TypedPropertyKey<T>> for PropertyKey {
+    fn from(typed: TypedPropertyKey<T>) -> Self {
+        typed.key
+    }
+}
+
+/// Conversion from a [`Property`] value.
+pub trait FromProperty: Sized {
+    /// A description of the expected property type, used in error messages.
+    const EXPECTED: &'static str;
+
+    /// Converts the property, or returns it unchanged if it has the wrong
+    /// type.
+    fn from_property(property: Property) -> Result<Self, Property>;
+}
+
+/// Conversion into a [`Property`] value.
+pub trait IntoProperty {
+    fn into_property(self) -> Property;
+}
+
+macro_rules! property_conversions {
+    ($($ty:ty => $variant:ident,)*) => {$(
+        impl FromProperty for $ty {
+            const EXPECTED: &'static str = stringify!($variant);
+
+            fn from_property(property: Property) -> Result<Self, Property> {
+                match property {
+                    Property::$variant(x) => Ok(x),
+                    other => Err(other),
+                }
+            }
+        }
+
+        impl IntoProperty for $ty {
+            fn into_property(self) -> Property {
+                Property::$variant(self)
+            }
+        }
+    )*};
+}
+
+property_conversions! {
+    i8 => I8,
+    u8 => U8,
+    i16 => I16,
+    u16 => U16,
+    i32 => I32,
+    u32 => U32,
+    i64 => I64,
+    u64 => U64,
+    f32 => F32,
+    f64 => F64,
+    bool => Bool,
+    WinString => Str,
+    Guid => Guid,
+    Vec<WinString> => StrVec,
+}
+
+/// Reads either a `VT_BLOB` or a `VT_VECTOR | VT_UI1`.
+impl FromProperty for Vec<u8> {
+    const EXPECTED: &'static str = "Blob";
+
+    fn from_property(property: Property) -> Result<Self, Property> {
+        match property {
+            Property::Blob(x) | Property::Bytes(x) => Ok(x),
+            other => Err(other),
+        }
+    }
+}
+
+/// Writes a `VT_BLOB`.
+impl IntoProperty for Vec<u8> {
+    fn into_property(self) -> Property {
+        Property::Blob(self)
+    }
+}
+
+impl FromProperty for Property {
+    const EXPECTED: &'static str = "any property";
+
+    fn from_property(property: Property) -> Result<Self, Property> {
+        Ok(property)
+    }
+}
+
+impl IntoProperty for Property {
+    fn into_property(self) -> Property {
+        self
+    }
+}
+
+/// An error returned by [`PropertyStore::get`](crate::PropertyStore::get).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PropertyError {
+    /// The property store returned an error.
+    Windows(crate::Error),
+    /// The property has a different type than the key expects.
+    TypeMismatch {
+        key: PropertyKey,
+        expected: &'static str,
+        found: Property,
+    },
+}
+
+impl Display for PropertyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Windows(e) => Display::fmt(e, f),
+            Self::TypeMismatch {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "property {} has unexpected type: expected {}, found {:?}",
+                key, expected, found
+            ),
+        }
+    }
+}
+
+impl Error for PropertyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Windows(e) => Some(e),
+            Self::TypeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<crate::Error> for PropertyError {
+    fn from(e: crate::Error) -> Self {
+        Self::Windows(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkeys;
+
+    #[test]
+    fn empty_is_none() {
+        assert_eq!(
+            pkeys::DEVICE_FRIENDLY_NAME.decode(Property::Empty).unwrap(),
+            None
+        );
+        assert_eq!(
+            TypedPropertyKey::<Property>::new(pkeys::DEVICE_FRIENDLY_NAME.key())
+                .decode(Property::Empty)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let key = TypedPropertyKey::<u32>::new(pkeys::DEVICE_FRIENDLY_NAME.key());
+        assert_eq!(key.decode(Property::U32(7)).unwrap(), Some(7));
+        match key.decode(Property::I32(7)) {
+            Err(PropertyError::TypeMismatch {
+                key: mismatched,
+                expected,
+                found,
+            }) => {
+                assert_eq!(mismatched, *key);
+                assert_eq!(expected, "U32");
+                assert_eq!(found, Property::I32(7));
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+
+        let err = pkeys::DEVICE_FRIENDLY_NAME
+            .decode(Property::Bool(true))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PropertyError::TypeMismatch {
+                expected: "Str",
+                found: Property::Bool(true),
+                ..
+            }
+        ));
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn bytes() {
+        let key = TypedPropertyKey::<Vec<u8>>::new(pkeys::DEVICE_FRIENDLY_NAME.key());
+        assert_eq!(
+            key.decode(Property::Blob(vec![1, 2])).unwrap(),
+            Some(vec![1, 2])
+        );
+        assert_eq!(key.decode(Property::Bytes(vec![3])).unwrap(), Some(vec![3]));
+        assert!(matches!(
+            key.decode(Property::AnsiStr(vec![4])),
+            Err(PropertyError::TypeMismatch {
+                expected: "Blob",
+                ..
+            })
+        ));
+        assert_eq!(vec![5].into_property(), Property::Blob(vec![5]));
+    }
+}
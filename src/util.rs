@@ -1,3 +1,83 @@
+use windows::Guid;
+
+/// An optional closure of a callback builder.
+pub(crate) type Handler<F> = Option<Box<F>>;
+
 pub(crate) fn as_raw_or_null<T>(option: Option<&T>) -> *const T {
     option.map(|x| x as *const _).unwrap_or(std::ptr::null())
 }
+
+/// The raw fields of a `Guid`, which are not otherwise accessible.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct GuidFields {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl GuidFields {
+    pub fn of(guid: &Guid) -> Self {
+        // Safety: `Guid` is `repr(C)` with the same fields.
+        unsafe { *(guid as *const Guid as *const Self) }
+    }
+}
+
+/// Formats a GUID in registry format, e.g.
+/// `{A45C254E-DF1C-4EFD-8020-67D146A850E0}`.
+pub(crate) fn guid_to_string(guid: &Guid) -> String {
+    let GuidFields {
+        data1,
+        data2,
+        data3,
+        data4,
+    } = GuidFields::of(guid);
+    format!(
+        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        data1,
+        data2,
+        data3,
+        data4[0],
+        data4[1],
+        data4[2],
+        data4[3],
+        data4[4],
+        data4[5],
+        data4[6],
+        data4[7]
+    )
+}
+
+/// Parses a GUID in registry format. The surrounding braces are optional and
+/// hex digits may be in either case.
+pub(crate) fn parse_guid(s: &str) -> Option<Guid> {
+    let s = s
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .unwrap_or(s);
+    let bytes = s.as_bytes();
+    if !s.is_ascii() || bytes.len() != 36 || [8, 13, 18, 23].iter().any(|&i| bytes[i] != b'-') {
+        return None;
+    }
+    let hex = |range: std::ops::Range<usize>| -> Option<u64> {
+        let digits = &s[range];
+        if digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            u64::from_str_radix(digits, 16).ok()
+        } else {
+            None
+        }
+    };
+    let mut data4 = [0; 8];
+    data4[0] = hex(19..21)? as u8;
+    data4[1] = hex(21..23)? as u8;
+    for (i, byte) in data4[2..].iter_mut().enumerate() {
+        *byte = hex(24 + 2 * i..26 + 2 * i)? as u8;
+    }
+    Some(Guid::from_values(
+        hex(0..8)? as u32,
+        hex(9..13)? as u16,
+        hex(14..18)? as u16,
+        data4,
+    ))
+}
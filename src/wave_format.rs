@@ -0,0 +1,874 @@
+//! The `WAVEFORMATEX` and `WAVEFORMATEXTENSIBLE` structures, as stored in the
+//! `PKEY_AudioEngine_*Format` properties.
+
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use windows::Guid;
+
+use crate::{
+    error::ErrorKind,
+    property_store::Property,
+    typed_property::{FromProperty, IntoProperty, PropertyError},
+    util::GuidFields,
+};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// `sizeof(WAVEFORMATEX)`, including `cbSize`.
+const WAVEFORMATEX_SIZE: usize = 18;
+/// The number of bytes following `cbSize` in a `WAVEFORMATEXTENSIBLE`.
+const EXTENSIBLE_EXTRA_SIZE: u16 = 22;
+
+bitflags::bitflags! {
+    /// The `dwChannelMask` of a `WAVEFORMATEXTENSIBLE`, specifying which
+    /// speaker each channel is mapped to, in order of the set bits.
+    ///
+    /// See also: [`WAVEFORMATEXTENSIBLE`](https://docs.microsoft.com/en-us/windows/win32/api/mmreg/ns-mmreg-waveformatextensible)
+    pub struct SpeakerPositions: u32 {
+        const FRONT_LEFT = 0x1;
+        const FRONT_RIGHT = 0x2;
+        const FRONT_CENTER = 0x4;
+        const LOW_FREQUENCY = 0x8;
+        const BACK_LEFT = 0x10;
+        const BACK_RIGHT = 0x20;
+        const FRONT_LEFT_OF_CENTER = 0x40;
+        const FRONT_RIGHT_OF_CENTER = 0x80;
+        const BACK_CENTER = 0x100;
+        const SIDE_LEFT = 0x200;
+        const SIDE_RIGHT = 0x400;
+        const TOP_CENTER = 0x800;
+        const TOP_FRONT_LEFT = 0x1000;
+        const TOP_FRONT_CENTER = 0x2000;
+        const TOP_FRONT_RIGHT = 0x4000;
+        const TOP_BACK_LEFT = 0x8000;
+        const TOP_BACK_CENTER = 0x10000;
+        const TOP_BACK_RIGHT = 0x20000;
+        /// `SPEAKER_ALL`, any possible speaker configuration.
+        const ALL = 0x8000_0000;
+
+        /// `KSAUDIO_SPEAKER_MONO`
+        const MONO = Self::FRONT_CENTER.bits;
+        /// `KSAUDIO_SPEAKER_STEREO`
+        const STEREO = Self::FRONT_LEFT.bits | Self::FRONT_RIGHT.bits;
+        /// `KSAUDIO_SPEAKER_QUAD`
+        const QUAD = Self::STEREO.bits | Self::BACK_LEFT.bits | Self::BACK_RIGHT.bits;
+        /// `KSAUDIO_SPEAKER_SURROUND`
+        const SURROUND = Self::STEREO.bits | Self::FRONT_CENTER.bits | Self::BACK_CENTER.bits;
+        /// `KSAUDIO_SPEAKER_5POINT1`
+        const FIVE_POINT_ONE = Self::QUAD.bits | Self::FRONT_CENTER.bits | Self::LOW_FREQUENCY.bits;
+        /// `KSAUDIO_SPEAKER_5POINT1_SURROUND`
+        const FIVE_POINT_ONE_SURROUND = Self::STEREO.bits
+            | Self::FRONT_CENTER.bits
+            | Self::LOW_FREQUENCY.bits
+            | Self::SIDE_LEFT.bits
+            | Self::SIDE_RIGHT.bits;
+        /// `KSAUDIO_SPEAKER_7POINT1_SURROUND`
+        const SEVEN_POINT_ONE_SURROUND = Self::FIVE_POINT_ONE.bits
+            | Self::SIDE_LEFT.bits
+            | Self::SIDE_RIGHT.bits;
+    }
+}
+
+impl SpeakerPositions {
+    /// The default speaker layout for a number of channels, or an empty mask
+    /// if there is none.
+    pub fn for_channels(channels: u16) -> Self {
+        match channels {
+            1 => Self::MONO,
+            2 => Self::STEREO,
+            4 => Self::QUAD,
+            6 => Self::FIVE_POINT_ONE,
+            8 => Self::SEVEN_POINT_ONE_SURROUND,
+            _ => Self::empty(),
+        }
+    }
+
+    /// The number of channels with a speaker position.
+    pub fn channel_count(&self) -> u32 {
+        (*self - Self::ALL).bits().count_ones()
+    }
+}
+
+/// The encoding of the samples, given by the `SubFormat` of a
+/// `WAVEFORMATEXTENSIBLE` or the `wFormatTag` of a `WAVEFORMATEX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubFormat {
+    /// `KSDATAFORMAT_SUBTYPE_PCM`, integer samples.
+    Pcm,
+    /// `KSDATAFORMAT_SUBTYPE_IEEE_FLOAT`, floating-point samples.
+    IeeeFloat,
+    /// Any other sub-format GUID.
+    Other(Guid),
+}
+
+impl SubFormat {
+    /// Converts a `wFormatTag` into the sub-format GUID defined for it.
+    pub fn from_format_tag(tag: u16) -> Self {
+        match tag {
+            WAVE_FORMAT_PCM => Self::Pcm,
+            WAVE_FORMAT_IEEE_FLOAT => Self::IeeeFloat,
+            _ => Self::Other(Self::tag_guid(tag)),
+        }
+    }
+
+    pub fn from_guid(guid: Guid) -> Self {
+        match Self::guid_tag(&guid) {
+            Some(tag) => Self::from_format_tag(tag),
+            None => Self::Other(guid),
+        }
+    }
+
+    pub fn guid(&self) -> Guid {
+        match self {
+            Self::Pcm => Self::tag_guid(WAVE_FORMAT_PCM),
+            Self::IeeeFloat => Self::tag_guid(WAVE_FORMAT_IEEE_FLOAT),
+            Self::Other(guid) => *guid,
+        }
+    }
+
+    /// The `wFormatTag` corresponding to this sub-format, if it has one.
+    pub fn format_tag(&self) -> Option<u16> {
+        Self::guid_tag(&self.guid())
+    }
+
+    /// `DEFINE_WAVEFORMATEX_GUID`: `{0000xxxx-0000-0010-8000-00AA00389B71}`
+    fn tag_guid(tag: u16) -> Guid {
+        Guid::from_values(
+            tag as u32,
+            0x0000,
+            0x0010,
+            [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71],
+        )
+    }
+
+    fn guid_tag(guid: &Guid) -> Option<u16> {
+        let tag = GuidFields::of(guid).data1.try_into().ok()?;
+        if *guid == Self::tag_guid(tag) {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+}
+
+/// A decoded `WAVEFORMATEX` or `WAVEFORMATEXTENSIBLE`.
+///
+/// The block alignment and average byte rate are not stored; they are always
+/// derived from the other fields.
+///
+/// See also: [`WAVEFORMATEXTENSIBLE`](https://docs.microsoft.com/en-us/windows/win32/api/mmreg/ns-mmreg-waveformatextensible)
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct WaveFormat {
+    /// `nSamplesPerSec`
+    pub sample_rate: u32,
+    /// `nChannels`
+    pub channels: u16,
+    /// `wBitsPerSample`, the size of the container of each sample.
+    pub bits_per_sample: u16,
+    /// `wValidBitsPerSample`, the precision of each sample. Equal to
+    /// `bits_per_sample` for a plain `WAVEFORMATEX`.
+    pub valid_bits_per_sample: u16,
+    /// `dwChannelMask`. For a plain `WAVEFORMATEX`, this is the default layout
+    /// for the number of channels.
+    pub channel_mask: SpeakerPositions,
+    pub sub_format: SubFormat,
+    /// Whether the format is serialized as a `WAVEFORMATEXTENSIBLE`. Formats
+    /// that cannot be represented by a plain `WAVEFORMATEX` are always
+    /// serialized as extensible.
+    pub extensible: bool,
+}
+
+impl WaveFormat {
+    /// An extensible integer PCM format with the default speaker layout.
+    pub fn pcm(sample_rate: u32, channels: u16, bits_per_sample: u16) -> Self {
+        Self::new(SubFormat::Pcm, sample_rate, channels, bits_per_sample)
+    }
+
+    /// An extensible 32-bit floating-point format with the default speaker
+    /// layout.
+    pub fn ieee_float(sample_rate: u32, channels: u16) -> Self {
+        Self::new(SubFormat::IeeeFloat, sample_rate, channels, 32)
+    }
+
+    fn new(sub_format: SubFormat, sample_rate: u32, channels: u16, bits_per_sample: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            bits_per_sample,
+            valid_bits_per_sample: bits_per_sample,
+            channel_mask: SpeakerPositions::for_channels(channels),
+            sub_format,
+            extensible: true,
+        }
+    }
+
+    /// `nBlockAlign`, the size of one frame in bytes.
+    pub fn block_align(&self) -> u16 {
+        self.channels.wrapping_mul(self.bits_per_sample / 8)
+    }
+
+    /// `nAvgBytesPerSec`
+    pub fn avg_bytes_per_sec(&self) -> u32 {
+        self.sample_rate.wrapping_mul(self.block_align() as u32)
+    }
+
+    /// Checks that the fields are consistent with each other and with the
+    /// channel layout.
+    pub fn validate(&self) -> Result<(), WaveFormatError> {
+        if self.channels == 0 {
+            return Err(WaveFormatError::NoChannels);
+        }
+        if self.sample_rate == 0 {
+            return Err(WaveFormatError::NoSampleRate);
+        }
+        let bits_ok = match self.sub_format {
+            SubFormat::IeeeFloat => matches!(self.bits_per_sample, 32 | 64),
+            _ => self.bits_per_sample != 0 && self.bits_per_sample % 8 == 0,
+        };
+        if !bits_ok {
+            return Err(WaveFormatError::BitsPerSample(self.bits_per_sample));
+        }
+        if self.valid_bits_per_sample == 0 || self.valid_bits_per_sample > self.bits_per_sample {
+            return Err(WaveFormatError::ValidBitsPerSample(
+                self.valid_bits_per_sample,
+            ));
+        }
+        if !self.channel_mask.is_empty()
+            && !self.channel_mask.contains(SpeakerPositions::ALL)
+            && self.channel_mask.channel_count() != self.channels as u32
+        {
+            return Err(WaveFormatError::ChannelMask {
+                channels: self.channels,
+                channel_mask: self.channel_mask,
+            });
+        }
+        Ok(())
+    }
+
+    /// Parses a `WAVEFORMATEX`, `WAVEFORMATEXTENSIBLE` or `PCMWAVEFORMAT`.
+    ///
+    /// Any bytes following the structure are ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseWaveFormatError> {
+        let mut reader = Reader(bytes);
+        let format_tag = reader.u16()?;
+        let channels = reader.u16()?;
+        let sample_rate = reader.u32()?;
+        let _avg_bytes_per_sec = reader.u32()?;
+        let _block_align = reader.u16()?;
+        let bits_per_sample = reader.u16()?;
+
+        if format_tag != WAVE_FORMAT_EXTENSIBLE {
+            return Ok(Self {
+                extensible: false,
+                sub_format: SubFormat::from_format_tag(format_tag),
+                ..Self::pcm(sample_rate, channels, bits_per_sample)
+            });
+        }
+
+        if reader.u16()? < EXTENSIBLE_EXTRA_SIZE {
+            return Err(ParseWaveFormatError);
+        }
+        let valid_bits_per_sample = reader.u16()?;
+        let channel_mask = SpeakerPositions::from_bits_truncate(reader.u32()?);
+        let sub_format = SubFormat::from_guid(reader.guid()?);
+        Ok(Self {
+            sample_rate,
+            channels,
+            bits_per_sample,
+            valid_bits_per_sample,
+            channel_mask,
+            sub_format,
+            extensible: true,
+        })
+    }
+
+    /// Serializes the format as a `WAVEFORMATEXTENSIBLE`, or as a
+    /// `WAVEFORMATEX` if [`extensible`](Self::extensible) is false and the
+    /// sub-format has a format tag.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let format_tag = match self.sub_format.format_tag() {
+            Some(tag) if !self.extensible => tag,
+            _ => WAVE_FORMAT_EXTENSIBLE,
+        };
+
+        let mut bytes = Vec::with_capacity(WAVEFORMATEX_SIZE + EXTENSIBLE_EXTRA_SIZE as usize);
+        bytes.extend_from_slice(&format_tag.to_le_bytes());
+        bytes.extend_from_slice(&self.channels.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&self.avg_bytes_per_sec().to_le_bytes());
+        bytes.extend_from_slice(&self.block_align().to_le_bytes());
+        bytes.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+
+        if format_tag == WAVE_FORMAT_EXTENSIBLE {
+            let guid = self.sub_format.guid();
+            let fields = GuidFields::of(&guid);
+            bytes.extend_from_slice(&EXTENSIBLE_EXTRA_SIZE.to_le_bytes());
+            bytes.extend_from_slice(&self.valid_bits_per_sample.to_le_bytes());
+            bytes.extend_from_slice(&self.channel_mask.bits().to_le_bytes());
+            bytes.extend_from_slice(&fields.data1.to_le_bytes());
+            bytes.extend_from_slice(&fields.data2.to_le_bytes());
+            bytes.extend_from_slice(&fields.data3.to_le_bytes());
+            bytes.extend_from_slice(&fields.data4);
+        } else {
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Reads a `VT_BLOB` containing a `WAVEFORMATEX`.
+impl FromProperty for WaveFormat {
+    const EXPECTED: &'static str = "WAVEFORMATEX blob";
+
+    fn from_property(property: Property) -> Result<Self, Property> {
+        match &property {
+            Property::Blob(bytes) | Property::Bytes(bytes) => {
+                Self::from_bytes(bytes).map_err(|_| property)
+            }
+            _ => Err(property),
+        }
+    }
+}
+
+/// Writes a `VT_BLOB` containing a `WAVEFORMATEX`.
+impl IntoProperty for WaveFormat {
+    fn into_property(self) -> Property {
+        Property::Blob(self.to_bytes())
+    }
+}
+
+/// Reads little-endian fields from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ParseWaveFormatError> {
+        if self.0.len() < N {
+            return Err(ParseWaveFormatError);
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16, ParseWaveFormatError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, ParseWaveFormatError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn guid(&mut self) -> Result<Guid, ParseWaveFormatError> {
+        Ok(Guid::from_values(
+            self.u32()?,
+            self.u16()?,
+            self.u16()?,
+            self.take()?,
+        ))
+    }
+}
+
+/// An error returned when a byte buffer does not contain a valid
+/// `WAVEFORMATEX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseWaveFormatError;
+
+impl Display for ParseWaveFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid or truncated WAVEFORMATEX")
+    }
+}
+
+impl Error for ParseWaveFormatError {}
+
+/// A reason why a [`WaveFormat`] is invalid.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum WaveFormatError {
+    /// The format has no channels.
+    NoChannels,
+    /// The sample rate is zero.
+    NoSampleRate,
+    /// The sample container size is not supported for the sub-format.
+    BitsPerSample(u16),
+    /// The sample precision is zero or larger than the container.
+    ValidBitsPerSample(u16),
+    /// The channel mask does not have one speaker position per channel.
+    ChannelMask {
+        channels: u16,
+        channel_mask: SpeakerPositions,
+    },
+    /// The endpoint has a different channel layout; only the sample rate and
+    /// sample type of a device format can be changed.
+    LayoutMismatch {
+        channels: u16,
+        channel_mask: SpeakerPositions,
+    },
+}
+
+impl Display for WaveFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoChannels => f.write_str("format has no channels"),
+            Self::NoSampleRate => f.write_str("format has a sample rate of zero"),
+            Self::BitsPerSample(bits) => write!(f, "unsupported sample size of {} bits", bits),
+            Self::ValidBitsPerSample(bits) => {
+                write!(f, "invalid sample precision of {} bits", bits)
+            }
+            Self::ChannelMask {
+                channels,
+                channel_mask,
+            } => write!(
+                f,
+                "channel mask {:?} does not match {} channels",
+                channel_mask, channels
+            ),
+            Self::LayoutMismatch {
+                channels,
+                channel_mask,
+            } => write!(
+                f,
+                "format does not match the endpoint layout of {} channels ({:?})",
+                channels, channel_mask
+            ),
+        }
+    }
+}
+
+impl Error for WaveFormatError {}
+
+/// An error returned by
+/// [`Device::set_device_format`](crate::Device::set_device_format).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SetFormatError {
+    /// The format failed validation and was not written.
+    Invalid(WaveFormatError),
+    /// The property store could not be opened for writing. Changing the
+    /// device format requires administrator privileges.
+    AccessDenied(crate::Error),
+    /// The audio engine rejected the format.
+    Rejected(crate::Error),
+    /// The current format could not be read.
+    Property(PropertyError),
+    /// Any other error returned by the property store.
+    Windows(crate::Error),
+}
+
+impl Display for SetFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "invalid device format: {}", e),
+            Self::AccessDenied(e) => write!(f, "access denied: {}", e),
+            Self::Rejected(e) => write!(f, "device format rejected: {}", e),
+            Self::Property(e) => Display::fmt(e, f),
+            Self::Windows(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for SetFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Invalid(e) => Some(e),
+            Self::AccessDenied(e) | Self::Rejected(e) | Self::Windows(e) => Some(e),
+            Self::Property(e) => Some(e),
+        }
+    }
+}
+
+impl From<WaveFormatError> for SetFormatError {
+    fn from(e: WaveFormatError) -> Self {
+        Self::Invalid(e)
+    }
+}
+
+impl From<crate::Error> for SetFormatError {
+    fn from(e: crate::Error) -> Self {
+        match e.kind() {
+            ErrorKind::AccessDenied => Self::AccessDenied(e),
+            ErrorKind::InvalidArgument | ErrorKind::UnsupportedFormat => Self::Rejected(e),
+            _ => Self::Windows(e),
+        }
+    }
+}
+
+impl From<PropertyError> for SetFormatError {
+    fn from(e: PropertyError) -> Self {
+        match e {
+            PropertyError::Windows(e) => e.into(),
+            e => Self::Property(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{
+        AUDCLNT_E_UNSUPPORTED_FORMAT, E_ACCESSDENIED, E_INVALIDARG, E_NOTFOUND, STG_E_ACCESSDENIED,
+    };
+
+    /// `KSDATAFORMAT_SUBTYPE_DOLBY_AC3_SPDIF`, which has a format tag.
+    const AC3: u16 = 0x0092;
+
+    fn unknown_guid() -> Guid {
+        Guid::from_values(
+            0x00000092,
+            0x0cea,
+            0x0010,
+            [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71],
+        )
+    }
+
+    #[test]
+    fn waveformatex() {
+        let format = WaveFormat {
+            extensible: false,
+            ..WaveFormat::pcm(44100, 2, 16)
+        };
+        let bytes = format.to_bytes();
+        assert_eq!(
+            bytes,
+            [
+                0x01, 0x00, // wFormatTag
+                0x02, 0x00, // nChannels
+                0x44, 0xac, 0x00, 0x00, // nSamplesPerSec
+                0x10, 0xb1, 0x02, 0x00, // nAvgBytesPerSec
+                0x04, 0x00, // nBlockAlign
+                0x10, 0x00, // wBitsPerSample
+                0x00, 0x00, // cbSize
+            ]
+        );
+        assert_eq!(WaveFormat::from_bytes(&bytes), Ok(format.clone()));
+
+        // A `PCMWAVEFORMAT` has no `cbSize`, and trailing bytes are ignored.
+        assert_eq!(WaveFormat::from_bytes(&bytes[..16]), Ok(format.clone()));
+        let mut padded = bytes;
+        padded.extend_from_slice(&[0xff; 4]);
+        assert_eq!(WaveFormat::from_bytes(&padded), Ok(format));
+
+        let format = WaveFormat {
+            extensible: false,
+            ..WaveFormat::ieee_float(48000, 1)
+        };
+        let bytes = format.to_bytes();
+        assert_eq!(bytes.len(), WAVEFORMATEX_SIZE);
+        assert_eq!(bytes[..2], WAVE_FORMAT_IEEE_FLOAT.to_le_bytes());
+        assert_eq!(WaveFormat::from_bytes(&bytes), Ok(format));
+    }
+
+    #[test]
+    fn waveformatextensible() {
+        let format = WaveFormat {
+            valid_bits_per_sample: 20,
+            ..WaveFormat::pcm(48000, 6, 24)
+        };
+        let bytes = format.to_bytes();
+        assert_eq!(
+            bytes.len(),
+            WAVEFORMATEX_SIZE + EXTENSIBLE_EXTRA_SIZE as usize
+        );
+        assert_eq!(bytes[..2], WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        assert_eq!(bytes[8..12], 864000u32.to_le_bytes());
+        assert_eq!(bytes[12..14], 18u16.to_le_bytes());
+        assert_eq!(bytes[16..18], EXTENSIBLE_EXTRA_SIZE.to_le_bytes());
+        assert_eq!(bytes[18..20], 20u16.to_le_bytes());
+        assert_eq!(bytes[20..24], 0x3fu32.to_le_bytes());
+        assert_eq!(
+            bytes[24..],
+            [
+                0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38,
+                0x9b, 0x71,
+            ]
+        );
+        assert_eq!(WaveFormat::from_bytes(&bytes), Ok(format));
+
+        let format = WaveFormat {
+            channel_mask: SpeakerPositions::FRONT_LEFT | SpeakerPositions::BACK_RIGHT,
+            ..WaveFormat::ieee_float(96000, 2)
+        };
+        assert_eq!(WaveFormat::from_bytes(&format.to_bytes()), Ok(format));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = WaveFormat::pcm(44100, 2, 16).to_bytes();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                WaveFormat::from_bytes(&bytes[..len]),
+                Err(ParseWaveFormatError),
+                "{} bytes",
+                len
+            );
+        }
+
+        let bytes = WaveFormat {
+            extensible: false,
+            ..WaveFormat::pcm(44100, 2, 16)
+        }
+        .to_bytes();
+        for len in 0..16 {
+            assert_eq!(
+                WaveFormat::from_bytes(&bytes[..len]),
+                Err(ParseWaveFormatError),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn extensible_too_small() {
+        let mut bytes = WaveFormat::pcm(44100, 2, 16).to_bytes();
+        for size in [0, 1, EXTENSIBLE_EXTRA_SIZE - 1] {
+            bytes[16..18].copy_from_slice(&size.to_le_bytes());
+            assert_eq!(WaveFormat::from_bytes(&bytes), Err(ParseWaveFormatError));
+        }
+
+        // Extra bytes beyond the extensible fields are allowed.
+        bytes[16..18].copy_from_slice(&(EXTENSIBLE_EXTRA_SIZE + 2).to_le_bytes());
+        bytes.extend_from_slice(&[0; 2]);
+        assert_eq!(
+            WaveFormat::from_bytes(&bytes),
+            Ok(WaveFormat::pcm(44100, 2, 16))
+        );
+    }
+
+    #[test]
+    fn sub_format() {
+        let pcm = SubFormat::tag_guid(WAVE_FORMAT_PCM);
+        let float = SubFormat::tag_guid(WAVE_FORMAT_IEEE_FLOAT);
+        let ac3 = SubFormat::tag_guid(AC3);
+
+        assert_eq!(SubFormat::from_guid(pcm), SubFormat::Pcm);
+        assert_eq!(SubFormat::from_guid(float), SubFormat::IeeeFloat);
+        assert_eq!(SubFormat::from_guid(ac3), SubFormat::Other(ac3));
+        assert_eq!(SubFormat::from_format_tag(WAVE_FORMAT_PCM), SubFormat::Pcm);
+        assert_eq!(
+            SubFormat::from_format_tag(WAVE_FORMAT_IEEE_FLOAT),
+            SubFormat::IeeeFloat
+        );
+        assert_eq!(SubFormat::from_format_tag(AC3), SubFormat::Other(ac3));
+
+        assert_eq!(SubFormat::Pcm.guid(), pcm);
+        assert_eq!(SubFormat::IeeeFloat.guid(), float);
+        assert_eq!(SubFormat::Pcm.format_tag(), Some(WAVE_FORMAT_PCM));
+        assert_eq!(
+            SubFormat::IeeeFloat.format_tag(),
+            Some(WAVE_FORMAT_IEEE_FLOAT)
+        );
+        assert_eq!(SubFormat::Other(ac3).format_tag(), Some(AC3));
+
+        let unknown = SubFormat::from_guid(unknown_guid());
+        assert_eq!(unknown, SubFormat::Other(unknown_guid()));
+        assert_eq!(unknown.guid(), unknown_guid());
+        assert_eq!(unknown.format_tag(), None);
+    }
+
+    #[test]
+    fn sub_format_bytes() {
+        // A sub-format with a tag can be written as a plain `WAVEFORMATEX`.
+        let format = WaveFormat {
+            sub_format: SubFormat::Other(SubFormat::tag_guid(AC3)),
+            extensible: false,
+            ..WaveFormat::pcm(48000, 2, 16)
+        };
+        let bytes = format.to_bytes();
+        assert_eq!(bytes[..2], AC3.to_le_bytes());
+        assert_eq!(WaveFormat::from_bytes(&bytes), Ok(format));
+
+        // One without is always written as a `WAVEFORMATEXTENSIBLE`.
+        let format = WaveFormat {
+            sub_format: SubFormat::Other(unknown_guid()),
+            extensible: false,
+            ..WaveFormat::pcm(48000, 2, 16)
+        };
+        let bytes = format.to_bytes();
+        assert_eq!(bytes[..2], WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        assert_eq!(
+            WaveFormat::from_bytes(&bytes),
+            Ok(WaveFormat {
+                extensible: true,
+                ..format
+            })
+        );
+    }
+
+    #[test]
+    fn derived_fields() {
+        let format = WaveFormat::pcm(44100, 2, 24);
+        assert_eq!(format.block_align(), 6);
+        assert_eq!(format.avg_bytes_per_sec(), 264600);
+        assert_eq!(WaveFormat::ieee_float(48000, 8).block_align(), 32);
+
+        // The stored values are ignored when parsing, and derived again when
+        // serializing.
+        let mut bytes = format.to_bytes();
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        bytes[12..14].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(WaveFormat::from_bytes(&bytes), Ok(format.clone()));
+        assert_eq!(
+            WaveFormat::from_bytes(&bytes).unwrap().to_bytes(),
+            format.to_bytes()
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(WaveFormat::pcm(44100, 2, 16).validate(), Ok(()));
+        assert_eq!(WaveFormat::pcm(8000, 1, 8).validate(), Ok(()));
+        assert_eq!(WaveFormat::ieee_float(192000, 8).validate(), Ok(()));
+        assert_eq!(
+            WaveFormat {
+                bits_per_sample: 64,
+                valid_bits_per_sample: 64,
+                ..WaveFormat::ieee_float(48000, 2)
+            }
+            .validate(),
+            Ok(())
+        );
+
+        assert_eq!(
+            WaveFormat::pcm(44100, 0, 16).validate(),
+            Err(WaveFormatError::NoChannels)
+        );
+        assert_eq!(
+            WaveFormat::pcm(0, 2, 16).validate(),
+            Err(WaveFormatError::NoSampleRate)
+        );
+    }
+
+    #[test]
+    fn validate_sample_size() {
+        // Samples that don't fill whole bytes would give a fractional block
+        // alignment.
+        for bits in [0, 4, 12, 20] {
+            assert_eq!(
+                WaveFormat::pcm(44100, 2, bits).validate(),
+                Err(WaveFormatError::BitsPerSample(bits))
+            );
+        }
+        for bits in [16, 24] {
+            assert_eq!(
+                WaveFormat {
+                    bits_per_sample: bits,
+                    valid_bits_per_sample: bits,
+                    ..WaveFormat::ieee_float(48000, 2)
+                }
+                .validate(),
+                Err(WaveFormatError::BitsPerSample(bits))
+            );
+        }
+
+        for valid_bits in [0, 25] {
+            assert_eq!(
+                WaveFormat {
+                    valid_bits_per_sample: valid_bits,
+                    ..WaveFormat::pcm(44100, 2, 24)
+                }
+                .validate(),
+                Err(WaveFormatError::ValidBitsPerSample(valid_bits))
+            );
+        }
+        assert_eq!(
+            WaveFormat {
+                valid_bits_per_sample: 20,
+                ..WaveFormat::pcm(44100, 2, 24)
+            }
+            .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn validate_channel_mask() {
+        let format = WaveFormat {
+            channel_mask: SpeakerPositions::STEREO,
+            ..WaveFormat::pcm(48000, 6, 16)
+        };
+        assert_eq!(
+            format.validate(),
+            Err(WaveFormatError::ChannelMask {
+                channels: 6,
+                channel_mask: SpeakerPositions::STEREO,
+            })
+        );
+        assert_eq!(
+            WaveFormat {
+                channel_mask: SpeakerPositions::FIVE_POINT_ONE,
+                ..WaveFormat::pcm(48000, 2, 16)
+            }
+            .validate(),
+            Err(WaveFormatError::ChannelMask {
+                channels: 2,
+                channel_mask: SpeakerPositions::FIVE_POINT_ONE,
+            })
+        );
+
+        // An empty mask leaves the layout unspecified, and `ALL` allows any
+        // number of channels.
+        for channel_mask in [SpeakerPositions::empty(), SpeakerPositions::ALL] {
+            assert_eq!(
+                WaveFormat {
+                    channel_mask,
+                    ..WaveFormat::pcm(48000, 6, 16)
+                }
+                .validate(),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn set_format_error() {
+        let error = |code| SetFormatError::from(crate::Error::from_code("test", code));
+        assert!(matches!(
+            error(E_ACCESSDENIED),
+            SetFormatError::AccessDenied(_)
+        ));
+        assert!(matches!(
+            error(STG_E_ACCESSDENIED),
+            SetFormatError::AccessDenied(_)
+        ));
+        assert!(matches!(error(E_INVALIDARG), SetFormatError::Rejected(_)));
+        assert!(matches!(
+            error(AUDCLNT_E_UNSUPPORTED_FORMAT),
+            SetFormatError::Rejected(_)
+        ));
+        assert!(matches!(error(E_NOTFOUND), SetFormatError::Windows(_)));
+
+        let e = error(AUDCLNT_E_UNSUPPORTED_FORMAT);
+        let source = e.source().unwrap().downcast_ref::<crate::Error>().unwrap();
+        assert_eq!(source.kind(), ErrorKind::UnsupportedFormat);
+
+        // Errors from the property store are mapped the same way.
+        let e = SetFormatError::from(PropertyError::from(crate::Error::from_code(
+            "IPropertyStore::SetValue",
+            STG_E_ACCESSDENIED,
+        )));
+        assert!(matches!(e, SetFormatError::AccessDenied(_)));
+        let e = SetFormatError::from(PropertyError::TypeMismatch {
+            key: crate::pkeys::AUDIO_ENGINE_DEVICE_FORMAT.key(),
+            expected: WaveFormat::EXPECTED,
+            found: Property::U32(0),
+        });
+        assert!(matches!(e, SetFormatError::Property(_)));
+
+        let e = SetFormatError::from(WaveFormatError::NoChannels);
+        assert!(matches!(
+            e,
+            SetFormatError::Invalid(WaveFormatError::NoChannels)
+        ));
+        assert_eq!(
+            e.to_string(),
+            "invalid device format: format has no channels"
+        );
+    }
+}